@@ -0,0 +1,271 @@
+use bevy::prelude::*;
+use bevy_bobs::physics_2d::RigidBody;
+use bevy_prototype_lyon::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    ai::movement::OrcaNeighbouring,
+    orca::{Orca, PodId, PodPool},
+    sim::{Simulation, SystemRng},
+    vessel::NoiseField,
+};
+
+/// number of distinct call types that can be generated
+const CALL_TYPE_COUNT: CallType = 1000;
+/// calls in a clan's shared repertoire
+const CLAN_CALLS: usize = 8;
+/// calls only a single pod makes
+const POD_CALLS: usize = 2;
+/// chance that a pod keeps each of its clan's calls
+const CLAN_CALL_RETENTION: f64 = 0.8;
+
+/// calls per second made by an orca that can see its pod
+const CALL_RATE: f32 = 0.02;
+/// calls per second made by an orca that has lost sight of its pod
+const CONTACT_CALL_RATE: f32 = 0.5;
+/// source level of a call
+const CALL_LEVEL: f32 = 20.;
+/// distance at which a call is heard at half its source level
+const CALL_REFERENCE_RANGE: f32 = 100.;
+/// received level below which a call can not be heard, raised further by vessel noise
+const CALL_THRESHOLD: f32 = 1.;
+/// seconds a heard call is remembered for
+const CALL_MEMORY: f32 = 10.;
+/// weight of the force steering a separated orca towards calls in its pod's dialect
+const REGROUP: f32 = 2.;
+
+const CALL_RING_LIFETIME: f32 = 1.;
+const CALL_RING_RADIUS: f32 = 30.;
+
+pub type CallType = u32;
+
+/// call repertoire of a pod
+#[derive(Clone, Default)]
+pub struct Dialect {
+    pub calls: Vec<CallType>,
+}
+
+impl Dialect {
+    /// random repertoire shared by all pods of a clan
+    pub fn clan(rng: &mut impl Rng) -> Self {
+        Self {
+            calls: (0..CLAN_CALLS)
+                .map(|_| rng.gen_range(0..CALL_TYPE_COUNT))
+                .collect(),
+        }
+    }
+
+    /// dialect of a pod within a clan, keeping most of the clan calls and adding its own
+    pub fn pod(clan: &Dialect, rng: &mut impl Rng) -> Self {
+        let mut calls: Vec<CallType> = clan
+            .calls
+            .iter()
+            .copied()
+            .filter(|_| rng.gen_bool(CLAN_CALL_RETENTION))
+            .collect();
+        calls.extend((0..POD_CALLS).map(|_| rng.gen_range(0..CALL_TYPE_COUNT)));
+        Self { calls }
+    }
+
+    /// dialect passed down to a pod splitting off from this one, which drifts by a single call
+    pub fn inherit(&self, rng: &mut impl Rng) -> Self {
+        let mut calls = self.calls.clone();
+        if calls.len() > POD_CALLS {
+            let i = rng.gen_range(0..calls.len());
+            calls.remove(i);
+        }
+        calls.push(rng.gen_range(0..CALL_TYPE_COUNT));
+        Self { calls }
+    }
+
+    pub fn knows(&self, call: CallType) -> bool {
+        self.calls.contains(&call)
+    }
+
+    /// number of calls shared with another dialect
+    pub fn shared(&self, other: &Dialect) -> usize {
+        self.calls.iter().filter(|call| other.knows(**call)).count()
+    }
+}
+
+/// name of a call type, in the style of the discrete calls of the southern residents
+pub fn call_name(call: CallType) -> String {
+    format!("S{}", call)
+}
+
+pub struct CallEvent {
+    pub caller: Entity,
+    pub pod_id: PodId,
+    pub call: CallType,
+    pub position: Vec2,
+}
+
+pub struct HeardCall {
+    pub caller: Entity,
+    pub call: CallType,
+    pub position: Vec2,
+    /// simulation time the call was heard
+    pub time: f32,
+}
+
+/// most recent call heard from a member of the listener's own pod
+#[derive(Component, Default)]
+pub struct CallMemory {
+    pub heard: Option<HeardCall>,
+}
+
+#[derive(Component)]
+pub struct CallRing {
+    age: f32,
+}
+
+pub struct AcousticPlugin;
+
+impl Plugin for AcousticPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CallEvent>()
+            .add_system(emit_calls)
+            .add_system(receive_calls)
+            .add_system(regroup)
+            .add_system(spawn_call_rings)
+            .add_system(call_rings);
+    }
+}
+
+/// received level of a call made `dist` away
+pub fn call_level(dist: f32) -> f32 {
+    let d = dist / CALL_REFERENCE_RANGE;
+    CALL_LEVEL / (1. + d * d)
+}
+
+fn emit_calls(
+    time: Res<Time>,
+    pod_pool: Res<PodPool>,
+    query: Query<(Entity, &Orca, &Transform, &OrcaNeighbouring)>,
+    mut writer: EventWriter<CallEvent>,
+    mut rng: Local<SystemRng>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, orca, trans, neighbouring) in query.iter() {
+        let pod_id = match orca.pod_id {
+            Some(pod_id) => pod_id,
+            None => continue,
+        };
+        let pod = match pod_pool.get(&pod_id) {
+            Some(pod) => pod,
+            None => continue,
+        };
+
+        // orcas that have lost their pod make contact calls much more often
+        let rate = if neighbouring.pod_members.is_empty() {
+            CONTACT_CALL_RATE
+        } else {
+            CALL_RATE
+        };
+        if !rng.gen_bool((rate * dt).clamp(0., 1.) as f64) {
+            continue;
+        }
+
+        if let Some(call) = pod.dialect.calls.choose(&mut rng.0) {
+            writer.send(CallEvent {
+                caller: entity,
+                pod_id,
+                call: *call,
+                position: trans.translation.truncate(),
+            });
+        }
+    }
+}
+
+fn receive_calls(
+    sim: Res<Simulation>,
+    pod_pool: Res<PodPool>,
+    noise_field: Res<NoiseField>,
+    mut events: EventReader<CallEvent>,
+    mut query: Query<(Entity, &Orca, &Transform, &mut CallMemory)>,
+) {
+    for event in events.iter() {
+        for (entity, orca, trans, mut memory) in query.iter_mut() {
+            if entity == event.caller {
+                continue;
+            }
+
+            // listeners can not tell who called, only whether the call is in their own pod's
+            // dialect, so calls shared within a clan draw in related pods as well
+            let pod = match orca.pod_id.and_then(|pod_id| pod_pool.get(&pod_id)) {
+                Some(pod) => pod,
+                None => continue,
+            };
+            if !pod.dialect.knows(event.call) {
+                continue;
+            }
+
+            let pos = trans.translation.truncate();
+            let level = call_level(pos.distance(event.position));
+            if level < CALL_THRESHOLD + noise_field.level_at(pos) {
+                continue;
+            }
+
+            memory.heard = Some(HeardCall {
+                caller: event.caller,
+                call: event.call,
+                position: event.position,
+                time: sim.time,
+            });
+        }
+    }
+}
+
+/// orcas that can not see any of their pod swim towards the last call they recognised
+fn regroup(
+    time: Res<Time>,
+    sim: Res<Simulation>,
+    mut query: Query<(&Transform, &OrcaNeighbouring, &CallMemory, &mut RigidBody)>,
+) {
+    for (trans, neighbouring, memory, mut rb) in query.iter_mut() {
+        if !neighbouring.pod_members.is_empty() {
+            continue;
+        }
+        let heard = match &memory.heard {
+            Some(heard) if sim.time - heard.time < CALL_MEMORY => heard,
+            _ => continue,
+        };
+
+        let dir = (heard.position - trans.translation.truncate()).normalize_or_zero();
+        rb.force += dir * REGROUP * 1000. * time.delta_seconds();
+    }
+}
+
+fn spawn_call_rings(mut cmd: Commands, pod_pool: Res<PodPool>, mut events: EventReader<CallEvent>) {
+    for event in events.iter() {
+        let color = pod_pool
+            .get(&event.pod_id)
+            .map(|pod| pod.color)
+            .unwrap_or(Color::WHITE);
+
+        cmd.spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Circle {
+                radius: 1.,
+                ..default()
+            },
+            DrawMode::Stroke(StrokeMode::new(color, 0.05)),
+            Transform::from_translation(event.position.extend(1.)),
+        ))
+        .insert(CallRing { age: 0. });
+    }
+}
+
+fn call_rings(
+    mut cmd: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut CallRing, &mut Transform)>,
+) {
+    for (entity, mut ring, mut trans) in query.iter_mut() {
+        ring.age += time.delta_seconds();
+        if ring.age >= CALL_RING_LIFETIME {
+            cmd.entity(entity).despawn_recursive();
+            continue;
+        }
+        trans.scale = Vec3::splat((CALL_RING_RADIUS * ring.age / CALL_RING_LIFETIME).max(1.));
+    }
+}
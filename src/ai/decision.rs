@@ -0,0 +1,396 @@
+use std::{collections::VecDeque, fs, path::PathBuf};
+
+use bevy::prelude::*;
+use big_brain::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::hunger::{Hungry, Hunt};
+use crate::{
+    orca::{Orca, Type},
+    sim::Simulation,
+};
+
+/// transitions kept per orca
+const LOG_LENGTH: usize = 100;
+
+/// how raw input to a scorer, between 0 and 1, is turned into a score
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Curve {
+    Linear {
+        slope: f32,
+        intercept: f32,
+    },
+    Power {
+        exponent: f32,
+    },
+    /// 0 below the threshold and 1 from it on
+    Step {
+        threshold: f32,
+    },
+    Logistic {
+        midpoint: f32,
+        steepness: f32,
+    },
+}
+
+impl Default for Curve {
+    fn default() -> Self {
+        Curve::Linear {
+            slope: 1.,
+            intercept: 0.,
+        }
+    }
+}
+
+impl Curve {
+    pub fn eval(&self, x: f32) -> f32 {
+        let y = match *self {
+            Curve::Linear { slope, intercept } => slope * x + intercept,
+            Curve::Power { exponent } => x.max(0.).powf(exponent),
+            Curve::Step { threshold } => {
+                if x >= threshold {
+                    1.
+                } else {
+                    0.
+                }
+            },
+            Curve::Logistic {
+                midpoint,
+                steepness,
+            } => 1. / (1. + (-steepness * (x - midpoint)).exp()),
+        };
+        y.clamp(0., 1.)
+    }
+}
+
+/// how a thinker chooses between its actions
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PickerDef {
+    /// first action in order whose score reaches the threshold
+    FirstToScore { threshold: f32 },
+    /// action with the highest score, if any is above zero
+    Highest,
+}
+
+impl PickerDef {
+    pub fn describe(&self) -> String {
+        match self {
+            PickerDef::FirstToScore { threshold } => {
+                format!("first to score, threshold {:.2}", threshold)
+            },
+            PickerDef::Highest => String::from("highest"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScorerDef {
+    /// hunger passed through a curve
+    Hungry {
+        #[serde(default)]
+        curve: Curve,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionDef {
+    Hunt,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct RuleDef {
+    pub scorer: ScorerDef,
+    pub action: ActionDef,
+}
+
+/// decision logic of an orca, built into a `Thinker` when it is spawned
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub struct ThinkerDef {
+    pub picker: PickerDef,
+    /// scorer and action pairs, in order of priority
+    pub rules: Vec<RuleDef>,
+}
+
+impl Default for ThinkerDef {
+    fn default() -> Self {
+        Self {
+            picker: PickerDef::FirstToScore { threshold: 0.5 },
+            rules: vec![RuleDef {
+                scorer: ScorerDef::Hungry {
+                    curve: Curve::default(),
+                },
+                action: ActionDef::Hunt,
+            }],
+        }
+    }
+}
+
+impl ThinkerDef {
+    pub fn build(&self) -> ThinkerBuilder {
+        let mut builder = Thinker::build();
+        builder = match self.picker {
+            PickerDef::FirstToScore { threshold } => builder.picker(FirstToScore { threshold }),
+            PickerDef::Highest => builder.picker(Highest),
+        };
+        for rule in self.rules.iter() {
+            let ScorerDef::Hungry { curve } = rule.scorer;
+            builder = match rule.action {
+                ActionDef::Hunt => builder.when(Hungry { curve }, Hunt),
+            };
+        }
+        builder
+    }
+}
+
+/// thinker definitions for each ecotype, stored as json
+///
+/// An ecotype left out of the file keeps the default thinker.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Thinkers {
+    pub resident: ThinkerDef,
+    pub transient: ThinkerDef,
+}
+
+impl Thinkers {
+    pub fn load(path: &PathBuf) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::parse(&contents)
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let thinkers: Self = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+        for def in [&thinkers.resident, &thinkers.transient] {
+            if def.rules.is_empty() {
+                return Err(String::from("every thinker needs at least one rule"));
+            }
+            if let PickerDef::FirstToScore { threshold } = def.picker {
+                if !(0. ..=1.).contains(&threshold) {
+                    return Err(String::from("picker threshold must be between 0 and 1"));
+                }
+            }
+        }
+        Ok(thinkers)
+    }
+
+    pub fn of(&self, orca_type: Type) -> &ThinkerDef {
+        match orca_type {
+            Type::Resident => &self.resident,
+            Type::Transient => &self.transient,
+        }
+    }
+}
+
+/// replace the thinker definitions used for orcas spawned from now on
+pub struct LoadThinkersEvent(pub PathBuf);
+
+/// change in the state of an action taken by an orca
+pub struct Transition {
+    /// simulation time in seconds
+    pub time: f32,
+    pub action: String,
+    pub state: ActionState,
+}
+
+/// recent action transitions of an orca, newest last
+#[derive(Component, Default)]
+pub struct DecisionLog {
+    pub transitions: VecDeque<Transition>,
+}
+
+pub struct DecisionPlugin;
+
+impl Plugin for DecisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Thinkers::default())
+            .add_event::<LoadThinkersEvent>()
+            .add_system_to_stage(CoreStage::PreUpdate, load_thinkers)
+            .add_system_to_stage(CoreStage::PostUpdate, attach_thinkers);
+
+        // names are in place by the time transitions are logged
+        app.add_system_to_stage(CoreStage::PostUpdate, name_scorers)
+            .add_system_to_stage(CoreStage::PostUpdate, name_actions)
+            .add_system_to_stage(CoreStage::Last, log_transitions);
+    }
+}
+
+fn load_thinkers(mut events: EventReader<LoadThinkersEvent>, mut thinkers: ResMut<Thinkers>) {
+    for LoadThinkersEvent(path) in events.iter() {
+        match Thinkers::load(path) {
+            Ok(loaded) => {
+                info!("loaded thinkers from {}", path.display());
+                *thinkers = loaded;
+            },
+            Err(e) => warn!("failed to load thinkers {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// give newly spawned orcas the thinker of their ecotype, unless they were spawned with their own
+fn attach_thinkers(
+    mut cmd: Commands,
+    thinkers: Res<Thinkers>,
+    query: Query<(Entity, &Orca, Option<&ThinkerDef>), Added<Orca>>,
+) {
+    for (entity, orca, own) in query.iter() {
+        let def = own.unwrap_or_else(|| thinkers.of(orca.orca_type));
+        cmd.entity(entity)
+            .insert(def.build())
+            .insert(def.clone())
+            .insert(DecisionLog::default());
+    }
+}
+
+/// scorer and action entities are spawned by big-brain, name them so they can be told apart
+fn name_scorers(mut cmd: Commands, query: Query<Entity, (Added<Hungry>, Without<Name>)>) {
+    for entity in query.iter() {
+        cmd.entity(entity).insert(Name::new("Hungry"));
+    }
+}
+
+fn name_actions(mut cmd: Commands, query: Query<Entity, (Added<Hunt>, Without<Name>)>) {
+    for entity in query.iter() {
+        cmd.entity(entity).insert(Name::new("Hunt"));
+    }
+}
+
+fn log_transitions(
+    sim: Res<Simulation>,
+    query: Query<(&Actor, &ActionState, Option<&Name>), Changed<ActionState>>,
+    mut log_query: Query<&mut DecisionLog>,
+) {
+    for (Actor(actor), state, name) in query.iter() {
+        let mut log = match log_query.get_mut(*actor) {
+            Ok(log) => log,
+            Err(_) => continue,
+        };
+        let action = name
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| String::from("unnamed"));
+
+        // the same state can be written again without really changing
+        if let Some(last) = log.transitions.back() {
+            if last.action == action && last.state == *state {
+                continue;
+            }
+        }
+        log.transitions.push_back(Transition {
+            time: sim.time,
+            action,
+            state: state.clone(),
+        });
+        if log.transitions.len() > LOG_LENGTH {
+            log.transitions.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{} is not {}", a, b);
+    }
+
+    #[test]
+    fn linear_curve_is_clamped() {
+        let curve = Curve::default();
+        assert_near(curve.eval(0.), 0.);
+        assert_near(curve.eval(0.5), 0.5);
+        assert_near(curve.eval(1.), 1.);
+
+        let steep = Curve::Linear {
+            slope: 2.,
+            intercept: -0.5,
+        };
+        assert_near(steep.eval(0.), 0.);
+        assert_near(steep.eval(0.5), 0.5);
+        assert_near(steep.eval(1.), 1.);
+    }
+
+    #[test]
+    fn power_curve() {
+        let curve = Curve::Power { exponent: 2. };
+        assert_near(curve.eval(0.), 0.);
+        assert_near(curve.eval(0.5), 0.25);
+        assert_near(curve.eval(1.), 1.);
+        // negative input is not raised to a fractional power
+        assert_near(Curve::Power { exponent: 0.5 }.eval(-1.), 0.);
+    }
+
+    #[test]
+    fn step_curve_includes_threshold() {
+        let curve = Curve::Step { threshold: 0.5 };
+        assert_near(curve.eval(0.), 0.);
+        assert_near(curve.eval(0.49), 0.);
+        assert_near(curve.eval(0.5), 1.);
+        assert_near(curve.eval(1.), 1.);
+    }
+
+    #[test]
+    fn logistic_curve_is_half_at_midpoint() {
+        let curve = Curve::Logistic {
+            midpoint: 0.5,
+            steepness: 10.,
+        };
+        assert!(curve.eval(0.) < 0.01);
+        assert_near(curve.eval(0.5), 0.5);
+        assert!(curve.eval(1.) > 0.99);
+        assert!(curve.eval(1.) <= 1.);
+    }
+
+    #[test]
+    fn empty_rules_are_rejected() {
+        let json = r#"{ "resident": { "picker": { "type": "highest" }, "rules": [] } }"#;
+        assert!(Thinkers::parse(json).is_err());
+    }
+
+    #[test]
+    fn threshold_above_one_is_rejected() {
+        let json = r#"{
+            "transient": {
+                "picker": { "type": "first_to_score", "threshold": 1.5 },
+                "rules": [{ "scorer": { "type": "hungry" }, "action": "hunt" }]
+            }
+        }"#;
+        assert!(Thinkers::parse(json).is_err());
+    }
+
+    #[test]
+    fn missing_ecotype_keeps_default() {
+        let json = r#"{ "transient": { "picker": { "type": "highest" }, "rules": [{ "scorer": { "type": "hungry" }, "action": "hunt" }] } }"#;
+        let thinkers = Thinkers::parse(json).unwrap();
+        assert_eq!(thinkers.resident.picker, ThinkerDef::default().picker);
+        assert_eq!(thinkers.transient.picker, PickerDef::Highest);
+    }
+
+    #[test]
+    fn readme_example_parses() {
+        let readme = include_str!("../../README.md");
+        let start = readme.find("```json").unwrap() + "```json".len();
+        let end = start + readme[start..].find("```").unwrap();
+        let thinkers = Thinkers::parse(&readme[start..end]).unwrap();
+
+        assert_eq!(
+            thinkers.resident.picker,
+            PickerDef::FirstToScore { threshold: 0.5 }
+        );
+        let ScorerDef::Hungry { curve } = thinkers.resident.rules[0].scorer;
+        assert_eq!(
+            curve,
+            Curve::Logistic {
+                midpoint: 0.6,
+                steepness: 10.
+            }
+        );
+
+        assert_eq!(thinkers.transient.picker, PickerDef::Highest);
+        let ScorerDef::Hungry { curve } = thinkers.transient.rules[0].scorer;
+        assert_eq!(curve, Curve::Power { exponent: 2. });
+    }
+}
@@ -0,0 +1,150 @@
+use std::ops::Range;
+
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::DrawMode;
+
+use super::movement::Movement;
+use crate::{map::Bathymetry, sim::SystemRng};
+
+/// depth above which a boid counts as being at the surface
+const SURFACE_DEPTH: f32 = 1.;
+/// how many times faster breath is recovered at the surface than it is used up underwater
+const BREATH_RECOVERY_RATE: f32 = 4.;
+/// share of the breath hold used up on a dive made while not hunting
+const ROUTINE_DIVE_FRACTION: f32 = 0.5;
+/// depths in metres of dives made while not hunting
+const ROUTINE_DIVE_DEPTH: Range<f32> = 10.0..60.0;
+/// depth at which boids are drawn in the darkest shade
+const MAX_SHADE_DEPTH: f32 = 100.;
+const DEEP_COLOR: Color = Color::rgb(0.05, 0.1, 0.3);
+
+/// breath holding for air breathing boids
+#[derive(Component)]
+pub struct Breath {
+    /// seconds spent underwater since breath was last fully recovered
+    pub held: f32,
+    /// longest time in seconds that can be spent underwater
+    pub max_hold: f32,
+    /// forced back to the surface to recover breath
+    pub surfacing: bool,
+}
+
+impl Breath {
+    pub fn new(max_hold: f32) -> Self {
+        Self {
+            held: 0.,
+            max_hold,
+            surfacing: false,
+        }
+    }
+}
+
+/// shape that gets darker as its parent boid dives deeper, holds the colour at the surface
+#[derive(Component)]
+pub struct DepthShaded(pub Color);
+
+pub struct DivingPlugin;
+
+impl Plugin for DivingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(breath_system)
+            .add_system(dive_cycle)
+            .add_system(depth_system)
+            .add_system(depth_shading);
+    }
+}
+
+/// position of a boid including its depth, with the z axis pointing up
+///
+/// A world unit is taken to be a metre, so depth and horizontal distances share a scale.
+pub fn depth_position(trans: &Transform, movement: &Movement) -> Vec3 {
+    trans.translation.truncate().extend(-movement.depth)
+}
+
+fn breath_system(time: Res<Time>, mut query: Query<(&Movement, &mut Breath)>) {
+    let dt = time.delta_seconds();
+    for (movement, mut breath) in query.iter_mut() {
+        if movement.depth > SURFACE_DEPTH {
+            breath.held += dt;
+            if breath.held >= breath.max_hold {
+                breath.surfacing = true;
+            }
+        } else {
+            breath.held = (breath.held - BREATH_RECOVERY_RATE * dt).max(0.);
+            if breath.held == 0. {
+                breath.surfacing = false;
+            }
+        }
+    }
+}
+
+/// boids that are not chasing anything still dive and come back up before running short of breath
+fn dive_cycle(mut rng: Local<SystemRng>, mut query: Query<(&mut Movement, &Breath)>) {
+    use rand::Rng;
+
+    for (mut movement, breath) in query.iter_mut() {
+        // hunts and forced surfacing set their own depth
+        if movement.target.is_some() || breath.surfacing {
+            continue;
+        }
+
+        if movement.target_depth > 0. {
+            if breath.held >= breath.max_hold * ROUTINE_DIVE_FRACTION {
+                movement.target_depth = 0.;
+            }
+        } else if movement.depth <= SURFACE_DEPTH && breath.held == 0. {
+            movement.target_depth = rng.gen_range(ROUTINE_DIVE_DEPTH);
+        }
+    }
+}
+
+fn depth_system(
+    time: Res<Time>,
+    bathymetry: Res<Bathymetry>,
+    mut query: Query<(&Transform, &mut Movement, Option<&Breath>)>,
+) {
+    let dt = time.delta_seconds();
+    for (trans, mut movement, breath) in query.iter_mut() {
+        let floor = bathymetry.depth_at(trans.translation.truncate());
+
+        let target = match breath {
+            Some(breath) if breath.surfacing => 0.,
+            _ => movement.target_depth.clamp(0., floor),
+        };
+
+        let step = movement.dive_speed * dt;
+        let diff = target - movement.depth;
+        movement.depth += diff.clamp(-step, step);
+        movement.depth = movement.depth.clamp(0., floor);
+    }
+}
+
+fn depth_shading(
+    movement_query: Query<&Movement>,
+    mut query: Query<(&Parent, &DepthShaded, &mut DrawMode)>,
+) {
+    for (parent, DepthShaded(base), mut draw_mode) in query.iter_mut() {
+        let movement = match movement_query.get(parent.get()) {
+            Ok(movement) => movement,
+            Err(_) => continue,
+        };
+
+        let t = (movement.depth / MAX_SHADE_DEPTH).clamp(0., 1.);
+        let base = base.as_rgba_f32();
+        let deep = DEEP_COLOR.as_rgba_f32();
+        let color = Color::rgba(
+            base[0] + (deep[0] - base[0]) * t,
+            base[1] + (deep[1] - base[1]) * t,
+            base[2] + (deep[2] - base[2]) * t,
+            base[3],
+        );
+
+        match *draw_mode {
+            DrawMode::Fill(ref mut fill_mode) => fill_mode.color = color,
+            DrawMode::Outlined {
+                ref mut fill_mode, ..
+            } => fill_mode.color = color,
+            DrawMode::Stroke(_) => {},
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use bevy::prelude::*;
+use bevy_bobs::physics_2d::RigidBody;
+use rand::Rng;
+
+use super::{
+    diving::depth_position,
+    hunger::Hunger,
+    movement::{Movement, OrcaNeighbouring, SenseLabel},
+    perception::{angle_between, heading},
+};
+use crate::{fish::Fish, orca::Orca, sim::SystemRng, vessel::NoiseField};
+
+/// hunger above which orcas start echolocating for prey
+const ECHOLOCATION_HUNGER: f32 = 0.5;
+/// body length of prey that is detected with certainty at point blank range
+const REFERENCE_PREY_SIZE: f32 = 1.;
+/// speed above which prey counts as swimming
+const SWIMMING_SPEED: f32 = 1.;
+/// how much harder it is to pick up prey that is keeping still
+const STILL_PREY_ECHO: f32 = 0.6;
+
+/// active sonar, trading energy for a long range forward facing sense
+#[derive(Component)]
+pub struct Echolocation {
+    pub range: f32,
+    /// full width of the beam in degrees
+    pub cone_angle: f32,
+    /// seconds between clicks
+    pub click_interval: f32,
+    /// hunger gained per click
+    pub click_cost: f32,
+    pub active: bool,
+    timer: f32,
+    detected: Vec<Entity>,
+}
+
+/// listening for the sounds prey make while swimming, without giving away the listener
+#[derive(Component)]
+pub struct PassiveListening {
+    pub range: f32,
+    /// seconds between attempts to pick out prey
+    pub listen_interval: f32,
+    timer: f32,
+    detected: Vec<Entity>,
+}
+
+impl Default for Echolocation {
+    fn default() -> Self {
+        Self {
+            range: 200.,
+            cone_angle: 40.,
+            click_interval: 0.5,
+            click_cost: 0.0005,
+            active: false,
+            timer: 0.,
+            detected: vec![],
+        }
+    }
+}
+
+impl Default for PassiveListening {
+    fn default() -> Self {
+        Self {
+            range: 120.,
+            listen_interval: 0.5,
+            timer: 0.,
+            detected: vec![],
+        }
+    }
+}
+
+pub struct EcholocationPlugin;
+
+impl Plugin for EcholocationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(echolocation.after(SenseLabel::PreySight))
+            .add_system(passive_listening.after(SenseLabel::PreySight));
+    }
+}
+
+fn is_swimming(rb: &RigidBody) -> bool {
+    rb.velocity.length() > SWIMMING_SPEED
+}
+
+fn echolocation(
+    time: Res<Time>,
+    noise_field: Res<NoiseField>,
+    mut query: Query<
+        (
+            &Transform,
+            &Movement,
+            &RigidBody,
+            &mut Hunger,
+            &mut Echolocation,
+            &mut OrcaNeighbouring,
+        ),
+        With<Orca>,
+    >,
+    prey_query: Query<(Entity, &Fish, &Transform, &Movement, &RigidBody)>,
+    mut rng: Local<SystemRng>,
+) {
+    for (trans, movement, rb, mut hunger, mut echo, mut neighbours) in query.iter_mut() {
+        echo.active = hunger.0 > ECHOLOCATION_HUNGER;
+        if !echo.active {
+            echo.detected.clear();
+            continue;
+        }
+
+        echo.timer += time.delta_seconds();
+        if echo.timer >= echo.click_interval {
+            echo.timer = 0.;
+            hunger.0 = (hunger.0 + echo.click_cost).clamp(0., 1.);
+
+            let front = heading(rb.velocity, trans.rotation);
+            let pos = depth_position(trans, movement);
+            let range = echo.range * noise_field.detection_factor(trans.translation.truncate());
+            let half_cone = echo.cone_angle.to_radians() / 2.;
+
+            let mut detected = vec![];
+            for (prey_entity, fish, prey_trans, prey_movement, prey_rb) in prey_query.iter() {
+                let diff = (prey_trans.translation - trans.translation).truncate();
+                if diff != Vec2::ZERO && angle_between(front, diff) > half_cone {
+                    continue;
+                }
+                let dist = pos.distance(depth_position(prey_trans, prey_movement));
+                if dist >= range {
+                    continue;
+                }
+
+                // larger and moving prey return a stronger echo
+                let mut chance =
+                    (fish.size / REFERENCE_PREY_SIZE).clamp(0., 1.) * (1. - dist / range);
+                if !is_swimming(prey_rb) {
+                    chance *= STILL_PREY_ECHO;
+                }
+                if rng.gen_bool(chance.clamp(0., 1.) as f64) {
+                    detected.push(prey_entity);
+                }
+            }
+            echo.detected = detected;
+        }
+
+        for prey_entity in echo.detected.iter() {
+            if prey_query.get(*prey_entity).is_ok() && !neighbours.prey.contains(prey_entity) {
+                neighbours.prey.push(*prey_entity);
+            }
+        }
+    }
+}
+
+fn passive_listening(
+    time: Res<Time>,
+    noise_field: Res<NoiseField>,
+    mut query: Query<
+        (
+            &Transform,
+            &Movement,
+            &mut PassiveListening,
+            &mut OrcaNeighbouring,
+        ),
+        With<Orca>,
+    >,
+    prey_query: Query<(Entity, &Fish, &Transform, &Movement, &RigidBody)>,
+    mut rng: Local<SystemRng>,
+) {
+    for (trans, movement, mut listening, mut neighbours) in query.iter_mut() {
+        listening.timer += time.delta_seconds();
+        if listening.timer >= listening.listen_interval {
+            listening.timer = 0.;
+
+            let pos = depth_position(trans, movement);
+            let range =
+                listening.range * noise_field.detection_factor(trans.translation.truncate());
+
+            let mut detected = vec![];
+            for (prey_entity, fish, prey_trans, prey_movement, prey_rb) in prey_query.iter() {
+                // prey keeping still makes no sound to listen for
+                if !is_swimming(prey_rb) {
+                    continue;
+                }
+                let dist = pos.distance(depth_position(prey_trans, prey_movement));
+                if dist >= range {
+                    continue;
+                }
+
+                let chance = (fish.size / REFERENCE_PREY_SIZE).clamp(0., 1.) * (1. - dist / range);
+                if rng.gen_bool(chance.clamp(0., 1.) as f64) {
+                    detected.push(prey_entity);
+                }
+            }
+            listening.detected = detected;
+        }
+
+        for prey_entity in listening.detected.iter() {
+            if prey_query.get(*prey_entity).is_ok() && !neighbours.prey.contains(prey_entity) {
+                neighbours.prey.push(*prey_entity);
+            }
+        }
+    }
+}
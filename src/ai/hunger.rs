@@ -1,14 +1,31 @@
 use bevy::prelude::*;
 use big_brain::prelude::*;
-
-use super::movement::{Movement, OrcaNeighbouring, Sight};
-use crate::orca::{DespawnOrcaEvent, Orca};
+use rand::Rng;
+
+use super::{
+    decision::Curve,
+    diving::depth_position,
+    movement::{Movement, OrcaNeighbouring, Sight},
+};
+use crate::{
+    orca::{DespawnOrcaEvent, Orca},
+    sim::SystemRng,
+    vessel::NoiseField,
+};
 
 const HUNGER_RATE: f32 = 0.001;
+/// chance per second and unit of received noise that a hunt is abandoned
+const NOISE_DISRUPTION: f32 = 0.2;
 
 #[derive(Component, Default, Deref)]
 pub struct Hunger(pub f32);
 
+/// prey eaten by an orca
+pub struct KillEvent {
+    pub predator: Entity,
+    pub prey: Entity,
+}
+
 impl Hunger {
     fn eat(&mut self, amount: f32) {
         self.0 = (self.0 + amount).clamp(0., 1.);
@@ -19,10 +36,12 @@ pub struct HungerPlugin;
 
 impl Plugin for HungerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(passive_hunger_system)
+        app.add_event::<KillEvent>()
             .add_system(passive_hunger_system);
 
-        app.add_system(hungry_scorer).add_system(hunt_action);
+        app.add_system(hungry_scorer)
+            .add_system(hunt_action)
+            .add_system(end_forced_hunt);
     }
 }
 
@@ -40,13 +59,27 @@ fn passive_hunger_system(
     }
 }
 
+/// scores hunger through a utility curve
 #[derive(Clone, Component, Debug)]
-pub struct Hungry;
+pub struct Hungry {
+    pub curve: Curve,
+}
+
+/// hunt regardless of hunger until the hunt is over
+#[derive(Component)]
+pub struct ForceHunt;
 
-fn hungry_scorer(hungers: Query<&Hunger>, mut query: Query<(&Actor, &mut Score), With<Hungry>>) {
-    for (Actor(actor), mut score) in query.iter_mut() {
-        if let Ok(hunger) = hungers.get(*actor) {
-            score.set(hunger.0);
+fn hungry_scorer(
+    hungers: Query<(&Hunger, Option<&ForceHunt>)>,
+    mut query: Query<(&Actor, &mut Score, &Hungry)>,
+) {
+    for (Actor(actor), mut score, hungry) in query.iter_mut() {
+        if let Ok((hunger, forced)) = hungers.get(*actor) {
+            score.set(if forced.is_some() {
+                1.
+            } else {
+                hungry.curve.eval(hunger.0)
+            });
         }
     }
 }
@@ -56,9 +89,13 @@ pub struct Hunt;
 
 fn hunt_action(
     mut cmd: Commands,
+    time: Res<Time>,
+    noise_field: Res<NoiseField>,
     mut actor_query: Query<(&Transform, &mut Hunger, &OrcaNeighbouring, &mut Movement), With<Orca>>,
-    mut prey_query: Query<(&Transform), Without<Orca>>,
+    mut prey_query: Query<(&Transform, &Movement), Without<Orca>>,
     mut query: Query<(&Actor, &mut ActionState, &Hunt)>,
+    mut kill_writer: EventWriter<KillEvent>,
+    mut rng: Local<SystemRng>,
 ) {
     for (Actor(actor), mut state, hunt) in query.iter_mut() {
         if let Ok((trans, mut hunger, neighbours, mut movement)) = actor_query.get_mut(*actor) {
@@ -71,6 +108,9 @@ fn hunt_action(
                         // TODO better way of choosing a fish to hunt
                         let target = neighbours.prey[0];
                         movement.target = Some(target);
+                        if let Ok((_, prey_movement)) = prey_query.get(target) {
+                            movement.target_depth = prey_movement.depth;
+                        }
 
                         *state = ActionState::Executing
                     }
@@ -85,21 +125,50 @@ fn hunt_action(
                         return;
                     }
 
-                    if let Ok(prey_trans) = prey_query.get(movement.target.unwrap()) {
+                    // boat noise cuts foraging bouts short
+                    let noise = noise_field.level_at(trans.translation.truncate());
+                    let give_up_chance =
+                        (noise * NOISE_DISRUPTION * time.delta_seconds()).clamp(0., 1.);
+                    if rng.gen_bool(give_up_chance as f64) {
+                        movement.target = None;
+                        movement.target_depth = 0.;
+                        *state = ActionState::Cancelled;
+                        continue;
+                    }
+
+                    if let Ok((prey_trans, prey_movement)) =
+                        prey_query.get(movement.target.unwrap())
+                    {
+                        // follow the prey as it changes depth
+                        movement.target_depth = prey_movement.depth;
+
+                        let dist = depth_position(trans, &movement)
+                            .distance(depth_position(prey_trans, prey_movement));
+
                         // Eat the prey
-                        if trans.translation.distance(prey_trans.translation) < EAT_RANGE {
+                        if dist < EAT_RANGE {
                             cmd.entity(movement.target.unwrap()).despawn_recursive();
+                            kill_writer.send(KillEvent {
+                                predator: *actor,
+                                prey: movement.target.unwrap(),
+                            });
 
                             hunger.eat(0.01);
                             movement.target = None;
+                            movement.target_depth = 0.;
                             *state = ActionState::Success;
                         }
 
                         // Give up persuing prey
-                        if trans.translation.distance(prey_trans.translation) > GIVE_UP_RANGE {
+                        if dist > GIVE_UP_RANGE {
+                            movement.target = None;
+                            movement.target_depth = 0.;
                             *state = ActionState::Cancelled;
                         }
                     } else {
+                        // prey is gone, eaten by another orca or despawned
+                        movement.target = None;
+                        movement.target_depth = 0.;
                         *state = ActionState::Cancelled;
                         return;
                     }
@@ -109,3 +178,17 @@ fn hunt_action(
         }
     }
 }
+
+fn end_forced_hunt(
+    mut cmd: Commands,
+    forced_query: Query<(), With<ForceHunt>>,
+    query: Query<(&Actor, &ActionState), With<Hunt>>,
+) {
+    for (Actor(actor), state) in query.iter() {
+        if forced_query.contains(*actor)
+            && matches!(state, ActionState::Success | ActionState::Cancelled)
+        {
+            cmd.entity(*actor).remove::<ForceHunt>();
+        }
+    }
+}
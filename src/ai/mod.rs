@@ -1,18 +1,29 @@
+pub mod decision;
+pub mod diving;
+pub mod echolocation;
 pub mod hunger;
 pub mod movement;
+pub mod perception;
 pub mod reproduction;
 
 use bevy::prelude::*;
 use big_brain::prelude::*;
 
-use self::{hunger::HungerPlugin, movement::MovementPlugin};
+use self::{
+    decision::DecisionPlugin, diving::DivingPlugin, echolocation::EcholocationPlugin,
+    hunger::HungerPlugin, movement::MovementPlugin, reproduction::ReproductionPlugin,
+};
 
 pub struct AIPlugin;
 
 impl Plugin for AIPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(BigBrainPlugin)
+            .add_plugin(DecisionPlugin)
+            .add_plugin(DivingPlugin)
+            .add_plugin(EcholocationPlugin)
             .add_plugin(HungerPlugin)
-            .add_plugin(MovementPlugin);
+            .add_plugin(MovementPlugin)
+            .add_plugin(ReproductionPlugin);
     }
 }
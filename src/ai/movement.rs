@@ -1,17 +1,32 @@
-use std::{collections::HashMap, f32::consts::PI};
+use std::collections::BTreeMap;
 
 use bevy::prelude::*;
 use bevy_bobs::physics_2d::*;
+use serde::{Deserialize, Serialize};
 
+use super::{
+    diving::depth_position,
+    perception::{can_see, can_see_within, heading, separation},
+};
 use crate::{
+    calendar::Calendar,
     fish::Fish,
-    orca::{Orca, PodPool},
+    map::ObstacleMap,
+    orca::{Orca, PodId},
+    sim::{FishStock, SystemRng},
+    vessel::NoiseField,
 };
 
+/// distance at which boids start steering away from land
+const OBSTACLE_RANGE: f32 = 30.;
+
 #[derive(Component)]
 pub struct Sight {
+    /// half width of the view cone in degrees
     pub view_angle: f32,
     pub view_range: f32,
+    /// how much the view range shrinks towards the edge of the view cone, between 0 and 1
+    pub peripheral_falloff: f32,
 }
 
 #[derive(Component, Default)]
@@ -25,6 +40,17 @@ pub struct FishNeighbouring {
     pub around: Vec<Entity>,
 }
 
+/// weighted steering forces from the last boid update, kept around for overlays
+#[derive(Component, Default, Clone, Copy)]
+pub struct BoidForces {
+    pub alignment: Vec2,
+    pub cohesion: Vec2,
+    pub separation: Vec2,
+    pub wander: Vec2,
+    pub avoidance: Vec2,
+    pub target: Vec2,
+}
+
 /// ai with flocking behavior
 #[derive(Component, Clone)]
 pub struct Movement {
@@ -38,15 +64,24 @@ pub struct Movement {
     pub randomess: f32,
     /// weight for tracking
     pub tracking: f32,
+    /// weight for steering around obstacles
+    pub avoidance: f32,
     /// range between 0..359
     pub wander_angle: u32,
     /// optional target to move towards
     pub target: Option<Entity>,
     /// Speed scale
     pub speed_scale: f32,
+    /// current depth below the surface in metres
+    pub depth: f32,
+    /// depth the boid is diving or surfacing towards
+    pub target_depth: f32,
+    /// vertical speed in metres per second
+    pub dive_speed: f32,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct BoidParams {
     pub coherence: f32,
     pub alignment: f32,
@@ -55,6 +90,7 @@ pub struct BoidParams {
 
     pub view_range: f32,
     pub view_angle: f32,
+    pub peripheral_falloff: f32,
 }
 
 impl Default for BoidParams {
@@ -67,10 +103,40 @@ impl Default for BoidParams {
 
             view_range: 50.,
             view_angle: 30.,
+            peripheral_falloff: 0.,
         }
     }
 }
 
+impl BoidParams {
+    /// set the flocking weights and sight of a boid
+    pub fn apply(&self, movement: &mut Movement, sight: &mut Sight) {
+        movement.coherence = self.coherence;
+        movement.alignment = self.alignment;
+        movement.seperation = self.seperation;
+        movement.randomess = self.randomness;
+        sight.view_range = self.view_range;
+        sight.view_angle = self.view_angle;
+        sight.peripheral_falloff = self.peripheral_falloff;
+    }
+}
+
+/// boids that a live edit of boid parameters applies to
+#[derive(Clone, Copy, PartialEq)]
+pub enum ParamTarget {
+    Orcas,
+    Pod(PodId),
+    /// a single orca, ignored if the entity is not an orca
+    Orca(Entity),
+    Fish,
+}
+
+/// change the boid parameters of boids that are already swimming
+pub struct SetBoidParamsEvent {
+    pub params: BoidParams,
+    pub target: ParamTarget,
+}
+
 impl Default for Movement {
     fn default() -> Self {
         Self {
@@ -79,64 +145,97 @@ impl Default for Movement {
             seperation: 2.,
             randomess: 1.,
             tracking: 1.,
+            avoidance: 5.,
             wander_angle: 10,
             target: None,
             speed_scale: 1.,
+            depth: 0.,
+            target_depth: 0.,
+            dive_speed: 2.,
         }
     }
 }
 
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SenseLabel {
+    /// prey found by sight, which other senses add to
+    PreySight,
+}
+
 pub struct MovementPlugin;
 
 impl Plugin for MovementPlugin {
     fn build(&self, app: &mut App) {
+        app.add_event::<SetBoidParamsEvent>()
+            .add_system(set_boid_params);
+
         app.add_system(pod_member_sight)
             .add_system(orca_boid_ai)
-            .add_system(prey_sight);
+            .add_system(prey_sight.label(SenseLabel::PreySight));
 
         app.add_system(fish_sight).add_system(fish_boid_ai);
     }
 }
 
+fn set_boid_params(
+    mut events: EventReader<SetBoidParamsEvent>,
+    mut stock: Option<ResMut<FishStock>>,
+    mut query: Query<(
+        Entity,
+        Option<&Orca>,
+        Option<&Fish>,
+        &mut Movement,
+        &mut Sight,
+    )>,
+) {
+    for SetBoidParamsEvent { params, target } in events.iter() {
+        for (entity, orca, fish, mut movement, mut sight) in query.iter_mut() {
+            let targeted = match target {
+                ParamTarget::Orcas => orca.is_some(),
+                ParamTarget::Pod(pod_id) => orca.and_then(|orca| orca.pod_id) == Some(*pod_id),
+                ParamTarget::Orca(orca_entity) => orca.is_some() && entity == *orca_entity,
+                ParamTarget::Fish => fish.is_some(),
+            };
+            if targeted {
+                params.apply(&mut movement, &mut sight);
+            }
+        }
+
+        // fish spawned later on take the edited parameters too
+        if let (ParamTarget::Fish, Some(stock)) = (target, stock.as_mut()) {
+            stock.params = *params;
+        }
+    }
+}
+
 fn pod_member_sight(
     mut query: Query<(
         Entity,
         &Orca,
-        &mut Transform,
+        &Transform,
         &Sight,
         &mut OrcaNeighbouring,
-        &Movement,
         &RigidBody,
     )>,
-    pod_pool: Res<PodPool>,
+    obstacle_map: Res<ObstacleMap>,
 ) {
-    let mut updates: HashMap<Entity, Vec<Entity>> = HashMap::new();
-    for (self_entity, self_orca, self_trans, self_sight, neighbours, self_movement, self_rb) in
-        query.iter()
-    {
+    let mut updates: BTreeMap<Entity, Vec<Entity>> = BTreeMap::new();
+    for (self_entity, self_orca, self_trans, self_sight, _, self_rb) in query.iter() {
+        let front = heading(self_rb.velocity, self_trans.rotation);
+        let pos = self_trans.translation.truncate().extend(0.);
+
         // fetch all boids in viewing range
         let mut neighbours: Vec<Entity> = vec![];
-        for (other_entity, other_orca, other_trans, other_sight, _, other_ai, other_rb) in
-            query.iter()
-        {
+        for (other_entity, other_orca, other_trans, _, _, _) in query.iter() {
             if self_entity == other_entity {
                 continue;
             }
-            if self_orca.pod_id.is_none()
-                || other_orca.pod_id.is_none()
-                || self_orca.pod_id != other_orca.pod_id
-            {
+            if self_orca.pod_id.is_none() || self_orca.pod_id != other_orca.pod_id {
                 continue;
             }
 
-            let front = self_rb.velocity.normalize();
-            let diff = (other_trans.translation - self_trans.translation).truncate();
-            let angle = front.angle_between(diff);
-            let view_range = self_sight.view_angle * PI / 180.;
-            if self_trans.translation.distance(other_trans.translation) < self_sight.view_range
-                && angle < view_range
-                && angle > -view_range
-            {
+            let other_pos = other_trans.translation.truncate().extend(0.);
+            if can_see(self_sight, &obstacle_map, pos, front, other_pos) {
                 neighbours.push(other_entity);
             }
         }
@@ -145,20 +244,37 @@ fn pod_member_sight(
     }
 
     for (e, n) in updates.iter() {
-        let (_, _, _, _, mut neighbours, _, _) = query.get_mut(*e).unwrap();
+        let (_, _, _, _, mut neighbours, _) = query.get_mut(*e).unwrap();
         neighbours.pod_members.clear();
         neighbours.pod_members.extend(n);
     }
 }
 
 fn prey_sight(
-    mut query: Query<(&Transform, &Sight, &mut OrcaNeighbouring), With<Orca>>,
-    prey_query: Query<(Entity, &Transform), With<Fish>>,
+    mut query: Query<
+        (
+            &Transform,
+            &Sight,
+            &Movement,
+            &RigidBody,
+            &mut OrcaNeighbouring,
+        ),
+        With<Orca>,
+    >,
+    prey_query: Query<(Entity, &Transform, &Movement), With<Fish>>,
+    noise_field: Res<NoiseField>,
+    obstacle_map: Res<ObstacleMap>,
 ) {
-    for (trans, sight, mut neighbours) in query.iter_mut() {
+    for (trans, sight, movement, rb, mut neighbours) in query.iter_mut() {
         neighbours.prey.clear();
-        for (prey_entity, prey_trans) in &prey_query {
-            if trans.translation.distance(prey_trans.translation) < sight.view_range {
+        let front = heading(rb.velocity, trans.rotation);
+        let pos = depth_position(trans, movement);
+        // vessel noise masks the sounds of prey
+        let view_range =
+            sight.view_range * noise_field.detection_factor(trans.translation.truncate());
+        for (prey_entity, prey_trans, prey_movement) in &prey_query {
+            let prey_pos = depth_position(prey_trans, prey_movement);
+            if can_see_within(sight, view_range, &obstacle_map, pos, front, prey_pos) {
                 neighbours.prey.push(prey_entity);
             }
         }
@@ -169,32 +285,28 @@ fn fish_sight(
     mut query: Query<
         (
             Entity,
-            &mut Transform,
+            &Transform,
             &Sight,
             &mut FishNeighbouring,
-            &Movement,
             &RigidBody,
         ),
         With<Fish>,
     >,
-    pod_pool: Res<PodPool>,
+    obstacle_map: Res<ObstacleMap>,
 ) {
-    let mut updates: HashMap<Entity, Vec<Entity>> = HashMap::new();
-    for (self_entity, self_trans, self_sight, neighbours, self_movement, self_rb) in query.iter() {
+    let mut updates: BTreeMap<Entity, Vec<Entity>> = BTreeMap::new();
+    for (self_entity, self_trans, self_sight, _, self_rb) in query.iter() {
+        let front = heading(self_rb.velocity, self_trans.rotation);
+        let pos = self_trans.translation.truncate().extend(0.);
+
         // fetch all boids in viewing range
         let mut neighbours: Vec<Entity> = vec![];
-        for (other_entity, other_trans, other_sight, _, other_ai, other_rb) in query.iter() {
+        for (other_entity, other_trans, _, _, _) in query.iter() {
             if self_entity == other_entity {
                 continue;
             }
-            let front = self_rb.velocity.normalize();
-            let diff = (other_trans.translation - self_trans.translation).truncate();
-            let angle = front.angle_between(diff);
-            let view_range = self_sight.view_angle * PI / 180.;
-            if self_trans.translation.distance(other_trans.translation) < self_sight.view_range
-                && angle < view_range
-                && angle > -view_range
-            {
+            let other_pos = other_trans.translation.truncate().extend(0.);
+            if can_see(self_sight, &obstacle_map, pos, front, other_pos) {
                 neighbours.push(other_entity);
             }
         }
@@ -203,7 +315,7 @@ fn fish_sight(
     }
 
     for (e, n) in updates.iter() {
-        let (_, _, _, mut neighbours, _, _) = query.get_mut(*e).unwrap();
+        let (_, _, _, mut neighbours, _) = query.get_mut(*e).unwrap();
         neighbours.around.clear();
         neighbours.around.extend(n);
     }
@@ -222,32 +334,48 @@ fn orca_boid_ai(
         With<Orca>,
     >,
     target_query: Query<&Transform, Without<Orca>>,
+    obstacle_map: Res<ObstacleMap>,
+    calendar: Res<Calendar>,
+    mut forces_query: Query<&mut BoidForces>,
+    mut rng: Local<SystemRng>,
 ) {
-    let mut force_updates: HashMap<Entity, Vec2> = HashMap::new();
+    let mut force_updates: BTreeMap<Entity, Vec2> = BTreeMap::new();
+    let mut boid_forces: BTreeMap<Entity, BoidForces> = BTreeMap::new();
     for (entity, trans, neighbouring, movement, rb) in query.iter() {
         let neighbours = &neighbouring.pod_members;
 
+        let mut cur_force = force_updates.get(&entity).unwrap_or(&Vec2::ZERO).clone();
+        let mut forces = BoidForces::default();
+
+        // obstacle avoidance
+        forces.avoidance =
+            obstacle_map.avoidance_force(trans.translation.truncate(), rb.velocity, OBSTACLE_RANGE)
+                * movement.avoidance;
+        cur_force += forces.avoidance;
+
         if neighbours.len() == 0 {
+            force_updates.insert(entity, cur_force);
+            boid_forces.insert(entity, forces);
             continue;
         }
 
-        let mut cur_force = force_updates.get(&entity).unwrap_or(&Vec2::ZERO).clone();
-
         // randomness force
         use std::f32::consts::PI;
 
-        use rand::{thread_rng, Rng};
+        use rand::Rng;
 
         if rb.velocity.length() != 0. {
             // let rand: i32 = thread_rng().gen_range(0..(movement.wander_angle as i32));
-            let angle_deviation = thread_rng()
+            let angle_deviation = rng
                 .gen_range(-(movement.wander_angle as i32)..(movement.wander_angle as i32))
                 as f32
                 * PI
                 / 180.;
-            let forward = rb.velocity.angle_between(Vec2::X);
+            let front = heading(rb.velocity, trans.rotation);
+            let forward = front.y.atan2(front.x);
             let random_force = Mat2::from_angle(angle_deviation + forward) * Vec2::X;
-            cur_force += random_force * movement.randomess;
+            forces.wander = random_force * movement.randomess;
+            cur_force += forces.wander;
         }
 
         // alignment (attempt to face same direction as neighbours)
@@ -255,7 +383,9 @@ fn orca_boid_ai(
             .iter_many(neighbours)
             .fold(Vec2::ZERO, |acc, (_, _, _, _, rb)| acc + rb.velocity)
             / neighbours.len() as f32;
-        cur_force += (avg_heading - rb.velocity.normalize()) * movement.alignment;
+        forces.alignment =
+            (avg_heading - heading(rb.velocity, trans.rotation)) * movement.alignment;
+        cur_force += forces.alignment;
 
         // cohesion
         let avg_position = query
@@ -264,48 +394,66 @@ fn orca_boid_ai(
                 acc + trans.translation
             })
             / neighbours.len() as f32;
-        cur_force += (avg_position - trans.translation).truncate() * movement.coherence;
+        forces.cohesion = (avg_position - trans.translation).truncate() * movement.coherence;
+        cur_force += forces.cohesion;
 
         // separation
         let seperation_force =
             query
                 .iter_many(neighbours)
-                .fold(Vec2::ZERO, |acc, (_, other_trans, _, _, _)| {
-                    let dist = trans.translation.distance(other_trans.translation);
-                    let dir = (trans.translation - other_trans.translation).truncate();
-                    // TODO careful for division by zero
-                    acc + dir / dist
+                .fold(Vec2::ZERO, |acc, (other, other_trans, _, _, _)| {
+                    acc + separation(
+                        trans.translation.truncate(),
+                        other_trans.translation.truncate(),
+                        entity < other,
+                    )
                 });
-        cur_force += seperation_force * movement.seperation;
+        forces.separation = seperation_force * movement.seperation;
+        cur_force += forces.separation;
 
         // avoidance
         let avoidance_force =
             50. / (100. - trans.translation.truncate().length()).clamp(0.00001, 1e10);
-        cur_force += -trans.translation.truncate().normalize() * avoidance_force;
+        let boundary_force = -trans.translation.truncate().normalize_or_zero() * avoidance_force;
+        forces.avoidance += boundary_force;
+        cur_force += boundary_force;
 
         // target
         if let Some(target) = movement.target {
             if let Ok(target_trans) = target_query.get(target) {
                 let target_force = (target_trans.translation - trans.translation).truncate();
-                cur_force += target_force * movement.tracking;
+                forces.target = target_force * movement.tracking;
+                cur_force += forces.target;
             }
         }
 
         force_updates.insert(entity, cur_force);
+        boid_forces.insert(entity, forces);
+    }
+
+    for (entity, forces) in boid_forces {
+        if let Ok(mut last_forces) = forces_query.get_mut(entity) {
+            *last_forces = forces;
+        }
     }
 
     // update all the forces
     for (e, _, _, ai, mut rb) in query.iter_mut() {
         if let Some(force) = force_updates.get(&e) {
-            rb.force +=
-                *force * 1000. * ai.speed_scale * time.delta().as_micros() as f32 / 1_000_000.0;
+            rb.force += *force
+                * 1000.
+                * ai.speed_scale
+                * calendar.activity()
+                * time.delta().as_micros() as f32
+                / 1_000_000.0;
         }
     }
 
-    // rotate boid to face direction of travel
-    for (e, mut trans, _, _, rb) in query.iter_mut() {
-        let facing = rb.velocity.angle_between(Vec2::X);
-        trans.rotation = Quat::from_rotation_z(facing);
+    // rotate boid to face direction of travel, keeping the last facing while stopped
+    for (_, mut trans, _, _, rb) in query.iter_mut() {
+        if rb.velocity != Vec2::ZERO {
+            trans.rotation = Quat::from_rotation_z(rb.velocity.y.atan2(rb.velocity.x));
+        }
     }
 }
 
@@ -323,28 +471,43 @@ fn fish_boid_ai(
         With<Fish>,
     >,
     target_query: Query<&Transform, Without<Fish>>,
+    obstacle_map: Res<ObstacleMap>,
+    mut forces_query: Query<&mut BoidForces>,
+    mut rng: Local<SystemRng>,
 ) {
-    let mut force_updates: HashMap<Entity, Vec2> = HashMap::new();
+    let mut force_updates: BTreeMap<Entity, Vec2> = BTreeMap::new();
+    let mut boid_forces: BTreeMap<Entity, BoidForces> = BTreeMap::new();
     for (entity, trans, neighbouring, movement, rb) in query.iter() {
         let neighbours = &neighbouring.around;
 
+        let mut cur_force = force_updates.get(&entity).unwrap_or(&Vec2::ZERO).clone();
+        let mut forces = BoidForces::default();
+
+        // obstacle avoidance
+        forces.avoidance =
+            obstacle_map.avoidance_force(trans.translation.truncate(), rb.velocity, OBSTACLE_RANGE)
+                * movement.avoidance;
+        cur_force += forces.avoidance;
+
         if neighbours.len() == 0 {
+            force_updates.insert(entity, cur_force);
+            boid_forces.insert(entity, forces);
             continue;
         }
 
-        let mut cur_force = force_updates.get(&entity).unwrap_or(&Vec2::ZERO).clone();
-
         // randomness force
         use std::f32::consts::PI;
 
-        use rand::{thread_rng, Rng};
+        use rand::Rng;
 
         if rb.velocity.length() != 0. {
-            let rand: i32 = thread_rng().gen_range(0..(movement.wander_angle as i32));
+            let rand: i32 = rng.gen_range(0..(movement.wander_angle as i32));
             let angle_deviation = ((rand - 180) as f32) * PI / 180.;
-            let forward = rb.velocity.angle_between(Vec2::X);
+            let front = heading(rb.velocity, trans.rotation);
+            let forward = front.y.atan2(front.x);
             let random_force = Mat2::from_angle(angle_deviation + forward) * Vec2::X;
-            cur_force += random_force * movement.randomess;
+            forces.wander = random_force * movement.randomess;
+            cur_force += forces.wander;
         }
 
         // alignment (attempt to face same direction as neighbours)
@@ -352,7 +515,9 @@ fn fish_boid_ai(
             .iter_many(neighbours)
             .fold(Vec2::ZERO, |acc, (_, _, _, _, rb)| acc + rb.velocity)
             / neighbours.len() as f32;
-        cur_force += (avg_heading - rb.velocity.normalize()) * movement.alignment;
+        forces.alignment =
+            (avg_heading - heading(rb.velocity, trans.rotation)) * movement.alignment;
+        cur_force += forces.alignment;
 
         // cohesion
         let avg_position = query
@@ -361,19 +526,22 @@ fn fish_boid_ai(
                 acc + trans.translation
             })
             / neighbours.len() as f32;
-        cur_force += (avg_position - trans.translation).truncate() * movement.coherence;
+        forces.cohesion = (avg_position - trans.translation).truncate() * movement.coherence;
+        cur_force += forces.cohesion;
 
         // separation
         let seperation_force =
             query
                 .iter_many(neighbours)
-                .fold(Vec2::ZERO, |acc, (_, other_trans, _, _, _)| {
-                    let dist = trans.translation.distance(other_trans.translation);
-                    let dir = (trans.translation - other_trans.translation).truncate();
-                    // TODO careful for division by zero
-                    acc + dir / dist
+                .fold(Vec2::ZERO, |acc, (other, other_trans, _, _, _)| {
+                    acc + separation(
+                        trans.translation.truncate(),
+                        other_trans.translation.truncate(),
+                        entity < other,
+                    )
                 });
-        cur_force += seperation_force * movement.seperation;
+        forces.separation = seperation_force * movement.seperation;
+        cur_force += forces.separation;
 
         // avoidance
         // let avoidance_force =
@@ -389,6 +557,13 @@ fn fish_boid_ai(
         // }
 
         force_updates.insert(entity, cur_force);
+        boid_forces.insert(entity, forces);
+    }
+
+    for (entity, forces) in boid_forces {
+        if let Ok(mut last_forces) = forces_query.get_mut(entity) {
+            *last_forces = forces;
+        }
     }
 
     // update all the forces
@@ -399,9 +574,10 @@ fn fish_boid_ai(
         }
     }
 
-    // rotate boid to face direction of travel
-    for (e, mut trans, _, _, rb) in query.iter_mut() {
-        let facing = rb.velocity.angle_between(Vec2::X);
-        trans.rotation = Quat::from_rotation_z(facing);
+    // rotate boid to face direction of travel, keeping the last facing while stopped
+    for (_, mut trans, _, _, rb) in query.iter_mut() {
+        if rb.velocity != Vec2::ZERO {
+            trans.rotation = Quat::from_rotation_z(rb.velocity.y.atan2(rb.velocity.x));
+        }
     }
 }
@@ -0,0 +1,231 @@
+use bevy::prelude::*;
+
+use super::movement::Sight;
+use crate::map::ObstacleMap;
+
+/// distance below which two boids are treated as occupying the same spot
+const COLOCATED_DIST: f32 = 0.0001;
+
+/// direction a boid is facing, falling back to its rotation when it is not moving
+pub fn heading(velocity: Vec2, rotation: Quat) -> Vec2 {
+    let front = velocity.normalize_or_zero();
+    if front != Vec2::ZERO {
+        return front;
+    }
+    let facing = (rotation * Vec3::X).truncate().normalize_or_zero();
+    if facing != Vec2::ZERO {
+        facing
+    } else {
+        Vec2::X
+    }
+}
+
+/// unsigned angle in radians between two directions, well defined even for parallel vectors
+pub fn angle_between(a: Vec2, b: Vec2) -> f32 {
+    a.perp_dot(b).atan2(a.dot(b)).abs()
+}
+
+/// how well something at offset `diff` is seen, between zero (not seen) and one
+///
+/// Anything outside the view cone is not seen at all. With peripheral falloff the factor drops
+/// towards the edge of the cone, reaching `1 - peripheral_falloff` at the edge. Boids that are
+/// on top of each other always see each other, and a boid without a heading sees all around.
+pub fn view_factor(sight: &Sight, front: Vec2, diff: Vec2) -> f32 {
+    if diff.length() < COLOCATED_DIST || front == Vec2::ZERO {
+        return 1.;
+    }
+
+    let half_angle = sight.view_angle.to_radians();
+    let angle = angle_between(front, diff);
+    if half_angle <= 0. || angle > half_angle {
+        return 0.;
+    }
+
+    let edge = (angle / half_angle).clamp(0., 1.);
+    1. - sight.peripheral_falloff.clamp(0., 1.) * edge * edge
+}
+
+/// whether `to` is within sight of a boid at `from` facing `front`
+///
+/// Positions may carry a depth in `z`, which counts towards the distance but not the view cone.
+/// Peripheral falloff shortens the effective view range, and land blocks the line of sight.
+pub fn can_see(
+    sight: &Sight,
+    obstacle_map: &ObstacleMap,
+    from: Vec3,
+    front: Vec2,
+    to: Vec3,
+) -> bool {
+    can_see_within(sight, sight.view_range, obstacle_map, from, front, to)
+}
+
+/// `can_see` with the view range overridden, such as when noise masks the senses
+pub fn can_see_within(
+    sight: &Sight,
+    view_range: f32,
+    obstacle_map: &ObstacleMap,
+    from: Vec3,
+    front: Vec2,
+    to: Vec3,
+) -> bool {
+    let diff = (to - from).truncate();
+    let factor = view_factor(sight, front, diff);
+    if factor <= 0. || from.distance(to) >= view_range * factor {
+        return false;
+    }
+    !obstacle_map.occludes(from.truncate(), to.truncate())
+}
+
+/// unit vector pushing a boid at `pos` away from a neighbour at `other`
+///
+/// Boids in exactly the same spot are pushed apart along the x axis, in opposite directions
+/// depending on `tiebreak`, so the pair does not stay stuck together.
+pub fn separation(pos: Vec2, other: Vec2, tiebreak: bool) -> Vec2 {
+    let diff = pos - other;
+    let dist = diff.length();
+    if dist < COLOCATED_DIST {
+        return if tiebreak { Vec2::X } else { -Vec2::X };
+    }
+    diff / dist
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Obstacle;
+
+    fn sight(view_angle: f32, peripheral_falloff: f32) -> Sight {
+        Sight {
+            view_range: 50.,
+            view_angle,
+            peripheral_falloff,
+        }
+    }
+
+    fn wall() -> ObstacleMap {
+        ObstacleMap {
+            obstacles: vec![Obstacle {
+                points: vec![
+                    Vec2::new(10., -10.),
+                    Vec2::new(12., -10.),
+                    Vec2::new(12., 10.),
+                    Vec2::new(10., 10.),
+                ],
+            }],
+        }
+    }
+
+    #[test]
+    fn heading_at_zero_velocity_uses_rotation() {
+        let rotation = Quat::from_rotation_z(std::f32::consts::FRAC_PI_2);
+        let front = heading(Vec2::ZERO, rotation);
+        assert!(front.is_finite());
+        assert!(front.distance(Vec2::Y) < 1e-5);
+    }
+
+    #[test]
+    fn heading_follows_velocity() {
+        assert_eq!(heading(Vec2::new(3., 0.), Quat::IDENTITY), Vec2::X);
+    }
+
+    #[test]
+    fn view_cone_excludes_behind() {
+        let sight = sight(30., 0.);
+        assert_eq!(view_factor(&sight, Vec2::X, Vec2::new(10., 1.)), 1.);
+        assert_eq!(view_factor(&sight, Vec2::X, Vec2::new(-10., 0.)), 0.);
+        assert_eq!(view_factor(&sight, Vec2::X, Vec2::new(0., 10.)), 0.);
+    }
+
+    #[test]
+    fn colocated_entities_see_each_other() {
+        let sight = sight(30., 0.5);
+        assert_eq!(view_factor(&sight, Vec2::X, Vec2::ZERO), 1.);
+        assert!(can_see(
+            &sight,
+            &ObstacleMap::default(),
+            Vec3::ZERO,
+            Vec2::X,
+            Vec3::ZERO
+        ));
+    }
+
+    #[test]
+    fn zero_heading_is_not_nan() {
+        let sight = sight(30., 0.5);
+        let factor = view_factor(&sight, Vec2::ZERO, Vec2::new(-5., 0.));
+        assert!(factor.is_finite());
+    }
+
+    #[test]
+    fn peripheral_falloff_shortens_range() {
+        let sight = sight(90., 0.5);
+        let ahead = view_factor(&sight, Vec2::X, Vec2::X);
+        let side = view_factor(&sight, Vec2::X, Vec2::new(0.01, 1.));
+        assert_eq!(ahead, 1.);
+        assert!(side < ahead && side >= 0.5);
+
+        let map = ObstacleMap::default();
+        assert!(can_see(
+            &sight,
+            &map,
+            Vec3::ZERO,
+            Vec2::X,
+            Vec3::new(40., 0., 0.)
+        ));
+        assert!(!can_see(
+            &sight,
+            &map,
+            Vec3::ZERO,
+            Vec2::X,
+            Vec3::new(0.1, 40., 0.)
+        ));
+    }
+
+    #[test]
+    fn depth_counts_towards_distance() {
+        let sight = sight(90., 0.);
+        let map = ObstacleMap::default();
+        assert!(!can_see(
+            &sight,
+            &map,
+            Vec3::ZERO,
+            Vec2::X,
+            Vec3::new(30., 0., 45.)
+        ));
+    }
+
+    #[test]
+    fn land_blocks_line_of_sight() {
+        let sight = sight(90., 0.);
+        let map = wall();
+        assert!(!can_see(
+            &sight,
+            &map,
+            Vec3::ZERO,
+            Vec2::X,
+            Vec3::new(20., 0., 0.)
+        ));
+        assert!(can_see(
+            &sight,
+            &map,
+            Vec3::ZERO,
+            Vec2::X,
+            Vec3::new(5., 0., 0.)
+        ));
+    }
+
+    #[test]
+    fn separation_of_colocated_is_finite() {
+        let a = separation(Vec2::ONE, Vec2::ONE, true);
+        let b = separation(Vec2::ONE, Vec2::ONE, false);
+        assert!(a.is_finite() && b.is_finite());
+        assert_ne!(a, Vec2::ZERO);
+        assert_eq!(a, -b);
+    }
+
+    #[test]
+    fn separation_points_away() {
+        let force = separation(Vec2::new(3., 4.), Vec2::ZERO, true);
+        assert!(force.distance(Vec2::new(0.6, 0.8)) < 1e-5);
+    }
+}
@@ -1 +1,50 @@
 use bevy::prelude::*;
+
+use crate::{
+    calendar::Calendar,
+    orca::{Gender, Orca, SpawnOrcaEvent},
+    sim::{RunSimEvent, SystemRng},
+};
+
+/// age in years at which females can start giving birth
+const MATURE_AGE: u32 = 12;
+
+pub struct ReproductionPlugin;
+
+impl Plugin for ReproductionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(birth_system);
+    }
+}
+
+/// each new day, adult females give birth with the chance set by the current season
+fn birth_system(
+    calendar: Res<Calendar>,
+    query: Query<(Entity, &Orca)>,
+    mut writer: EventWriter<SpawnOrcaEvent>,
+    mut last_day: Local<u32>,
+    mut rng: Local<SystemRng>,
+    mut run_events: EventReader<RunSimEvent>,
+) {
+    use rand::Rng;
+
+    // a new run starts back on day zero, which is not a new day
+    if run_events.iter().count() > 0 {
+        *last_day = 0;
+        return;
+    }
+    if calendar.day == *last_day {
+        return;
+    }
+    *last_day = calendar.day;
+
+    let birth_rate = calendar.season_params().birth_rate;
+    for (entity, orca) in query.iter() {
+        if orca.gender != Gender::Female || orca.age < MATURE_AGE || orca.pod_id.is_none() {
+            continue;
+        }
+        if rng.gen_bool(birth_rate.clamp(0., 1.) as f64) {
+            writer.send(SpawnOrcaEvent { mother: entity });
+        }
+    }
+}
@@ -1,31 +1,57 @@
-use bevy::{prelude::*, render::texture::ImageSettings};
+use std::path::PathBuf;
+
+use bevy::{app::AppExit, prelude::*, render::texture::ImageSettings};
 use bevy_bobs::physics_2d::*;
 use bevy_hanabi::HanabiPlugin;
 use bevy_mod_picking::*;
 use bevy_prototype_lyon::prelude::*;
+use pino_utils::some_or_return;
 
 use crate::{
+    acoustic::AcousticPlugin,
     ai::{
         hunger::Hunger,
         movement::{Movement, Sight},
         AIPlugin,
     },
+    calendar::CalendarPlugin,
     camera::CameraPlugin,
+    capture::{CapturePlugin, ScheduledScreenshot, StartCaptureEvent},
+    cli::Cli,
+    current::CurrentPlugin,
+    export::{ExportPlugin, StartRecordingEvent, DEFAULT_RECORD_INTERVAL},
     fish::FishPlugin,
+    headless,
+    map::MapPlugin,
+    metrics::MetricsPlugin,
     orca::{Gender, Orca, OrcaPlugin, Pod, PodPool, Type},
-    sim::SimPlugin,
+    replay::{
+        LoadReplayEvent, PlaybackPlugin, ReplayPlugin, StartReplayRecordingEvent,
+        DEFAULT_REPLAY_INTERVAL,
+    },
+    scenario::Scenario,
+    sim::{SimPlugin, SimRng, SimSpeed, SimSpeedPlugin, Simulation},
+    space_use::{SpaceUse, SpaceUsePlugin},
+    stats::{Stats, StatsPlugin},
     ui::UIPlugin,
+    vessel::VesselPlugin,
 };
 
-pub fn app() {
+/// when a run started from the command line ends and where its results go
+struct RunLimit {
+    duration: Option<f32>,
+    output: Option<PathBuf>,
+}
+
+pub fn app(cli: &Cli, scenario: Option<Scenario>) {
     let mut window_descriptor = WindowDescriptor {
         present_mode: bevy::window::PresentMode::Fifo,
         title: "sakamata".into(),
         ..default()
     };
 
-    window_descriptor.width = 800.;
-    window_descriptor.height = 600.;
+    window_descriptor.width = cli.width as f32;
+    window_descriptor.height = cli.height as f32;
 
     let mut app = App::new();
 
@@ -36,17 +62,115 @@ pub fn app() {
 
     app.add_plugins(DefaultPlugins)
         .add_plugin(ShapePlugin)
-        .add_plugin(PhysicsPlugin)
         .add_plugins(DefaultPickingPlugins)
         .add_plugin(HanabiPlugin);
     // .add_plugin(DebugEventsPickingPlugin);
 
     app.add_plugin(UIPlugin)
-        .add_plugin(AIPlugin)
-        .add_plugin(OrcaPlugin)
-        .add_plugin(FishPlugin)
         .add_plugin(CameraPlugin)
-        .add_plugin(SimPlugin);
+        .add_plugin(CapturePlugin)
+        .add_plugin(PlaybackPlugin);
+    add_sim_plugins(&mut app);
+    app.add_plugin(SimSpeedPlugin)
+        .insert_resource(SimSpeed(cli.speed))
+        .insert_resource(RunLimit {
+            duration: cli.duration,
+            output: cli.output.clone(),
+        })
+        .add_system(end_run)
+        .add_system_to_stage(CoreStage::Last, write_output);
+    if let Some(seed) = cli.seed {
+        app.insert_resource(SimRng::seeded(seed));
+    }
+
+    // record trajectories and events from the start
+    if let Some(dir) = cli.record.clone() {
+        app.world
+            .resource_mut::<Events<StartRecordingEvent>>()
+            .send(StartRecordingEvent {
+                dir,
+                interval: DEFAULT_RECORD_INTERVAL,
+            });
+    }
+
+    if let Some(path) = cli.record_replay.clone() {
+        app.world
+            .resource_mut::<Events<StartReplayRecordingEvent>>()
+            .send(StartReplayRecordingEvent {
+                path,
+                interval: DEFAULT_REPLAY_INTERVAL,
+            });
+    }
+
+    if let Some(dir) = cli.capture.clone() {
+        app.world
+            .resource_mut::<Events<StartCaptureEvent>>()
+            .send(StartCaptureEvent {
+                dir,
+                fps: cli.capture_fps,
+                video: cli.capture_video.clone(),
+            });
+    }
+
+    if let (Some(path), Some(at)) = (cli.screenshot.clone(), cli.screenshot_at) {
+        app.insert_resource(ScheduledScreenshot { at, path });
+    }
+
+    if let Some(scenario) = scenario {
+        scenario.start(&mut app.world);
+    }
+    if let Some(path) = cli.replay.clone() {
+        app.world
+            .resource_mut::<Events<LoadReplayEvent>>()
+            .send(LoadReplayEvent(path));
+    }
 
     app.run();
 }
+
+fn end_run(run_limit: Res<RunLimit>, sim: Res<Simulation>, mut exit_writer: EventWriter<AppExit>) {
+    let duration = some_or_return!(run_limit.duration);
+    if sim.time >= duration {
+        exit_writer.send(AppExit);
+    }
+}
+
+/// write out the results of the run as the app exits, however it was closed
+fn write_output(
+    run_limit: Res<RunLimit>,
+    stats: Res<Stats>,
+    space_use: Res<SpaceUse>,
+    pod_pool: Res<PodPool>,
+    orca_query: Query<(), With<Orca>>,
+    mut exit_reader: EventReader<AppExit>,
+) {
+    let dir = some_or_return!(&run_limit.output);
+    if exit_reader.iter().count() == 0 {
+        return;
+    }
+
+    let summary = headless::summarise(&stats, orca_query.iter().count());
+    match headless::write_results(dir, &stats, &space_use, &pod_pool, &summary) {
+        Ok(()) => info!("wrote results to {}", dir.display()),
+        Err(e) => warn!("failed to write results to {}: {}", dir.display(), e),
+    }
+}
+
+/// plugins that make up the simulation itself, shared by the windowed and headless apps
+pub fn add_sim_plugins(app: &mut App) {
+    app.add_plugin(PhysicsPlugin)
+        .add_plugin(AcousticPlugin)
+        .add_plugin(AIPlugin)
+        .add_plugin(OrcaPlugin)
+        .add_plugin(ReplayPlugin)
+        .add_plugin(FishPlugin)
+        .add_plugin(CalendarPlugin)
+        .add_plugin(CurrentPlugin)
+        .add_plugin(ExportPlugin)
+        .add_plugin(MapPlugin)
+        .add_plugin(MetricsPlugin)
+        .add_plugin(SimPlugin)
+        .add_plugin(SpaceUsePlugin)
+        .add_plugin(StatsPlugin)
+        .add_plugin(VesselPlugin);
+}
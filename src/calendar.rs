@@ -0,0 +1,186 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use pino_utils::enum_string;
+use serde::{Deserialize, Serialize};
+
+use crate::sim::{RunSimEvent, Simulation};
+
+const DAY_COLOR: Color = Color::rgb(0.26, 0.52, 0.96);
+const NIGHT_COLOR: Color = Color::rgb(0.02, 0.05, 0.15);
+/// simulations start in the morning
+const START_TIME_OF_DAY: f32 = 0.25;
+
+#[enum_string]
+#[derive(PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Season {
+    pub const ALL: [Season; 4] = [
+        Season::Spring,
+        Season::Summer,
+        Season::Autumn,
+        Season::Winter,
+    ];
+
+    pub fn index(&self) -> usize {
+        match self {
+            Season::Spring => 0,
+            Season::Summer => 1,
+            Season::Autumn => 2,
+            Season::Winter => 3,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SeasonParams {
+    /// multiplier on the number of fish kept stocked in the simulation
+    pub salmon_abundance: f32,
+    /// how active orcas are at night compared to during the day
+    pub night_activity: f32,
+    /// chance per day that an adult female gives birth
+    pub birth_rate: f32,
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CalendarParams {
+    /// length of a day in simulation seconds
+    pub day_length: f32,
+    pub days_per_season: u32,
+    pub start_season: Season,
+    /// parameters of each season, indexed by `Season::index`
+    pub seasons: [SeasonParams; 4],
+}
+
+impl Default for SeasonParams {
+    fn default() -> Self {
+        Self {
+            salmon_abundance: 1.,
+            night_activity: 0.5,
+            birth_rate: 0.,
+        }
+    }
+}
+
+impl Default for CalendarParams {
+    fn default() -> Self {
+        Self {
+            day_length: 120.,
+            days_per_season: 5,
+            start_season: Season::Spring,
+            seasons: [
+                SeasonParams {
+                    salmon_abundance: 0.8,
+                    birth_rate: 0.02,
+                    ..default()
+                },
+                SeasonParams {
+                    salmon_abundance: 1.5,
+                    night_activity: 0.7,
+                    ..default()
+                },
+                SeasonParams {
+                    salmon_abundance: 1.2,
+                    birth_rate: 0.01,
+                    ..default()
+                },
+                SeasonParams {
+                    salmon_abundance: 0.5,
+                    night_activity: 0.3,
+                    ..default()
+                },
+            ],
+        }
+    }
+}
+
+/// calendar derived from `Simulation::time`
+pub struct Calendar {
+    pub params: CalendarParams,
+    /// days passed since the start of the simulation
+    pub day: u32,
+    /// fraction of the current day that has passed, starting at midnight
+    pub time_of_day: f32,
+    pub season: Season,
+}
+
+impl Calendar {
+    pub fn new(params: CalendarParams) -> Self {
+        Self {
+            params,
+            day: 0,
+            time_of_day: START_TIME_OF_DAY,
+            season: params.start_season,
+        }
+    }
+
+    pub fn season_params(&self) -> &SeasonParams {
+        &self.params.seasons[self.season.index()]
+    }
+
+    /// one at noon and zero at midnight
+    pub fn daylight(&self) -> f32 {
+        (1. - (2. * PI * self.time_of_day).cos()) / 2.
+    }
+
+    /// multiplier on how actively orcas swim at the current time of day
+    pub fn activity(&self) -> f32 {
+        let night = self.season_params().night_activity;
+        night + (1. - night) * self.daylight()
+    }
+
+    /// time of day formatted as a 24 hour clock
+    pub fn clock(&self) -> String {
+        let minutes = (self.time_of_day * 24. * 60.) as u32;
+        format!("{:02}:{:02}", minutes / 60, minutes % 60)
+    }
+
+    fn update(&mut self, time: f32) {
+        let days = time / self.params.day_length.max(f32::EPSILON) + START_TIME_OF_DAY;
+        self.day = days as u32;
+        self.time_of_day = days.fract();
+
+        let seasons_passed = self.day / self.params.days_per_season.max(1);
+        let index = (self.params.start_season.index() + seasons_passed as usize) % 4;
+        self.season = Season::ALL[index];
+    }
+}
+
+pub struct CalendarPlugin;
+
+impl Plugin for CalendarPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Calendar::new(CalendarParams::default()))
+            .add_system(reset_calendar)
+            .add_system(calendar_system)
+            .add_system(sky_color);
+    }
+}
+
+fn reset_calendar(mut cmd: Commands, mut events: EventReader<RunSimEvent>) {
+    for event in events.iter() {
+        cmd.insert_resource(Calendar::new(event.calendar));
+    }
+}
+
+fn calendar_system(sim: Res<Simulation>, mut calendar: ResMut<Calendar>) {
+    calendar.update(sim.time);
+}
+
+fn sky_color(calendar: Res<Calendar>, mut clear_color: ResMut<ClearColor>) {
+    let t = calendar.daylight();
+    let (day, night) = (DAY_COLOR.as_rgba_f32(), NIGHT_COLOR.as_rgba_f32());
+    clear_color.0 = Color::rgb(
+        night[0] + (day[0] - night[0]) * t,
+        night[1] + (day[1] - night[1]) * t,
+        night[2] + (day[2] - night[2]) * t,
+    );
+}
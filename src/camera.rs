@@ -1,10 +1,28 @@
 use bevy::prelude::*;
 use bevy_mod_picking::PickingCameraBundle;
 use bevy_pancam::{PanCam, PanCamPlugin};
-use pino_utils::ok_or_return;
+use pino_utils::{ok_or_return, some_or_return};
+
+use crate::{
+    fish::Fish,
+    orca::{Orca, PodId},
+    vessel::Vessel,
+};
+
+/// how quickly the camera catches up with what it follows, higher is snappier
+const FOLLOW_RATE: f32 = 5.;
+/// room left around everything when zooming to fit
+const FIT_MARGIN: f32 = 1.2;
+const MIN_SCALE: f32 = 0.05;
 
 pub struct CameraFollow(pub Entity);
 
+/// follow the centroid of a pod, while no single entity is followed
+pub struct CameraFollowPod(pub PodId);
+
+/// frame every orca, fish and vessel
+pub struct ZoomToFitEvent;
+
 #[derive(Component)]
 pub struct MainCamera;
 
@@ -13,21 +31,91 @@ pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(PanCamPlugin::default())
+            .add_event::<ZoomToFitEvent>()
             .add_startup_system(spawn_camera)
-            .add_system(camera_follow);
+            .add_system(camera_follow)
+            .add_system(zoom_to_fit)
+            .add_system(camera_controller);
     }
 }
 
 fn camera_follow(
+    time: Res<Time>,
     follow: Option<Res<CameraFollow>>,
-    entity_query: Query<&Transform, Without<Camera>>,
-    mut camera_query: Query<&mut Transform, With<Camera>>,
+    follow_pod: Option<Res<CameraFollowPod>>,
+    entity_query: Query<&Transform, Without<MainCamera>>,
+    orca_query: Query<(&Orca, &Transform), Without<MainCamera>>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+) {
+    let target = if let Some(follow) = follow {
+        ok_or_return!(entity_query.get(follow.0))
+            .translation
+            .truncate()
+    } else if let Some(follow_pod) = follow_pod {
+        let positions: Vec<Vec2> = orca_query
+            .iter()
+            .filter(|(orca, _)| orca.pod_id == Some(follow_pod.0))
+            .map(|(_, trans)| trans.translation.truncate())
+            .collect();
+        if positions.is_empty() {
+            return;
+        }
+        positions.iter().copied().sum::<Vec2>() / positions.len() as f32
+    } else {
+        return;
+    };
+    let mut camera_trans = ok_or_return!(camera_query.get_single_mut());
+
+    // ease towards the target, independent of frame rate
+    let t = 1. - (-FOLLOW_RATE * time.delta_seconds()).exp();
+    let pos = camera_trans.translation.truncate().lerp(target, t);
+    camera_trans.translation = pos.extend(camera_trans.translation.z);
+}
+
+fn zoom_to_fit(
+    mut cmd: Commands,
+    mut events: EventReader<ZoomToFitEvent>,
+    windows: Res<Windows>,
+    query: Query<
+        &Transform,
+        (
+            Or<(With<Orca>, With<Fish>, With<Vessel>)>,
+            Without<MainCamera>,
+        ),
+    >,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
 ) {
-    if let Some(follow) = follow {
-        let follow_trans = ok_or_return!(entity_query.get(follow.0));
-        let mut camera_trans = ok_or_return!(camera_query.get_single_mut());
+    if events.iter().count() == 0 {
+        return;
+    }
+    let window = some_or_return!(windows.get_primary());
+    let (mut camera_trans, mut projection) = ok_or_return!(camera_query.get_single_mut());
+
+    let (min, max) = query.iter().fold(
+        (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+        |(min, max), trans| {
+            let pos = trans.translation.truncate();
+            (min.min(pos), max.max(pos))
+        },
+    );
+    if min.x > max.x {
+        return;
+    }
+
+    let size = (max - min) * FIT_MARGIN;
+    camera_trans.translation = ((min + max) / 2.).extend(camera_trans.translation.z);
+    projection.scale = (size.x / window.width())
+        .max(size.y / window.height())
+        .max(MIN_SCALE);
+
+    // following would pull the camera straight back off
+    cmd.remove_resource::<CameraFollow>();
+    cmd.remove_resource::<CameraFollowPod>();
+}
 
-        camera_trans.translation = follow_trans.translation;
+fn camera_controller(keys: Res<Input<KeyCode>>, mut fit_writer: EventWriter<ZoomToFitEvent>) {
+    if keys.just_pressed(KeyCode::F) {
+        fit_writer.send(ZoomToFitEvent);
     }
 }
 
@@ -0,0 +1,587 @@
+use std::{
+    fs,
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    app::AppExit,
+    prelude::*,
+    render::{
+        camera::{CameraUpdateSystem, RenderTarget},
+        main_graph::node::CAMERA_DRIVER,
+        render_asset::RenderAssets,
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext},
+        render_resource::{
+            Buffer, BufferDescriptor, BufferUsages, Extent3d, ImageCopyBuffer, ImageDataLayout,
+            MapMode, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+        },
+        renderer::{RenderContext, RenderDevice},
+        texture::BevyDefault,
+        RenderApp, RenderStage,
+    },
+    transform::TransformSystem,
+};
+use pino_utils::{ok_or_return, some_or_return};
+use wgpu::{Maintain, COPY_BYTES_PER_ROW_ALIGNMENT};
+
+use crate::{
+    camera::MainCamera,
+    sim::{LockStep, ScaleTimeLabel, Simulation},
+};
+
+/// frames per simulation second captured when none is given
+pub const DEFAULT_CAPTURE_FPS: f32 = 30.;
+
+const CAPTURE_NODE: &str = "capture";
+
+/// save what the main camera sees, without the ui, as a png
+pub struct ScreenshotEvent(pub PathBuf);
+
+/// save every frame as a png sequence, with the simulation stepping `1 / fps` seconds per frame
+pub struct StartCaptureEvent {
+    pub dir: PathBuf,
+    pub fps: f32,
+    /// encode the frames into this video with ffmpeg once capturing stops
+    pub video: Option<PathBuf>,
+}
+pub struct StopCaptureEvent;
+
+/// screenshot to take once the simulation reaches a time
+pub struct ScheduledScreenshot {
+    pub at: f32,
+    pub path: PathBuf,
+}
+
+/// png sequence being captured
+pub struct FrameCapture {
+    pub dir: PathBuf,
+    pub fps: f32,
+    pub video: Option<PathBuf>,
+    /// frames written so far, counted by the render world as they are saved
+    saved: Arc<AtomicU32>,
+    /// frames that should have been saved by the end of the last frame
+    requested: u32,
+}
+
+#[derive(Default)]
+pub struct Capture {
+    screenshots: Vec<PathBuf>,
+    recording: Option<FrameCapture>,
+}
+
+impl Capture {
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    fn is_active(&self) -> bool {
+        !self.screenshots.is_empty() || self.recording.is_some()
+    }
+}
+
+/// image the capture camera draws into
+struct CaptureTarget(Handle<Image>);
+
+/// second camera that mirrors the main one, drawing into `CaptureTarget` while capturing
+#[derive(Component)]
+struct CaptureCamera;
+
+/// next frame of a png sequence, only numbered once it is written so a dropped frame leaves no gap
+#[derive(Clone)]
+struct SequenceFrame {
+    dir: PathBuf,
+    saved: Arc<AtomicU32>,
+}
+
+/// files this frame is to be saved to, handed over to the render world
+#[derive(Clone)]
+struct CaptureRequest {
+    image: Handle<Image>,
+    size: UVec2,
+    format: TextureFormat,
+    paths: Vec<PathBuf>,
+    frame: Option<SequenceFrame>,
+}
+
+/// buffer the captured image is copied into on the gpu to be read back
+struct Readback {
+    buffer: Buffer,
+    image: Handle<Image>,
+    size: UVec2,
+    format: TextureFormat,
+    padded_row: u32,
+    paths: Vec<PathBuf>,
+    frame: Option<SequenceFrame>,
+    copied: AtomicBool,
+}
+
+pub struct CapturePlugin;
+
+impl Plugin for CapturePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Capture::default())
+            .add_event::<ScreenshotEvent>()
+            .add_event::<StartCaptureEvent>()
+            .add_event::<StopCaptureEvent>()
+            .add_startup_system(spawn_capture_camera)
+            .add_system(capture_controller)
+            .add_system(scheduled_screenshot)
+            .add_system(take_screenshot)
+            .add_system(start_capture)
+            .add_system(stop_capture)
+            .add_system_to_stage(CoreStage::First, hold_lock_step.before(ScaleTimeLabel))
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                sync_capture_camera
+                    .before(CameraUpdateSystem)
+                    .before(TransformSystem::TransformPropagate),
+            )
+            .add_system_to_stage(CoreStage::Last, request_capture)
+            .add_system_to_stage(CoreStage::Last, finish_capture);
+
+        let render_app = ok_or_return!(app.get_sub_app_mut(RenderApp));
+        render_app
+            .add_system_to_stage(RenderStage::Extract, extract_capture)
+            .add_system_to_stage(RenderStage::Prepare, prepare_readback)
+            .add_system_to_stage(RenderStage::Cleanup, read_back);
+        let mut graph = render_app.world.resource_mut::<RenderGraph>();
+        graph.add_node(CAPTURE_NODE, CaptureNode);
+        graph.add_node_edge(CAMERA_DRIVER, CAPTURE_NODE).unwrap();
+    }
+}
+
+fn timestamp() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis())
+        .unwrap_or_default()
+}
+
+fn spawn_capture_camera(mut cmd: Commands, mut images: ResMut<Assets<Image>>) {
+    let size = Extent3d {
+        width: 1,
+        height: 1,
+        depth_or_array_layers: 1,
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("capture"),
+            size,
+            dimension: TextureDimension::D2,
+            // the 2d pipelines only draw into the default format
+            format: TextureFormat::bevy_default(),
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+        },
+        ..default()
+    };
+    image.resize(size);
+    let handle = images.add(image);
+
+    cmd.spawn_bundle(Camera2dBundle {
+        camera: Camera {
+            target: RenderTarget::Image(handle.clone()),
+            priority: -1,
+            is_active: false,
+            ..default()
+        },
+        ..default()
+    })
+    .insert(CaptureCamera);
+    cmd.insert_resource(CaptureTarget(handle));
+}
+
+fn capture_controller(
+    keys: Res<Input<KeyCode>>,
+    capture: Res<Capture>,
+    mut screenshot_writer: EventWriter<ScreenshotEvent>,
+    mut start_writer: EventWriter<StartCaptureEvent>,
+    mut stop_writer: EventWriter<StopCaptureEvent>,
+) {
+    if keys.just_pressed(KeyCode::F12) {
+        screenshot_writer.send(ScreenshotEvent(
+            PathBuf::from("screenshots").join(format!("sakamata_{}.png", timestamp())),
+        ));
+    }
+    if keys.just_pressed(KeyCode::F11) {
+        if capture.is_recording() {
+            stop_writer.send(StopCaptureEvent);
+        } else {
+            start_writer.send(StartCaptureEvent {
+                dir: PathBuf::from("captures").join(format!("sakamata_{}", timestamp())),
+                fps: DEFAULT_CAPTURE_FPS,
+                video: None,
+            });
+        }
+    }
+}
+
+fn scheduled_screenshot(
+    mut cmd: Commands,
+    scheduled: Option<Res<ScheduledScreenshot>>,
+    sim: Res<Simulation>,
+    mut screenshot_writer: EventWriter<ScreenshotEvent>,
+) {
+    let scheduled = some_or_return!(scheduled);
+    if sim.time >= scheduled.at {
+        screenshot_writer.send(ScreenshotEvent(scheduled.path.clone()));
+        cmd.remove_resource::<ScheduledScreenshot>();
+    }
+}
+
+fn take_screenshot(mut capture: ResMut<Capture>, mut events: EventReader<ScreenshotEvent>) {
+    for ScreenshotEvent(path) in events.iter() {
+        capture.screenshots.push(path.clone());
+    }
+}
+
+fn start_capture(
+    mut cmd: Commands,
+    mut capture: ResMut<Capture>,
+    mut events: EventReader<StartCaptureEvent>,
+) {
+    for event in events.iter() {
+        if let Some(recording) = capture.recording.take() {
+            finish(recording);
+        }
+        if let Err(e) = fs::create_dir_all(&event.dir) {
+            warn!(
+                "failed to start capturing to {}: {}",
+                event.dir.display(),
+                e
+            );
+            continue;
+        }
+
+        let fps = event.fps.max(1.);
+        info!("capturing frames to {}", event.dir.display());
+        // step the simulation by exactly one frame's worth, however slowly frames are drawn
+        cmd.insert_resource(LockStep {
+            step: Duration::from_secs_f32(1. / fps),
+            hold: false,
+        });
+        capture.recording = Some(FrameCapture {
+            dir: event.dir.clone(),
+            fps,
+            video: event.video.clone(),
+            saved: Arc::new(AtomicU32::new(0)),
+            requested: 0,
+        });
+    }
+}
+
+fn stop_capture(
+    mut cmd: Commands,
+    mut capture: ResMut<Capture>,
+    mut events: EventReader<StopCaptureEvent>,
+) {
+    if events.iter().count() == 0 {
+        return;
+    }
+    if let Some(recording) = capture.recording.take() {
+        cmd.remove_resource::<LockStep>();
+        finish(recording);
+    }
+}
+
+/// encode whatever was captured before the app exits
+fn finish_capture(mut capture: ResMut<Capture>, mut exit_reader: EventReader<AppExit>) {
+    if exit_reader.iter().count() == 0 {
+        return;
+    }
+    if let Some(recording) = capture.recording.take() {
+        finish(recording);
+    }
+}
+
+fn finish(recording: FrameCapture) {
+    info!(
+        "captured {} frames to {}",
+        recording.saved.load(Ordering::Relaxed),
+        recording.dir.display()
+    );
+    let video = some_or_return!(&recording.video);
+    match encode(&recording.dir, recording.fps, video) {
+        Ok(()) => info!("encoded {}", video.display()),
+        Err(e) => warn!("failed to encode {}: {}", video.display(), e),
+    }
+}
+
+/// turn a png sequence into a video with a local ffmpeg
+fn encode(dir: &Path, fps: f32, video: &Path) -> Result<(), String> {
+    let status = process::Command::new("ffmpeg")
+        .args([
+            "-y",
+            "-loglevel",
+            "error",
+            "-framerate",
+            fps.to_string().as_str(),
+            "-i",
+        ])
+        .arg(dir.join("frame_%06d.png"))
+        // most encoders want even dimensions
+        .args([
+            "-vf",
+            "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+            "-pix_fmt",
+            "yuv420p",
+        ])
+        .arg(video)
+        .status()
+        .map_err(|e| format!("could not run ffmpeg: {}", e))?;
+    if !status.success() {
+        return Err(format!("ffmpeg exited with {}", status));
+    }
+    Ok(())
+}
+
+/// keep the capture camera looking where the main camera does, at the window's resolution
+fn sync_capture_camera(
+    capture: Res<Capture>,
+    target: Res<CaptureTarget>,
+    windows: Res<Windows>,
+    mut images: ResMut<Assets<Image>>,
+    main_query: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    mut capture_query: Query<
+        (&mut Camera, &mut Transform, &mut OrthographicProjection),
+        (With<CaptureCamera>, Without<MainCamera>),
+    >,
+) {
+    let (mut camera, mut trans, mut projection) = ok_or_return!(capture_query.get_single_mut());
+    if camera.is_active != capture.is_active() {
+        camera.is_active = capture.is_active();
+    }
+
+    // kept up to date while idle too, as a resize takes a frame to reach the camera
+    let (main_trans, main_projection) = ok_or_return!(main_query.get_single());
+    *trans = *main_trans;
+    projection.scale = main_projection.scale;
+
+    let window = some_or_return!(windows.get_primary());
+    let size = Extent3d {
+        width: window.physical_width().max(1),
+        height: window.physical_height().max(1),
+        depth_or_array_layers: 1,
+    };
+    if images
+        .get(&target.0)
+        .map(|image| image.texture_descriptor.size != size)
+        .unwrap_or(false)
+    {
+        if let Some(image) = images.get_mut(&target.0) {
+            image.resize(size);
+        }
+    }
+}
+
+/// keep the simulation where it is until the frame showing its last step has been saved
+///
+/// Rendering finishes before the next frame starts, so whether the last request made it is known
+/// by now. Dropped frames are requested again without the clock moving on.
+fn hold_lock_step(capture: Res<Capture>, lock_step: Option<ResMut<LockStep>>) {
+    let mut lock_step = some_or_return!(lock_step);
+    let recording = some_or_return!(capture.recording.as_ref());
+    let hold = recording.saved.load(Ordering::Relaxed) < recording.requested;
+    if lock_step.hold != hold {
+        lock_step.hold = hold;
+    }
+}
+
+fn request_capture(
+    mut cmd: Commands,
+    mut capture: ResMut<Capture>,
+    target: Res<CaptureTarget>,
+    images: Res<Assets<Image>>,
+) {
+    cmd.remove_resource::<CaptureRequest>();
+    if !capture.is_active() {
+        return;
+    }
+    let image = some_or_return!(images.get(&target.0));
+    let size = image.texture_descriptor.size;
+
+    let paths: Vec<PathBuf> = capture.screenshots.drain(..).collect();
+    let frame = capture.recording.as_mut().map(|recording| {
+        recording.requested = recording.saved.load(Ordering::Relaxed) + 1;
+        SequenceFrame {
+            dir: recording.dir.clone(),
+            saved: recording.saved.clone(),
+        }
+    });
+    cmd.insert_resource(CaptureRequest {
+        image: target.0.clone(),
+        size: UVec2::new(size.width, size.height),
+        format: image.texture_descriptor.format,
+        paths,
+        frame,
+    });
+}
+
+/// move this frame's request, if any, over to the render world
+fn extract_capture(mut cmd: Commands, request: Option<Res<CaptureRequest>>) {
+    if let Some(request) = request {
+        cmd.insert_resource(request.clone());
+    }
+}
+
+/// rows of a buffer copied from a texture have to be aligned
+fn padded_bytes_per_row(width: u32) -> u32 {
+    let row = width * 4;
+    let align = COPY_BYTES_PER_ROW_ALIGNMENT;
+    (row + align - 1) / align * align
+}
+
+fn prepare_readback(
+    mut cmd: Commands,
+    request: Option<Res<CaptureRequest>>,
+    render_device: Res<RenderDevice>,
+) {
+    let request = some_or_return!(request);
+    cmd.remove_resource::<CaptureRequest>();
+
+    let padded_row = padded_bytes_per_row(request.size.x);
+    let buffer = render_device.create_buffer(&BufferDescriptor {
+        label: Some("capture_readback"),
+        size: (padded_row * request.size.y) as u64,
+        usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    cmd.insert_resource(Readback {
+        buffer,
+        image: request.image.clone(),
+        size: request.size,
+        format: request.format,
+        padded_row,
+        paths: request.paths.clone(),
+        frame: request.frame.clone(),
+        copied: AtomicBool::new(false),
+    });
+}
+
+/// copies the capture camera's image into the readback buffer once every camera has drawn
+struct CaptureNode;
+
+impl Node for CaptureNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let readback = match world.get_resource::<Readback>() {
+            Some(readback) => readback,
+            None => return Ok(()),
+        };
+        let gpu_image = match world.resource::<RenderAssets<Image>>().get(&readback.image) {
+            // skip a frame drawn before a resize caught up
+            Some(gpu_image) if gpu_image.size == readback.size.as_vec2() => gpu_image,
+            _ => return Ok(()),
+        };
+
+        render_context.command_encoder.copy_texture_to_buffer(
+            gpu_image.texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &readback.buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(readback.padded_row),
+                    rows_per_image: None,
+                },
+            },
+            Extent3d {
+                width: readback.size.x,
+                height: readback.size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+        readback.copied.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// wait for the copy to finish and save it, holding up the next frame until it has
+fn read_back(mut cmd: Commands, readback: Option<Res<Readback>>, render_device: Res<RenderDevice>) {
+    let readback = some_or_return!(readback);
+    cmd.remove_resource::<Readback>();
+    if !readback.copied.load(Ordering::Relaxed) {
+        for path in readback.paths.iter() {
+            warn!("dropped capture of {}", path.display());
+        }
+        if let Some(frame) = &readback.frame {
+            warn!("dropped frame of {}, retrying", frame.dir.display());
+        }
+        return;
+    }
+
+    let slice = readback.buffer.slice(..);
+    let mapped = Arc::new(AtomicBool::new(false));
+    let on_mapped = mapped.clone();
+    slice.map_async(MapMode::Read, move |result| {
+        on_mapped.store(result.is_ok(), Ordering::Relaxed)
+    });
+    render_device.wgpu_device().poll(Maintain::Wait);
+    if !mapped.load(Ordering::Relaxed) {
+        warn!("failed to read back captured frame");
+        return;
+    }
+
+    // the default format is bgra on most desktops, the saved pngs are always rgba
+    let bgra = matches!(
+        readback.format,
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
+    );
+    let (width, height) = (readback.size.x, readback.size.y);
+    let mut data: Vec<u8> = Vec::with_capacity((width * height * 4) as usize);
+    for row in slice
+        .get_mapped_range()
+        .chunks(readback.padded_row as usize)
+    {
+        for pixel in row[..(width * 4) as usize].chunks_exact(4) {
+            if bgra {
+                data.extend([pixel[2], pixel[1], pixel[0], pixel[3]]);
+            } else {
+                data.extend_from_slice(pixel);
+            }
+        }
+    }
+    readback.buffer.unmap();
+
+    let save = |path: &Path| {
+        path.parent()
+            .map(|dir| fs::create_dir_all(dir).map_err(|e| e.to_string()))
+            .unwrap_or(Ok(()))
+            .and_then(|_| {
+                image::save_buffer(path, &data, width, height, image::ColorType::Rgba8)
+                    .map_err(|e| e.to_string())
+            })
+    };
+    for path in readback.paths.iter() {
+        if let Err(e) = save(path) {
+            warn!("failed to save {}: {}", path.display(), e);
+        }
+    }
+    // numbered by what has been written, so the sequence stays unbroken for ffmpeg
+    if let Some(frame) = &readback.frame {
+        let path = frame.dir.join(format!(
+            "frame_{:06}.png",
+            frame.saved.load(Ordering::Relaxed)
+        ));
+        match save(&path) {
+            Ok(()) => {
+                frame.saved.fetch_add(1, Ordering::Relaxed);
+            },
+            Err(e) => warn!("failed to save {}: {}", path.display(), e),
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use std::path::PathBuf;
+
+use bevy::prelude::Events;
+use clap::{Args, Parser, Subcommand};
+
+use crate::{
+    app,
+    capture::DEFAULT_CAPTURE_FPS,
+    export::{StartRecordingEvent, DEFAULT_RECORD_INTERVAL},
+    headless::{self, Summary},
+    orca::PodPool,
+    replay::{ReplayRecorder, StartReplayRecordingEvent, DEFAULT_REPLAY_INTERVAL},
+    scenario::Scenario,
+    space_use::SpaceUse,
+    stats::Stats,
+    sweep::{self, ParamRange},
+    tune::{self, Fitness, Species},
+};
+
+/// orca whale simulation software
+#[derive(Parser)]
+#[clap(version, about)]
+pub struct Cli {
+    /// scenario file to start running straight away
+    #[clap(long, value_parser)]
+    pub scenario: Option<PathBuf>,
+
+    /// seed of the simulation's randomness, random if left out
+    #[clap(long, value_parser)]
+    pub seed: Option<u64>,
+
+    /// run without a window at a fixed timestep, as fast as possible
+    #[clap(long, requires = "duration")]
+    pub headless: bool,
+
+    /// simulation seconds to run for before exiting
+    #[clap(long, value_parser)]
+    pub duration: Option<f32>,
+
+    /// how many times faster than real time to run
+    #[clap(long, value_parser, default_value_t = 1., conflicts_with = "headless")]
+    pub speed: f32,
+
+    /// directory to write the stats and summary of the run to when it ends
+    #[clap(long, short, value_parser)]
+    pub output: Option<PathBuf>,
+
+    /// record trajectories and events to this directory from the start
+    #[clap(long, value_parser)]
+    pub record: Option<PathBuf>,
+
+    /// record a replay of the run to this file, saved when the app exits
+    #[clap(long, value_parser)]
+    pub record_replay: Option<PathBuf>,
+
+    /// save every frame as a png sequence to this directory from the start
+    #[clap(long, value_parser, conflicts_with = "headless")]
+    pub capture: Option<PathBuf>,
+
+    /// frames captured per simulation second, the simulation steps one frame's worth per frame
+    /// drawn while capturing
+    #[clap(long, value_parser, default_value_t = DEFAULT_CAPTURE_FPS)]
+    pub capture_fps: f32,
+
+    /// encode the captured frames into this video file with ffmpeg when the app exits
+    #[clap(long, value_parser, requires = "capture")]
+    pub capture_video: Option<PathBuf>,
+
+    /// save a screenshot to this file once the simulation reaches `--screenshot-at` seconds
+    #[clap(
+        long,
+        value_parser,
+        requires = "screenshot_at",
+        conflicts_with = "headless"
+    )]
+    pub screenshot: Option<PathBuf>,
+
+    /// simulation seconds to take the screenshot at
+    #[clap(long, value_parser, requires = "screenshot")]
+    pub screenshot_at: Option<f32>,
+
+    /// play back a replay file instead of simulating
+    #[clap(long, value_parser, conflicts_with_all = &["headless", "scenario", "record_replay"])]
+    pub replay: Option<PathBuf>,
+
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..), default_value_t = 800, conflicts_with = "headless")]
+    pub width: u32,
+
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..), default_value_t = 600, conflicts_with = "headless")]
+    pub height: u32,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// run every combination of parameter values headlessly and write a table of results
+    Sweep(SweepArgs),
+    /// evolve boid parameters against a fitness and write the best as scenario files
+    Tune(TuneArgs),
+}
+
+#[derive(Args)]
+pub struct SweepArgs {
+    /// scenario file to start each run from
+    #[clap(long, value_parser)]
+    pub scenario: Option<PathBuf>,
+
+    /// parameter to vary, as name=start:end:step or name=a,b,c, may be given more than once
+    #[clap(long = "param", short, value_parser, required = true)]
+    pub params: Vec<ParamRange>,
+
+    /// number of seeds to run each combination with
+    #[clap(long, value_parser, default_value_t = 1)]
+    pub seeds: u64,
+
+    /// first seed, later seeds count up from it
+    #[clap(long, value_parser, default_value_t = 0)]
+    pub seed: u64,
+
+    /// simulation seconds to run each combination for
+    #[clap(long, value_parser, default_value_t = 300.)]
+    pub duration: f32,
+
+    /// csv file to write the results to
+    #[clap(long, short, value_parser, default_value = "sweep.csv")]
+    pub output: PathBuf,
+
+    /// number of runs at once, defaults to the number of cpu cores
+    #[clap(long, short, value_parser)]
+    pub jobs: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct TuneArgs {
+    /// scenario file to start from, its boid parameters seed the first generation
+    #[clap(long, value_parser)]
+    pub scenario: Option<PathBuf>,
+
+    /// which boid parameters to evolve
+    #[clap(long, value_enum, default_value_t = Species::Both)]
+    pub species: Species,
+
+    /// what to score parameter sets on
+    #[clap(long, value_enum, default_value_t = Fitness::Hunting)]
+    pub fitness: Fitness,
+
+    /// polarization aimed for by the polarization fitnesses
+    #[clap(long, value_parser, default_value_t = 0.8)]
+    pub target_polarization: f32,
+
+    /// parameter sets in each generation
+    #[clap(long, value_parser, default_value_t = 16)]
+    pub population: usize,
+
+    #[clap(long, value_parser, default_value_t = 20)]
+    pub generations: usize,
+
+    /// fittest parameter sets carried over to the next generation unchanged
+    #[clap(long, value_parser, default_value_t = 2)]
+    pub elite: usize,
+
+    /// chance of each parameter being mutated in offspring
+    #[clap(long, value_parser, default_value_t = 0.2)]
+    pub mutation_rate: f32,
+
+    /// number of seeds each parameter set is scored over
+    #[clap(long, value_parser, default_value_t = 2)]
+    pub seeds: u64,
+
+    /// seed of the optimiser, simulation seeds count up from it
+    #[clap(long, value_parser, default_value_t = 0)]
+    pub seed: u64,
+
+    /// simulation seconds of each run
+    #[clap(long, value_parser, default_value_t = 120.)]
+    pub duration: f32,
+
+    /// number of best parameter sets to write out
+    #[clap(long, value_parser, default_value_t = 3)]
+    pub keep: usize,
+
+    /// directory to write the best scenarios and fitness history to
+    #[clap(long, short, value_parser, default_value = "tuned")]
+    pub output: PathBuf,
+
+    /// number of runs at once, defaults to the number of cpu cores
+    #[clap(long, short, value_parser)]
+    pub jobs: Option<usize>,
+}
+
+pub fn run(cli: Cli) -> Result<(), String> {
+    match cli.command {
+        Some(Command::Sweep(args)) => return sweep::sweep(args),
+        Some(Command::Tune(args)) => return tune::tune(args),
+        None => {},
+    }
+
+    if cli.speed <= 0. {
+        return Err(String::from("speed must be positive"));
+    }
+    if cli.duration.map_or(false, |duration| duration <= 0.) {
+        return Err(String::from("duration must be positive"));
+    }
+    let scenario = match &cli.scenario {
+        Some(path) => Some(
+            Scenario::load(path)
+                .map_err(|e| format!("failed to load scenario {}: {}", path.display(), e))?,
+        ),
+        None => None,
+    };
+
+    if cli.headless {
+        run_headless(&cli, scenario.unwrap_or_default())
+    } else {
+        app::app(&cli, scenario);
+        Ok(())
+    }
+}
+
+/// run a single scenario without a window and print its summary
+fn run_headless(cli: &Cli, scenario: Scenario) -> Result<(), String> {
+    // a random seed is still reported so that the run can be repeated
+    let seed = cli.seed.unwrap_or_else(rand::random);
+    eprintln!("running with seed {}", seed);
+
+    let mut app = headless::headless_app(seed);
+    if let Some(dir) = &cli.record {
+        app.world
+            .resource_mut::<Events<StartRecordingEvent>>()
+            .send(StartRecordingEvent {
+                dir: dir.clone(),
+                interval: DEFAULT_RECORD_INTERVAL,
+            });
+    }
+    if let Some(path) = &cli.record_replay {
+        app.world
+            .resource_mut::<Events<StartReplayRecordingEvent>>()
+            .send(StartReplayRecordingEvent {
+                path: path.clone(),
+                interval: DEFAULT_REPLAY_INTERVAL,
+            });
+    }
+    scenario.start(&mut app.world);
+    // clap makes sure headless runs have a duration
+    headless::run_app(&mut app, cli.duration.unwrap());
+
+    let orcas_end = headless::orca_count(&mut app.world);
+    let stats = app.world.resource::<Stats>();
+    let summary = headless::summarise(stats, orcas_end);
+    if let Some(recorder) = app.world.get_resource::<ReplayRecorder>() {
+        recorder
+            .save()
+            .map_err(|e| format!("failed to save replay {}: {}", recorder.path.display(), e))?;
+    }
+    if let Some(dir) = &cli.output {
+        headless::write_results(
+            dir,
+            stats,
+            app.world.resource::<SpaceUse>(),
+            app.world.resource::<PodPool>(),
+            &summary,
+        )?;
+    }
+
+    println!("{}\n{}", Summary::HEADER, summary.csv_row());
+    Ok(())
+}
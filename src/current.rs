@@ -0,0 +1,213 @@
+use std::{f32::consts::PI, fs, path::PathBuf};
+
+use bevy::prelude::*;
+use bevy_bobs::physics_2d::RigidBody;
+
+use crate::{ai::hunger::Hunger, sim::Simulation};
+
+/// hunger gained per second when swimming directly against a current of unit strength
+const CURRENT_HUNGER_COST: f32 = 0.0001;
+
+/// grid of current vectors in world units per second
+pub struct CurrentGrid {
+    /// world position of the south west corner of the grid
+    pub origin: Vec2,
+    pub cell_size: f32,
+    pub width: usize,
+    pub height: usize,
+    /// vectors in row major order, starting from the southern most row
+    pub vectors: Vec<Vec2>,
+}
+
+pub enum CurrentSource {
+    None,
+    /// same current everywhere
+    Constant(Vec2),
+    /// field of counter rotating eddies
+    Procedural {
+        strength: f32,
+        scale: f32,
+    },
+    Grid(CurrentGrid),
+}
+
+pub struct CurrentField {
+    pub source: CurrentSource,
+    /// length of a full tidal cycle in seconds, zero disables the tide
+    pub tide_period: f32,
+    /// how much the tide modulates the current, at one the current fully reverses on the ebb
+    pub tide_strength: f32,
+}
+
+/// how strongly an entity is carried along by the current
+#[derive(Component)]
+pub struct Drift(pub f32);
+
+pub struct LoadCurrentsEvent(pub PathBuf);
+
+pub struct CurrentPlugin;
+
+impl Plugin for CurrentPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CurrentField::default())
+            .add_event::<LoadCurrentsEvent>()
+            .add_system(load_currents)
+            .add_system(current_drift)
+            .add_system(current_cost);
+    }
+}
+
+impl Default for CurrentField {
+    fn default() -> Self {
+        Self {
+            source: CurrentSource::None,
+            tide_period: 0.,
+            tide_strength: 0.,
+        }
+    }
+}
+
+impl CurrentField {
+    /// current at a world position and simulation time
+    pub fn at(&self, pos: Vec2, time: f32) -> Vec2 {
+        let current = match &self.source {
+            CurrentSource::None => Vec2::ZERO,
+            CurrentSource::Constant(current) => *current,
+            CurrentSource::Procedural { strength, scale } => {
+                let p = pos / scale.max(f32::EPSILON);
+                Vec2::new(p.x.sin() * p.y.cos(), -p.x.cos() * p.y.sin()) * *strength
+            },
+            CurrentSource::Grid(grid) => grid.at(pos),
+        };
+        current * self.tide(time)
+    }
+
+    /// multiplier applied to the current by the tidal cycle
+    pub fn tide(&self, time: f32) -> f32 {
+        if self.tide_period <= 0. {
+            return 1.;
+        }
+        let phase = (2. * PI * time / self.tide_period).cos();
+        (1. - self.tide_strength) + self.tide_strength * phase
+    }
+}
+
+impl CurrentGrid {
+    pub fn at(&self, pos: Vec2) -> Vec2 {
+        let cell = ((pos - self.origin) / self.cell_size).floor();
+        if cell.x < 0. || cell.y < 0. {
+            return Vec2::ZERO;
+        }
+        let (x, y) = (cell.x as usize, cell.y as usize);
+        if x >= self.width || y >= self.height {
+            return Vec2::ZERO;
+        }
+        self.vectors[y * self.width + x]
+    }
+
+    pub fn load(path: &PathBuf) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::from_grid(&contents)
+    }
+
+    /// parse a current grid file
+    ///
+    /// Same layout as a bathymetry grid, except every cell is a `u,v` pair.
+    pub fn from_grid(contents: &str) -> Result<Self, String> {
+        let mut rows = contents
+            .lines()
+            .enumerate()
+            .map(|(lineno, line)| (lineno, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        let (lineno, header) = rows.next().ok_or("missing header")?;
+        let header = header
+            .split_whitespace()
+            .map(|s| s.parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("line {}: {}", lineno + 1, e))?;
+        if header.len() != 3 {
+            return Err("header should be `origin_x origin_y cell_size`".into());
+        }
+        if header[2] <= 0. {
+            return Err("cell size must be positive".into());
+        }
+
+        let rows = rows
+            .map(|(lineno, line)| {
+                line.split_whitespace()
+                    .map(|cell| -> Result<Vec2, String> {
+                        let (u, v) = cell
+                            .split_once(',')
+                            .ok_or_else(|| format!("line {}: expected `u,v`", lineno + 1))?;
+                        let u = u
+                            .parse::<f32>()
+                            .map_err(|e| format!("line {}: {}", lineno + 1, e))?;
+                        let v = v
+                            .parse::<f32>()
+                            .map_err(|e| format!("line {}: {}", lineno + 1, e))?;
+                        Ok(Vec2::new(u, v))
+                    })
+                    .collect::<Result<Vec<_>, String>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let width = rows.first().map(|row| row.len()).unwrap_or(0);
+        if rows.iter().any(|row| row.len() != width) {
+            return Err("all rows must have the same number of cells".into());
+        }
+
+        Ok(CurrentGrid {
+            origin: Vec2::new(header[0], header[1]),
+            cell_size: header[2],
+            width,
+            height: rows.len(),
+            vectors: rows.into_iter().flatten().collect(),
+        })
+    }
+}
+
+fn load_currents(mut events: EventReader<LoadCurrentsEvent>, mut field: ResMut<CurrentField>) {
+    for LoadCurrentsEvent(path) in events.iter() {
+        match CurrentGrid::load(path) {
+            Ok(grid) => {
+                info!(
+                    "loaded {}x{} current grid from {}",
+                    grid.width,
+                    grid.height,
+                    path.display()
+                );
+                field.source = CurrentSource::Grid(grid);
+            },
+            Err(e) => warn!("failed to load currents {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// carry entities along with the current
+fn current_drift(
+    time: Res<Time>,
+    sim: Res<Simulation>,
+    field: Res<CurrentField>,
+    mut query: Query<(&mut Transform, &Drift)>,
+) {
+    let dt = time.delta_seconds();
+    for (mut trans, Drift(drift)) in query.iter_mut() {
+        let current = field.at(trans.translation.truncate(), sim.time);
+        trans.translation += (current * *drift * dt).extend(0.);
+    }
+}
+
+/// swimming against the current burns energy
+fn current_cost(
+    time: Res<Time>,
+    sim: Res<Simulation>,
+    field: Res<CurrentField>,
+    mut query: Query<(&Transform, &RigidBody, &mut Hunger)>,
+) {
+    let dt = time.delta_seconds();
+    for (trans, rb, mut hunger) in query.iter_mut() {
+        let current = field.at(trans.translation.truncate(), sim.time);
+        let against = (-rb.velocity.normalize_or_zero().dot(current)).max(0.);
+        hunger.0 = (hunger.0 + against * CURRENT_HUNGER_COST * dt).clamp(0., 1.);
+    }
+}
@@ -0,0 +1,338 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+
+use bevy::prelude::*;
+use bevy_bobs::physics_2d::RigidBody;
+use pino_utils::some_or_return;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ai::{
+        diving::Breath,
+        hunger::{Hunger, KillEvent},
+        movement::Movement,
+    },
+    fish::Fish,
+    metrics::{CollectiveMetrics, GroupMetrics},
+    orca::{Orca, OrcaDeathEvent, PodChangeEvent, SpawnOrcaEvent},
+    sim::{RunSimEvent, Simulation},
+};
+
+/// simulation seconds between trajectory samples when none is given
+pub const DEFAULT_RECORD_INTERVAL: f32 = 1.;
+
+/// entities are written as their bits, generation included, so an animal spawned into a reused
+/// index is not mistaken for the one before it
+const TRAJECTORY_HEADER: &str = "run,time,entity,species,pod,x,y,depth,vx,vy,state,hunger";
+const EVENT_HEADER: &str = "run,time,event,entity,other,pod,new_pod";
+const METRICS_HEADER: &str =
+    "run,time,group,size,polarization,milling,nearest_neighbour,extent,cohesion";
+
+/// what a boid is doing, as written to recordings
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Activity {
+    Hunting,
+    Surfacing,
+    Still,
+    Swimming,
+}
+
+impl Activity {
+    pub fn of(movement: &Movement, rb: &RigidBody, breath: Option<&Breath>) -> Self {
+        if movement.target.is_some() {
+            Activity::Hunting
+        } else if breath.map(|breath| breath.surfacing).unwrap_or(false) {
+            Activity::Surfacing
+        } else if rb.velocity == Vec2::ZERO {
+            Activity::Still
+        } else {
+            Activity::Swimming
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Activity::Hunting => "hunting",
+            Activity::Surfacing => "surfacing",
+            Activity::Still => "still",
+            Activity::Swimming => "swimming",
+        }
+    }
+}
+
+/// start writing trajectories and events as csv files into a directory
+pub struct StartRecordingEvent {
+    pub dir: PathBuf,
+    /// simulation seconds between trajectory samples
+    pub interval: f32,
+}
+pub struct StopRecordingEvent;
+
+/// recording in progress, with rows written out as they are produced
+pub struct Recording {
+    pub dir: PathBuf,
+    pub interval: f32,
+    /// number of simulation restarts since the recording started
+    run: usize,
+    next_sample: f32,
+    trajectories: BufWriter<File>,
+    events: BufWriter<File>,
+    metrics: BufWriter<File>,
+}
+
+pub struct ExportPlugin;
+
+impl Plugin for ExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<StartRecordingEvent>()
+            .add_event::<StopRecordingEvent>()
+            .add_system(start_recording)
+            .add_system(stop_recording)
+            .add_system(recording_run)
+            .add_system(record_trajectories)
+            .add_system(record_events);
+    }
+}
+
+impl Recording {
+    pub fn create(dir: &PathBuf, interval: f32) -> Result<Self, String> {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        let open = |name: &str, header: &str| -> Result<BufWriter<File>, String> {
+            let file = File::create(dir.join(name)).map_err(|e| e.to_string())?;
+            let mut writer = BufWriter::new(file);
+            writeln!(writer, "{}", header).map_err(|e| e.to_string())?;
+            Ok(writer)
+        };
+
+        Ok(Self {
+            dir: dir.clone(),
+            interval: interval.max(f32::EPSILON),
+            run: 0,
+            next_sample: 0.,
+            trajectories: open("trajectories.csv", TRAJECTORY_HEADER)?,
+            events: open("events.csv", EVENT_HEADER)?,
+            metrics: open("metrics.csv", METRICS_HEADER)?,
+        })
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        self.trajectories.flush().map_err(|e| e.to_string())?;
+        self.events.flush().map_err(|e| e.to_string())?;
+        self.metrics.flush().map_err(|e| e.to_string())
+    }
+}
+
+fn start_recording(mut cmd: Commands, mut events: EventReader<StartRecordingEvent>) {
+    for event in events.iter() {
+        match Recording::create(&event.dir, event.interval) {
+            Ok(recording) => {
+                info!("recording to {}", event.dir.display());
+                cmd.insert_resource(recording);
+            },
+            Err(e) => warn!("failed to start recording {}: {}", event.dir.display(), e),
+        }
+    }
+}
+
+fn stop_recording(
+    mut cmd: Commands,
+    recording: Option<ResMut<Recording>>,
+    mut events: EventReader<StopRecordingEvent>,
+) {
+    if events.iter().count() == 0 {
+        return;
+    }
+    let mut recording = some_or_return!(recording);
+    if let Err(e) = recording.flush() {
+        warn!(
+            "failed to finish recording {}: {}",
+            recording.dir.display(),
+            e
+        );
+    }
+    info!("stopped recording to {}", recording.dir.display());
+    cmd.remove_resource::<Recording>();
+}
+
+/// restarting the simulation starts a new run within the same recording
+fn recording_run(recording: Option<ResMut<Recording>>, mut events: EventReader<RunSimEvent>) {
+    let mut recording = some_or_return!(recording);
+    for _ in events.iter() {
+        recording.run += 1;
+        recording.next_sample = 0.;
+    }
+}
+
+fn record_trajectories(
+    mut cmd: Commands,
+    sim: Res<Simulation>,
+    recording: Option<ResMut<Recording>>,
+    metrics: Res<CollectiveMetrics>,
+    query: Query<
+        (
+            Entity,
+            &Transform,
+            &Movement,
+            &RigidBody,
+            Option<&Orca>,
+            Option<&Hunger>,
+            Option<&Breath>,
+        ),
+        Or<(With<Orca>, With<Fish>)>,
+    >,
+) {
+    let mut recording = some_or_return!(recording);
+    if sim.time < recording.next_sample {
+        return;
+    }
+    recording.next_sample = sim.time + recording.interval;
+
+    let run = recording.run;
+    let mut rows = String::new();
+    for (entity, trans, movement, rb, orca, hunger, breath) in query.iter() {
+        let species = if orca.is_some() { "orca" } else { "fish" };
+        let pod = orca
+            .and_then(|orca| orca.pod_id)
+            .map(|pod_id| pod_id.to_string())
+            .unwrap_or_default();
+        let state = Activity::of(movement, rb, breath).name();
+        let hunger = hunger
+            .map(|hunger| hunger.0.to_string())
+            .unwrap_or_default();
+
+        rows.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{}\n",
+            run,
+            sim.time,
+            entity.to_bits(),
+            species,
+            pod,
+            trans.translation.x,
+            trans.translation.y,
+            movement.depth,
+            rb.velocity.x,
+            rb.velocity.y,
+            state,
+            hunger
+        ));
+    }
+
+    // collective motion of the fish school and each pod, with pods named by id
+    let mut metric_rows = String::new();
+    let mut pods: Vec<_> = metrics.pods.iter().collect();
+    pods.sort_by_key(|(pod_id, _)| **pod_id);
+    let mut groups: Vec<(String, &GroupMetrics)> = pods
+        .into_iter()
+        .map(|(pod_id, m)| (pod_id.to_string(), m))
+        .collect();
+    if let Some(fish) = &metrics.fish {
+        groups.insert(0, (String::from("fish"), fish));
+    }
+    for (group, m) in groups {
+        metric_rows.push_str(&format!("{},{},{},{}", run, sim.time, group, m.size));
+        for value in m.values() {
+            metric_rows.push_str(&format!(",{}", value));
+        }
+        metric_rows.push('\n');
+    }
+
+    let result = recording
+        .trajectories
+        .write_all(rows.as_bytes())
+        .and_then(|_| recording.metrics.write_all(metric_rows.as_bytes()))
+        .map_err(|e| e.to_string())
+        .and_then(|_| recording.flush());
+    if let Err(e) = result {
+        warn!("stopped recording {}: {}", recording.dir.display(), e);
+        cmd.remove_resource::<Recording>();
+    }
+}
+
+fn record_events(
+    mut cmd: Commands,
+    sim: Res<Simulation>,
+    recording: Option<ResMut<Recording>>,
+    orca_query: Query<&Orca>,
+    mut kill_events: EventReader<KillEvent>,
+    mut birth_events: EventReader<SpawnOrcaEvent>,
+    mut death_events: EventReader<OrcaDeathEvent>,
+    mut pod_change_events: EventReader<PodChangeEvent>,
+) {
+    let mut recording = some_or_return!(recording);
+
+    let run = recording.run;
+    let pod_of = |entity: Entity| {
+        orca_query
+            .get(entity)
+            .ok()
+            .and_then(|orca| orca.pod_id)
+            .map(|pod_id| pod_id.to_string())
+            .unwrap_or_default()
+    };
+
+    let mut rows = String::new();
+    let mut row = |event: &str, entity: Entity, other: String, pod: String, new_pod: String| {
+        rows.push_str(&format!(
+            "{},{},{},{},{},{},{}\n",
+            run,
+            sim.time,
+            event,
+            entity.to_bits(),
+            other,
+            pod,
+            new_pod
+        ));
+    };
+
+    for event in kill_events.iter() {
+        row(
+            "kill",
+            event.predator,
+            event.prey.to_bits().to_string(),
+            pod_of(event.predator),
+            String::new(),
+        );
+    }
+    // births are logged against the mother, as the calf is spawned afterwards
+    for event in birth_events.iter() {
+        row(
+            "birth",
+            event.mother,
+            String::new(),
+            pod_of(event.mother),
+            String::new(),
+        );
+    }
+    for OrcaDeathEvent(entity) in death_events.iter() {
+        row(
+            "death",
+            *entity,
+            String::new(),
+            pod_of(*entity),
+            String::new(),
+        );
+    }
+    for event in pod_change_events.iter() {
+        row(
+            "pod_change",
+            event.orca,
+            String::new(),
+            event
+                .from
+                .map(|pod_id| pod_id.to_string())
+                .unwrap_or_default(),
+            event.to.to_string(),
+        );
+    }
+
+    if rows.is_empty() {
+        return;
+    }
+    if let Err(e) = recording.events.write_all(rows.as_bytes()) {
+        warn!("stopped recording {}: {}", recording.dir.display(), e);
+        cmd.remove_resource::<Recording>();
+    }
+}
@@ -4,7 +4,10 @@ use bevy_bobs::physics_2d::RigidBody;
 use crate::ai::movement::{Movement, Sight};
 
 #[derive(Component)]
-pub struct Fish;
+pub struct Fish {
+    /// body length in metres
+    pub size: f32,
+}
 
 pub struct FishPlugin;
 
@@ -0,0 +1,176 @@
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use bevy::{asset::AssetPlugin, core::CorePlugin, prelude::*, sprite::ColorMaterial};
+use bevy_hanabi::EffectAsset;
+
+use crate::{
+    app::add_sim_plugins,
+    orca::{Orca, PodPool},
+    scenario::Scenario,
+    sim::SimRng,
+    space_use::SpaceUse,
+    stats::Stats,
+};
+
+/// length of a headless simulation step in seconds
+pub const HEADLESS_STEP: f32 = 1. / 30.;
+
+/// outcome of a headless run
+pub struct Summary {
+    pub orcas_start: usize,
+    pub orcas_end: usize,
+    /// fraction of the starting orcas still alive, counting every death as one of them
+    pub survival: f32,
+    pub kills: usize,
+    pub births: usize,
+    pub deaths: usize,
+    pub fish_end: usize,
+    /// pod polarization averaged over pods and over the run
+    pub pod_polarization: f32,
+    pub fish_polarization: f32,
+}
+
+impl Summary {
+    pub const HEADER: &'static str = "orcas_start,orcas_end,survival,kills,births,deaths,fish_end,\
+                                      pod_polarization,fish_polarization";
+
+    pub fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{}",
+            self.orcas_start,
+            self.orcas_end,
+            self.survival,
+            self.kills,
+            self.births,
+            self.deaths,
+            self.fish_end,
+            self.pod_polarization,
+            self.fish_polarization
+        )
+    }
+}
+
+/// simulation without a window or rendering, stepped by hand at a fixed timestep
+pub fn headless_app(seed: u64) -> App {
+    let mut app = App::new();
+    app.add_plugin(CorePlugin)
+        .add_plugin(TransformPlugin)
+        .add_plugin(HierarchyPlugin)
+        .add_plugin(AssetPlugin)
+        .add_asset::<Mesh>()
+        .add_asset::<ColorMaterial>()
+        .add_asset::<EffectAsset>()
+        .insert_resource(Time::default())
+        .insert_resource(ClearColor::default());
+
+    add_sim_plugins(&mut app);
+    app.insert_resource(SimRng::seeded(seed));
+    app
+}
+
+/// advance a headless app by `steps` fixed timesteps, starting from step number `from`
+pub fn step(app: &mut App, start: Instant, from: u32, steps: u32) {
+    for i in from..from + steps {
+        let now = start + Duration::from_secs_f32(i as f32 * HEADLESS_STEP);
+        app.world.resource_mut::<Time>().update_with_instant(now);
+        app.update();
+    }
+}
+
+/// advance a headless app by `duration` simulation seconds from its first update
+pub fn run_app(app: &mut App, duration: f32) {
+    let steps = (duration / HEADLESS_STEP).ceil() as u32;
+    step(app, Instant::now(), 0, steps + 1);
+}
+
+/// run a scenario headlessly for `duration` simulation seconds
+pub fn run(scenario: &Scenario, seed: u64, duration: f32) -> Summary {
+    let mut app = headless_app(seed);
+    scenario.start(&mut app.world);
+    run_app(&mut app, duration);
+
+    let orcas_end = orca_count(&mut app.world);
+    summarise(app.world.resource::<Stats>(), orcas_end)
+}
+
+pub fn orca_count(world: &mut World) -> usize {
+    world.query_filtered::<(), With<Orca>>().iter(world).count()
+}
+
+/// summary of a run from its stats and the number of orcas left at the end
+pub fn summarise(stats: &Stats, orcas_end: usize) -> Summary {
+    let orcas_start = stats.orcas_start.unwrap_or(0);
+
+    let mean = |values: Vec<f32>| {
+        if values.is_empty() {
+            0.
+        } else {
+            values.iter().sum::<f32>() / values.len() as f32
+        }
+    };
+    let pod_polarization = mean(
+        stats
+            .samples
+            .iter()
+            .filter(|sample| !sample.pod_metrics.is_empty())
+            .map(|sample| {
+                mean(
+                    sample
+                        .pod_metrics
+                        .values()
+                        .map(|m| m.polarization)
+                        .collect(),
+                )
+            })
+            .collect(),
+    );
+    let fish_polarization = mean(
+        stats
+            .samples
+            .iter()
+            .filter_map(|sample| sample.fish_metrics.map(|m| m.polarization))
+            .collect(),
+    );
+
+    Summary {
+        orcas_start,
+        orcas_end,
+        survival: if orcas_start > 0 {
+            orcas_start.saturating_sub(stats.deaths) as f32 / orcas_start as f32
+        } else {
+            0.
+        },
+        kills: stats.kills,
+        births: stats.births,
+        deaths: stats.deaths,
+        fish_end: stats
+            .samples
+            .last()
+            .map(|sample| sample.fish_count)
+            .unwrap_or(0),
+        pod_polarization,
+        fish_polarization,
+    }
+}
+
+/// write the sampled stats, space use and the summary of a run into a directory
+pub fn write_results(
+    dir: &PathBuf,
+    stats: &Stats,
+    space_use: &SpaceUse,
+    pod_pool: &PodPool,
+    summary: &Summary,
+) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+    stats.write_csv(&dir.join("stats.csv"), pod_pool)?;
+    space_use.write(dir, pod_pool)?;
+    fs::write(
+        dir.join("summary.csv"),
+        format!("{}\n{}\n", Summary::HEADER, summary.csv_row()),
+    )
+    .map_err(|e| e.to_string())
+}
@@ -1,16 +1,39 @@
 #![allow(unused)]
 #![allow(dead_code)]
+mod acoustic;
 mod ai;
 mod app;
+mod calendar;
 mod camera;
+mod capture;
+mod cli;
+mod current;
+mod export;
 mod fish;
+mod headless;
+mod map;
+mod metrics;
 mod names;
 mod orca;
+mod replay;
+mod scenario;
 mod sim;
+mod space_use;
+mod stats;
+mod sweep;
+mod tune;
 mod ui;
+mod vessel;
 
 mod prelude {}
 
+use clap::Parser;
+
+use crate::cli::Cli;
+
 fn main() {
-    app::app();
+    if let Err(e) = cli::run(Cli::parse()) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
 }
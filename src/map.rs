@@ -0,0 +1,514 @@
+use std::{fs, path::PathBuf};
+
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+
+use crate::{fish::Fish, orca::Orca};
+
+/// width in world units that a geojson map is scaled to fit
+const GEOJSON_EXTENT: f32 = 1000.;
+const LAND_COLOR: Color = Color::rgb(0.46, 0.62, 0.33);
+/// depth in metres used where no bathymetry grid has been loaded
+const DEFAULT_DEPTH: f32 = 200.;
+/// half the width of the world when no map has been loaded
+const DEFAULT_WORLD_EXTENT: f32 = 1000.;
+
+/// polygon of land that boids are not allowed to enter
+pub struct Obstacle {
+    pub points: Vec<Vec2>,
+}
+
+#[derive(Default)]
+pub struct ObstacleMap {
+    pub obstacles: Vec<Obstacle>,
+}
+
+/// grid of the maximum depth (in metres) of each cell of the world
+pub struct Bathymetry {
+    /// world position of the south west corner of the grid
+    pub origin: Vec2,
+    pub cell_size: f32,
+    pub width: usize,
+    pub height: usize,
+    /// depths in row major order, starting from the southern most row
+    pub depths: Vec<f32>,
+}
+
+/// rectangle holding the map and every boid that has swum outside of it
+///
+/// Grows as boids leave it and is recomputed whenever a map is loaded.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct WorldBounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+pub struct LoadMapEvent(pub PathBuf);
+pub struct LoadBathymetryEvent(pub PathBuf);
+
+#[derive(Component)]
+pub struct ObstacleShape;
+
+pub struct MapPlugin;
+
+impl Plugin for MapPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ObstacleMap::default())
+            .insert_resource(Bathymetry::default())
+            .insert_resource(WorldBounds::default())
+            .add_event::<LoadMapEvent>()
+            .add_event::<LoadBathymetryEvent>()
+            .add_system(load_map)
+            .add_system(load_bathymetry)
+            .add_system(render_obstacles)
+            .add_system(update_world_bounds);
+    }
+}
+
+impl Obstacle {
+    /// ray casting point in polygon test
+    pub fn contains(&self, point: Vec2) -> bool {
+        let mut inside = false;
+        let mut j = self.points.len().wrapping_sub(1);
+        for i in 0..self.points.len() {
+            let (a, b) = (self.points[i], self.points[j]);
+            if (a.y > point.y) != (b.y > point.y)
+                && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+            {
+                inside = !inside;
+            }
+            j = i;
+        }
+        inside
+    }
+
+    /// closest point on the boundary of the polygon
+    pub fn closest_point(&self, point: Vec2) -> Option<Vec2> {
+        let len = self.points.len();
+        (0..len)
+            .map(|i| closest_point_on_segment(point, self.points[i], self.points[(i + 1) % len]))
+            .min_by(|a, b| {
+                a.distance_squared(point)
+                    .total_cmp(&b.distance_squared(point))
+            })
+    }
+
+    /// whether the segment from `a` to `b` crosses the boundary of the polygon
+    pub fn intersects_segment(&self, a: Vec2, b: Vec2) -> bool {
+        let len = self.points.len();
+        (0..len).any(|i| segments_intersect(a, b, self.points[i], self.points[(i + 1) % len]))
+    }
+}
+
+impl ObstacleMap {
+    pub fn contains(&self, point: Vec2) -> bool {
+        self.obstacles.iter().any(|o| o.contains(point))
+    }
+
+    /// whether land blocks the straight line between two points
+    pub fn occludes(&self, from: Vec2, to: Vec2) -> bool {
+        self.obstacles
+            .iter()
+            .any(|o| o.intersects_segment(from, to) || o.contains(from) || o.contains(to))
+    }
+
+    /// force pushing a boid away from any land within `range`
+    ///
+    /// Land is sampled both at the current position and at a point looking ahead along the
+    /// velocity, so boids start turning before they run into a coastline.
+    pub fn avoidance_force(&self, pos: Vec2, velocity: Vec2, range: f32) -> Vec2 {
+        let lookahead = pos + velocity.normalize_or_zero() * range;
+
+        let mut force = Vec2::ZERO;
+        for obstacle in self.obstacles.iter() {
+            for sample in [pos, lookahead] {
+                let closest = match obstacle.closest_point(sample) {
+                    Some(closest) => closest,
+                    None => continue,
+                };
+                let dist = sample.distance(closest);
+                let away = (sample - closest).normalize_or_zero();
+
+                if obstacle.contains(sample) {
+                    // already on land, head for the nearest shore at full strength
+                    force -= away;
+                } else if dist < range {
+                    force += away * (1. - dist / range);
+                }
+            }
+        }
+        force
+    }
+
+    pub fn load(path: &PathBuf) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("geojson") | Some("json") => Self::from_geojson(&contents),
+            _ => Self::from_vertices(&contents),
+        }
+    }
+
+    /// parse a simple vertex file
+    ///
+    /// Each line holds one `x y` (or `x,y`) vertex in world units. Polygons are separated by
+    /// blank lines and lines starting with `#` are ignored.
+    pub fn from_vertices(contents: &str) -> Result<Self, String> {
+        let mut obstacles = vec![];
+        let mut points = vec![];
+        for (lineno, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+            if line.is_empty() {
+                if points.len() >= 3 {
+                    obstacles.push(Obstacle { points });
+                }
+                points = vec![];
+                continue;
+            }
+
+            let coords = line
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|s| !s.is_empty())
+                .map(|s| s.parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("line {}: {}", lineno + 1, e))?;
+            if coords.len() != 2 {
+                return Err(format!("line {}: expected two coordinates", lineno + 1));
+            }
+            points.push(Vec2::new(coords[0], coords[1]));
+        }
+        if points.len() >= 3 {
+            obstacles.push(Obstacle { points });
+        }
+
+        Ok(ObstacleMap { obstacles })
+    }
+
+    /// parse the outer rings of all Polygon and MultiPolygon geometries in a geojson file
+    ///
+    /// Longitude and latitude are projected equirectangularly about the centre of the map and
+    /// scaled so the map spans `GEOJSON_EXTENT` world units.
+    pub fn from_geojson(contents: &str) -> Result<Self, String> {
+        use serde_json::Value;
+
+        let root: Value = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+
+        let geometries: Vec<&Value> = match root["type"].as_str() {
+            Some("FeatureCollection") => root["features"]
+                .as_array()
+                .ok_or("missing features")?
+                .iter()
+                .map(|f| &f["geometry"])
+                .collect(),
+            Some("Feature") => vec![&root["geometry"]],
+            _ => vec![&root],
+        };
+
+        let mut rings: Vec<Vec<Vec2>> = vec![];
+        for geometry in geometries {
+            let polygons = match geometry["type"].as_str() {
+                Some("Polygon") => vec![&geometry["coordinates"]],
+                Some("MultiPolygon") => geometry["coordinates"]
+                    .as_array()
+                    .ok_or("malformed MultiPolygon")?
+                    .iter()
+                    .collect(),
+                _ => continue,
+            };
+            for polygon in polygons {
+                let outer = polygon
+                    .get(0)
+                    .and_then(|ring| ring.as_array())
+                    .ok_or("malformed Polygon")?;
+                let ring = outer
+                    .iter()
+                    .map(|p| -> Result<Vec2, &str> {
+                        let lon = p
+                            .get(0)
+                            .and_then(|v| v.as_f64())
+                            .ok_or("malformed position")?;
+                        let lat = p
+                            .get(1)
+                            .and_then(|v| v.as_f64())
+                            .ok_or("malformed position")?;
+                        Ok(Vec2::new(lon as f32, lat as f32))
+                    })
+                    .collect::<Result<Vec<_>, &str>>()?;
+                rings.push(ring);
+            }
+        }
+
+        if rings.is_empty() {
+            return Ok(ObstacleMap::default());
+        }
+
+        // project into world space
+        let (min, max) = rings.iter().flatten().fold(
+            (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+            |(min, max), p| (min.min(*p), max.max(*p)),
+        );
+        let center = (min + max) / 2.;
+        let lat_scale = center.y.to_radians().cos();
+        let size = Vec2::new((max.x - min.x) * lat_scale, max.y - min.y);
+        let scale = GEOJSON_EXTENT / size.max_element().max(f32::EPSILON);
+
+        let obstacles = rings
+            .into_iter()
+            .filter(|ring| ring.len() >= 3)
+            .map(|ring| Obstacle {
+                points: ring
+                    .into_iter()
+                    .map(|p| Vec2::new((p.x - center.x) * lat_scale, p.y - center.y) * scale)
+                    .collect(),
+            })
+            .collect();
+
+        Ok(ObstacleMap { obstacles })
+    }
+}
+
+impl Default for Bathymetry {
+    fn default() -> Self {
+        Self {
+            origin: Vec2::ZERO,
+            cell_size: 1.,
+            width: 0,
+            height: 0,
+            depths: vec![],
+        }
+    }
+}
+
+impl Bathymetry {
+    /// maximum depth at a world position, cells outside of the grid use `DEFAULT_DEPTH`
+    pub fn depth_at(&self, pos: Vec2) -> f32 {
+        let cell = ((pos - self.origin) / self.cell_size).floor();
+        if cell.x < 0. || cell.y < 0. {
+            return DEFAULT_DEPTH;
+        }
+        let (x, y) = (cell.x as usize, cell.y as usize);
+        if x >= self.width || y >= self.height {
+            return DEFAULT_DEPTH;
+        }
+        self.depths[y * self.width + x]
+    }
+
+    pub fn load(path: &PathBuf) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::from_grid(&contents)
+    }
+
+    /// parse a bathymetry grid file
+    ///
+    /// The first line holds `origin_x origin_y cell_size`, every following line is a row of
+    /// whitespace separated depths in metres, starting from the southern most row. Lines
+    /// starting with `#` are ignored.
+    pub fn from_grid(contents: &str) -> Result<Self, String> {
+        let mut rows = contents
+            .lines()
+            .enumerate()
+            .map(|(lineno, line)| (lineno, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(lineno, line)| {
+                line.split_whitespace()
+                    .map(|s| s.parse::<f32>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| format!("line {}: {}", lineno + 1, e))
+            });
+
+        let header = rows.next().ok_or("missing header")??;
+        if header.len() != 3 {
+            return Err("header should be `origin_x origin_y cell_size`".into());
+        }
+        if header[2] <= 0. {
+            return Err("cell size must be positive".into());
+        }
+
+        let rows = rows.collect::<Result<Vec<_>, _>>()?;
+        let width = rows.first().map(|row| row.len()).unwrap_or(0);
+        if rows.iter().any(|row| row.len() != width) {
+            return Err("all rows must have the same number of cells".into());
+        }
+
+        Ok(Bathymetry {
+            origin: Vec2::new(header[0], header[1]),
+            cell_size: header[2],
+            width,
+            height: rows.len(),
+            depths: rows.into_iter().flatten().map(|d| d.max(0.)).collect(),
+        })
+    }
+}
+
+impl Default for WorldBounds {
+    fn default() -> Self {
+        Self {
+            min: Vec2::splat(-DEFAULT_WORLD_EXTENT),
+            max: Vec2::splat(DEFAULT_WORLD_EXTENT),
+        }
+    }
+}
+
+impl WorldBounds {
+    /// bounds of the obstacles and bathymetry grid, or the default when neither is loaded
+    pub fn of_map(obstacle_map: &ObstacleMap, bathymetry: &Bathymetry) -> Self {
+        let mut points: Vec<Vec2> = obstacle_map
+            .obstacles
+            .iter()
+            .flat_map(|obstacle| obstacle.points.iter().copied())
+            .collect();
+        if bathymetry.width > 0 && bathymetry.height > 0 {
+            points.push(bathymetry.origin);
+            points.push(
+                bathymetry.origin
+                    + Vec2::new(bathymetry.width as f32, bathymetry.height as f32)
+                        * bathymetry.cell_size,
+            );
+        }
+
+        let mut points = points.into_iter();
+        let mut bounds = match points.next() {
+            Some(first) => Self {
+                min: first,
+                max: first,
+            },
+            None => return Self::default(),
+        };
+        for point in points {
+            bounds.include(point);
+        }
+        bounds
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+
+    pub fn include(&mut self, point: Vec2) {
+        self.min = self.min.min(point);
+        self.max = self.max.max(point);
+    }
+
+    pub fn center(&self) -> Vec2 {
+        (self.min + self.max) / 2.
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+
+    /// smallest square with the same centre that holds the bounds
+    pub fn square(&self) -> Self {
+        let half = Vec2::splat(self.size().max_element().max(f32::EPSILON) / 2.);
+        Self {
+            min: self.center() - half,
+            max: self.center() + half,
+        }
+    }
+}
+
+fn closest_point_on_segment(point: Vec2, a: Vec2, b: Vec2) -> Vec2 {
+    let ab = b - a;
+    let len_sq = ab.length_squared();
+    if len_sq == 0. {
+        return a;
+    }
+    let t = ((point - a).dot(ab) / len_sq).clamp(0., 1.);
+    a + ab * t
+}
+
+fn segments_intersect(p1: Vec2, p2: Vec2, q1: Vec2, q2: Vec2) -> bool {
+    let (r, s) = (p2 - p1, q2 - q1);
+    let denom = r.perp_dot(s);
+    if denom == 0. {
+        // parallel segments are treated as not crossing
+        return false;
+    }
+    let t = (q1 - p1).perp_dot(s) / denom;
+    let u = (q1 - p1).perp_dot(r) / denom;
+    (0. ..=1.).contains(&t) && (0. ..=1.).contains(&u)
+}
+
+fn load_map(mut cmd: Commands, mut events: EventReader<LoadMapEvent>) {
+    for LoadMapEvent(path) in events.iter() {
+        match ObstacleMap::load(path) {
+            Ok(map) => {
+                info!(
+                    "loaded {} obstacles from {}",
+                    map.obstacles.len(),
+                    path.display()
+                );
+                cmd.insert_resource(map);
+            },
+            Err(e) => warn!("failed to load map {}: {}", path.display(), e),
+        }
+    }
+}
+
+fn load_bathymetry(mut cmd: Commands, mut events: EventReader<LoadBathymetryEvent>) {
+    for LoadBathymetryEvent(path) in events.iter() {
+        match Bathymetry::load(path) {
+            Ok(bathymetry) => {
+                info!(
+                    "loaded {}x{} bathymetry grid from {}",
+                    bathymetry.width,
+                    bathymetry.height,
+                    path.display()
+                );
+                cmd.insert_resource(bathymetry);
+            },
+            Err(e) => warn!("failed to load bathymetry {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// start again from a newly loaded map, then take in any boid that swam outside of it
+fn update_world_bounds(
+    mut bounds: ResMut<WorldBounds>,
+    obstacle_map: Res<ObstacleMap>,
+    bathymetry: Res<Bathymetry>,
+    query: Query<&Transform, Or<(With<Orca>, With<Fish>)>>,
+) {
+    if obstacle_map.is_changed() || bathymetry.is_changed() {
+        *bounds = WorldBounds::of_map(&obstacle_map, &bathymetry);
+    }
+
+    for trans in query.iter() {
+        let pos = trans.translation.truncate();
+        // only write when growing, so readers can rely on change detection
+        if !bounds.contains(pos) {
+            bounds.include(pos);
+        }
+    }
+}
+
+fn render_obstacles(
+    mut cmd: Commands,
+    obstacle_map: Res<ObstacleMap>,
+    query: Query<Entity, With<ObstacleShape>>,
+) {
+    if !obstacle_map.is_changed() {
+        return;
+    }
+
+    for entity in &query {
+        cmd.entity(entity).despawn_recursive();
+    }
+
+    for obstacle in obstacle_map.obstacles.iter() {
+        cmd.spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Polygon {
+                points: obstacle.points.clone(),
+                closed: true,
+            },
+            DrawMode::Outlined {
+                fill_mode: FillMode::color(LAND_COLOR),
+                outline_mode: StrokeMode::new(Color::BLACK, 0.5),
+            },
+            Transform::from_xyz(0., 0., -10.),
+        ))
+        .insert(ObstacleShape);
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy_bobs::physics_2d::RigidBody;
+
+use crate::{
+    fish::Fish,
+    orca::{Orca, PodId},
+};
+
+/// collective motion of a group of boids
+#[derive(Clone, Copy, Default)]
+pub struct GroupMetrics {
+    pub size: usize,
+    /// length of the mean heading, one when every boid swims the same way
+    pub polarization: f32,
+    /// normalised angular momentum around the centroid, one when the group mills in a circle
+    pub milling: f32,
+    /// mean distance from each boid to its nearest neighbour
+    pub nearest_neighbour: f32,
+    /// distance from the centroid to the furthest boid
+    pub extent: f32,
+    /// mean distance from each boid to the centroid
+    pub cohesion: f32,
+}
+
+impl GroupMetrics {
+    pub const NAMES: [&'static str; 5] = [
+        "polarization",
+        "milling",
+        "nearest_neighbour",
+        "extent",
+        "cohesion",
+    ];
+
+    /// metrics of boids with the given positions and velocities, where stopped boids count as
+    /// having no heading
+    pub fn compute(positions: &[Vec2], velocities: &[Vec2]) -> Option<Self> {
+        let size = positions.len().min(velocities.len());
+        if size == 0 {
+            return None;
+        }
+        let positions = &positions[..size];
+        let headings: Vec<Vec2> = velocities[..size]
+            .iter()
+            .map(|v| v.normalize_or_zero())
+            .collect();
+
+        let centroid = positions.iter().copied().sum::<Vec2>() / size as f32;
+
+        let polarization = headings.iter().copied().sum::<Vec2>().length() / size as f32;
+
+        let milling = positions
+            .iter()
+            .zip(headings.iter())
+            .map(|(pos, heading)| (*pos - centroid).normalize_or_zero().perp_dot(*heading))
+            .sum::<f32>()
+            .abs()
+            / size as f32;
+
+        let nearest_neighbour = if size > 1 {
+            positions
+                .iter()
+                .enumerate()
+                .map(|(i, pos)| {
+                    positions
+                        .iter()
+                        .enumerate()
+                        .filter(|(j, _)| *j != i)
+                        .map(|(_, other)| pos.distance(*other))
+                        .fold(f32::MAX, f32::min)
+                })
+                .sum::<f32>()
+                / size as f32
+        } else {
+            0.
+        };
+
+        let dists: Vec<f32> = positions.iter().map(|pos| pos.distance(centroid)).collect();
+        let extent = dists.iter().copied().fold(0., f32::max);
+        let cohesion = dists.iter().sum::<f32>() / size as f32;
+
+        Some(Self {
+            size,
+            polarization,
+            milling,
+            nearest_neighbour,
+            extent,
+            cohesion,
+        })
+    }
+
+    /// values in the same order as `NAMES`
+    pub fn values(&self) -> [f32; 5] {
+        [
+            self.polarization,
+            self.milling,
+            self.nearest_neighbour,
+            self.extent,
+            self.cohesion,
+        ]
+    }
+}
+
+/// collective motion metrics of every pod and of the fish school, updated every frame
+#[derive(Default)]
+pub struct CollectiveMetrics {
+    pub pods: BTreeMap<PodId, GroupMetrics>,
+    pub fish: Option<GroupMetrics>,
+}
+
+pub struct MetricsPlugin;
+
+impl Plugin for MetricsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CollectiveMetrics::default())
+            .add_system(collective_metrics);
+    }
+}
+
+fn collective_metrics(
+    mut metrics: ResMut<CollectiveMetrics>,
+    orca_query: Query<(&Orca, &Transform, &RigidBody)>,
+    fish_query: Query<(&Transform, &RigidBody), With<Fish>>,
+) {
+    let mut pods: BTreeMap<PodId, (Vec<Vec2>, Vec<Vec2>)> = BTreeMap::new();
+    for (orca, trans, rb) in orca_query.iter() {
+        if let Some(pod_id) = orca.pod_id {
+            let (positions, velocities) = pods.entry(pod_id).or_default();
+            positions.push(trans.translation.truncate());
+            velocities.push(rb.velocity);
+        }
+    }
+    metrics.pods = pods
+        .into_iter()
+        .filter_map(|(pod_id, (positions, velocities))| {
+            GroupMetrics::compute(&positions, &velocities).map(|m| (pod_id, m))
+        })
+        .collect();
+
+    let (positions, velocities): (Vec<Vec2>, Vec<Vec2>) = fish_query
+        .iter()
+        .map(|(trans, rb)| (trans.translation.truncate(), rb.velocity))
+        .unzip();
+    metrics.fish = GroupMetrics::compute(&positions, &velocities);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_group_has_no_metrics() {
+        assert!(GroupMetrics::compute(&[], &[]).is_none());
+    }
+
+    #[test]
+    fn aligned_group_is_polarized() {
+        let positions = [Vec2::ZERO, Vec2::new(5., 0.), Vec2::new(0., 5.)];
+        let velocities = [Vec2::X, Vec2::X * 3., Vec2::X * 0.5];
+        let metrics = GroupMetrics::compute(&positions, &velocities).unwrap();
+        assert!((metrics.polarization - 1.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn opposed_group_is_not_polarized() {
+        let positions = [Vec2::ZERO, Vec2::new(5., 0.)];
+        let velocities = [Vec2::Y, -Vec2::Y * 2.];
+        let metrics = GroupMetrics::compute(&positions, &velocities).unwrap();
+        assert!(metrics.polarization < 1e-5);
+    }
+
+    #[test]
+    fn circling_group_mills() {
+        let positions = [Vec2::X, Vec2::Y, -Vec2::X, -Vec2::Y];
+        let velocities = [Vec2::Y, -Vec2::X, -Vec2::Y, Vec2::X];
+        let metrics = GroupMetrics::compute(&positions, &velocities).unwrap();
+        assert!((metrics.milling - 1.).abs() < 1e-5);
+        assert!(metrics.polarization < 1e-5);
+        assert!((metrics.extent - 1.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn stopped_boids_have_no_heading() {
+        let metrics = GroupMetrics::compute(&[Vec2::ZERO], &[Vec2::ZERO]).unwrap();
+        assert!(metrics.polarization.is_finite());
+        assert_eq!(metrics.nearest_neighbour, 0.);
+    }
+}
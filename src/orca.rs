@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashSet};
 
 use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
 use bevy_bobs::physics_2d::RigidBody;
@@ -7,13 +7,36 @@ use bevy_prototype_lyon::prelude::*;
 use big_brain::prelude::*;
 use pino_utils::enum_string;
 
-use crate::ai::{
-    hunger::{Hunger, Hungry, Hunt},
-    movement::{Movement, Sight},
+use crate::{
+    acoustic::{CallMemory, Dialect},
+    ai::{
+        diving::{Breath, DepthShaded},
+        hunger::{Hunger, Hungry, Hunt},
+        movement::{Movement, OrcaNeighbouring, Sight},
+    },
+    current::Drift,
+    names::{POD_NAME_ADJ, POD_NAME_NOUN},
+    sim::SystemRng,
 };
 
-pub struct SpawnOrcaEvent;
+/// pods larger than this split in two
+const FISSION_SIZE: usize = 40;
+
+/// calf born to the given mother
+pub struct SpawnOrcaEvent {
+    pub mother: Entity,
+}
 pub struct DespawnOrcaEvent(pub Entity);
+/// orca that has died, sent once per orca however many times its despawn was asked for
+pub struct OrcaDeathEvent(pub Entity);
+/// spawn a copy of an orca next to it
+pub struct CloneOrcaEvent(pub Entity);
+/// orca leaving one pod for another
+pub struct PodChangeEvent {
+    pub orca: Entity,
+    pub from: Option<PodId>,
+    pub to: PodId,
+}
 
 pub type PodId = usize;
 
@@ -25,7 +48,7 @@ pub enum Gender {
 }
 
 #[enum_string]
-#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy)]
 pub enum Type {
     Resident,
     Transient,
@@ -43,30 +66,163 @@ pub struct Orca {
     pub pod_id: Option<PodId>,
 }
 
-pub struct OrcaBundle {}
+/// scale of an orca's body, which grows with age
+pub fn body_scale(age: u32) -> Vec3 {
+    Vec3::splat(0.8 + age as f32 / 50.)
+}
+
+/// outline that tells males and females apart
+pub fn outline_color(gender: Gender) -> Color {
+    if gender == Gender::Male {
+        Color::BLACK
+    } else {
+        Color::GRAY
+    }
+}
+
+/// simulation components of an orca, without anything needed to render it
+#[derive(Bundle)]
+pub struct OrcaBundle {
+    pub orca: Orca,
+    pub neighbouring: OrcaNeighbouring,
+    pub hunger: Hunger,
+    pub sight: Sight,
+    pub movement: Movement,
+    pub breath: Breath,
+    pub drift: Drift,
+    pub rigid_body: RigidBody,
+    pub call_memory: CallMemory,
+}
 
 pub struct Pod {
     pub name: String,
+    pub color: Color,
+    pub orca_type: Type,
+    pub dialect: Dialect,
     pub members: Vec<Entity>,
 }
 
 #[derive(Deref, DerefMut)]
-pub struct PodPool(pub HashMap<PodId, Pod>);
+pub struct PodPool(pub BTreeMap<PodId, Pod>);
 
 pub struct OrcaPlugin;
 
 impl Plugin for OrcaPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(PodPool(HashMap::new()))
+        app.insert_resource(PodPool(BTreeMap::new()))
             .add_event::<SpawnOrcaEvent>()
             .add_event::<DespawnOrcaEvent>()
-            .add_system(despawn);
+            .add_event::<OrcaDeathEvent>()
+            .add_event::<CloneOrcaEvent>()
+            .add_event::<PodChangeEvent>()
+            .add_system(despawn)
+            .add_system(pod_fission);
     }
 }
 
-fn despawn(mut cmd: Commands, mut events: EventReader<DespawnOrcaEvent>) {
+fn despawn(
+    mut cmd: Commands,
+    mut events: EventReader<DespawnOrcaEvent>,
+    query: Query<&Orca>,
+    mut pod_pool: ResMut<PodPool>,
+    mut death_writer: EventWriter<OrcaDeathEvent>,
+) {
+    // the same orca can be asked to despawn again before its despawn has gone through
+    let mut dead: HashSet<Entity> = HashSet::new();
     for DespawnOrcaEvent(entity) in events.iter() {
-        println!("orca has passed away {:?}", entity);
+        let orca = match query.get(*entity) {
+            Ok(orca) if dead.insert(*entity) => orca,
+            _ => continue,
+        };
+        info!("orca has passed away {:?}", entity);
+        if let Some(pod) = orca.pod_id.and_then(|pod_id| pod_pool.get_mut(&pod_id)) {
+            pod.members.retain(|member| member != entity);
+        }
         cmd.entity(*entity).despawn_recursive();
+        death_writer.send(OrcaDeathEvent(*entity));
+    }
+}
+
+/// large pods split in two, with the half closest to a random member leaving to form a new pod
+fn pod_fission(
+    mut pod_pool: ResMut<PodPool>,
+    mut orca_query: Query<(&mut Orca, &Transform, &Children)>,
+    mut shade_query: Query<&mut DepthShaded>,
+    mut writer: EventWriter<PodChangeEvent>,
+    mut rng: Local<SystemRng>,
+) {
+    use rand::{seq::SliceRandom, Rng};
+
+    let rng = &mut rng.0;
+
+    let splitting: Vec<PodId> = pod_pool
+        .iter()
+        .filter(|(_, pod)| pod.members.len() > FISSION_SIZE)
+        .map(|(pod_id, _)| *pod_id)
+        .collect();
+
+    for pod_id in splitting {
+        let new_pod_id = pod_pool.keys().max().map(|id| id + 1).unwrap_or(0);
+        let pod = pod_pool.get_mut(&pod_id).unwrap();
+
+        let leader = match pod.members.choose(rng) {
+            Some(leader) => *leader,
+            None => continue,
+        };
+        let leader_pos = match orca_query.get(leader) {
+            Ok((_, trans, _)) => trans.translation,
+            Err(_) => continue,
+        };
+
+        let mut members = pod.members.clone();
+        members.sort_by(|a, b| {
+            let dist = |e: &Entity| {
+                orca_query
+                    .get(*e)
+                    .map(|(_, trans, _)| trans.translation.distance(leader_pos))
+                    .unwrap_or(f32::MAX)
+            };
+            dist(a).total_cmp(&dist(b))
+        });
+        let staying = members.split_off(members.len() / 2);
+        let leaving = members;
+        pod.members = staying;
+
+        let mut jitter = || rng.gen_range(-0.2f32..0.2);
+        let color = Color::rgb(
+            (pod.color.r() + jitter()).clamp(0., 1.),
+            (pod.color.g() + jitter()).clamp(0., 1.),
+            (pod.color.b() + jitter()).clamp(0., 1.),
+        );
+        let new_pod = Pod {
+            name: format!(
+                "{} {}",
+                POD_NAME_ADJ.choose(rng).unwrap(),
+                POD_NAME_NOUN.choose(rng).unwrap()
+            ),
+            color,
+            orca_type: pod.orca_type,
+            dialect: pod.dialect.inherit(rng),
+            members: leaving.clone(),
+        };
+        info!("pod {} split off from {}", new_pod.name, pod.name);
+
+        for member in leaving {
+            if let Ok((mut orca, _, children)) = orca_query.get_mut(member) {
+                writer.send(PodChangeEvent {
+                    orca: member,
+                    from: orca.pod_id,
+                    to: new_pod_id,
+                });
+                orca.pod_id = Some(new_pod_id);
+                for child in children.iter() {
+                    if let Ok(mut shade) = shade_query.get_mut(*child) {
+                        shade.0 = color;
+                    }
+                }
+            }
+        }
+
+        pod_pool.insert(new_pod_id, new_pod);
     }
 }
@@ -0,0 +1,772 @@
+use std::{
+    collections::{HashMap, HashSet},
+    f32::consts::PI,
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::PathBuf,
+};
+
+use bevy::{app::AppExit, prelude::*, sprite::MaterialMesh2dBundle};
+use bevy_bobs::physics_2d::RigidBody;
+use bevy_mod_picking::PickableBundle;
+use bevy_prototype_lyon::prelude::*;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use pino_utils::some_or_return;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ai::{
+        diving::Breath,
+        hunger::{Hunger, KillEvent},
+        movement::Movement,
+    },
+    export::Activity,
+    fish::Fish,
+    orca::{
+        body_scale, outline_color, Gender, Orca, OrcaDeathEvent, PodChangeEvent, PodId, PodPool,
+        SpawnOrcaEvent,
+    },
+    sim::{FishStock, RunSimEvent, Simulation},
+    vessel::Vessel,
+};
+
+/// simulation seconds between replay frames when none is given
+pub const DEFAULT_REPLAY_INTERVAL: f32 = 0.1;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BodyKind {
+    Orca,
+    Fish,
+}
+
+/// state of an orca or fish in a single frame
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BodyFrame {
+    /// entity of the body while it was recorded
+    pub id: u64,
+    pub kind: BodyKind,
+    pub pod: Option<PodId>,
+    pub position: [f32; 2],
+    /// heading in radians
+    pub rotation: f32,
+    pub depth: f32,
+    pub velocity: [f32; 2],
+    pub hunger: Option<f32>,
+    pub activity: Activity,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Frame {
+    pub time: f32,
+    pub bodies: Vec<BodyFrame>,
+}
+
+/// details of an orca that stay the same over a run
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OrcaInfo {
+    pub name: String,
+    pub gender: String,
+    pub age: u32,
+    pub mass: f32,
+    pub orca_type: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PodInfo {
+    pub name: String,
+    pub color: [f32; 4],
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReplayEventKind {
+    Kill,
+    Birth,
+    Death,
+    PodChange,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReplayEvent {
+    pub time: f32,
+    pub kind: ReplayEventKind,
+    pub entity: u64,
+    /// prey of a kill, or the new pod of a pod change
+    pub other: Option<u64>,
+}
+
+/// a recorded run, stored as gzipped bincode
+#[derive(Default, Serialize, Deserialize)]
+pub struct Replay {
+    pub interval: f32,
+    pub orcas: HashMap<u64, OrcaInfo>,
+    pub pods: HashMap<PodId, PodInfo>,
+    pub frames: Vec<Frame>,
+    pub events: Vec<ReplayEvent>,
+}
+
+impl Replay {
+    pub fn load(path: &PathBuf) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        bincode::deserialize_from(GzDecoder::new(BufReader::new(file))).map_err(|e| e.to_string())
+    }
+
+    pub fn save(&self, path: &PathBuf) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| e.to_string())?;
+        let mut encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
+        bincode::serialize_into(&mut encoder, self).map_err(|e| e.to_string())?;
+        encoder
+            .finish()
+            .and_then(|mut writer| writer.flush())
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.frames.last().map(|frame| frame.time).unwrap_or(0.)
+    }
+
+    /// index of the last frame at or before `time`
+    pub fn frame_index(&self, time: f32) -> usize {
+        self.frames
+            .partition_point(|frame| frame.time <= time)
+            .saturating_sub(1)
+    }
+
+    /// every body at `time`, interpolated between the frames either side of it
+    pub fn bodies_at(&self, time: f32) -> Vec<BodyFrame> {
+        let i = self.frame_index(time);
+        let a = match self.frames.get(i) {
+            Some(a) => a,
+            None => return vec![],
+        };
+        let b = match self.frames.get(i + 1) {
+            Some(b) if b.time > a.time => b,
+            _ => return a.bodies.clone(),
+        };
+
+        let t = ((time - a.time) / (b.time - a.time)).clamp(0., 1.);
+        let next: HashMap<u64, &BodyFrame> = b.bodies.iter().map(|body| (body.id, body)).collect();
+        a.bodies
+            .iter()
+            .map(|body| match next.get(&body.id) {
+                Some(next) => body.lerp(next, t),
+                None => body.clone(),
+            })
+            .collect()
+    }
+}
+
+impl BodyFrame {
+    pub fn position(&self) -> Vec2 {
+        Vec2::from(self.position)
+    }
+
+    fn lerp(&self, other: &BodyFrame, t: f32) -> BodyFrame {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        // turn the short way round
+        let turn = (other.rotation - self.rotation + PI).rem_euclid(2. * PI) - PI;
+
+        BodyFrame {
+            position: [
+                lerp(self.position[0], other.position[0]),
+                lerp(self.position[1], other.position[1]),
+            ],
+            rotation: self.rotation + turn * t,
+            depth: lerp(self.depth, other.depth),
+            velocity: [
+                lerp(self.velocity[0], other.velocity[0]),
+                lerp(self.velocity[1], other.velocity[1]),
+            ],
+            hunger: match (self.hunger, other.hunger) {
+                (Some(a), Some(b)) => Some(lerp(a, b)),
+                (hunger, _) => hunger,
+            },
+            ..self.clone()
+        }
+    }
+}
+
+/// start recording a replay, which is saved to `path` once stopped
+pub struct StartReplayRecordingEvent {
+    pub path: PathBuf,
+    /// simulation seconds between frames
+    pub interval: f32,
+}
+pub struct StopReplayRecordingEvent;
+
+/// replay being recorded, kept in memory until it is saved
+pub struct ReplayRecorder {
+    pub path: PathBuf,
+    pub replay: Replay,
+    next_sample: f32,
+}
+
+impl ReplayRecorder {
+    pub fn save(&self) -> Result<(), String> {
+        self.replay.save(&self.path)
+    }
+}
+
+/// play a replay back in place of the simulation
+pub struct LoadReplayEvent(pub PathBuf);
+pub struct StopPlaybackEvent;
+
+/// stand-in for a recorded body during playback
+#[derive(Component)]
+pub struct ReplayBody(pub u64);
+
+/// replay being played back, with the simulation cleared out while it plays
+pub struct Playback {
+    pub path: PathBuf,
+    pub replay: Replay,
+    pub time: f32,
+    pub playing: bool,
+    pub speed: f32,
+    /// bodies at the current time
+    pub bodies: Vec<BodyFrame>,
+    entities: HashMap<u64, Entity>,
+}
+
+impl Playback {
+    pub fn seek(&mut self, time: f32) {
+        self.time = time.clamp(0., self.replay.duration());
+    }
+
+    /// move by whole frames, backwards for negative counts
+    pub fn step(&mut self, frames: isize) {
+        let last = self.replay.frames.len() as isize - 1;
+        let i = (self.replay.frame_index(self.time) as isize + frames)
+            .min(last)
+            .max(0) as usize;
+        if let Some(frame) = self.replay.frames.get(i) {
+            self.time = frame.time;
+        }
+    }
+
+    pub fn body(&self, id: u64) -> Option<&BodyFrame> {
+        self.bodies.iter().find(|body| body.id == id)
+    }
+
+    pub fn entity(&self, id: u64) -> Option<Entity> {
+        self.entities.get(&id).copied()
+    }
+}
+
+/// recording replays, which also works headless
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<StartReplayRecordingEvent>()
+            .add_event::<StopReplayRecordingEvent>()
+            .add_system(start_replay_recording)
+            .add_system(stop_replay_recording)
+            .add_system(replay_recording_run)
+            .add_system(record_frames)
+            .add_system(record_replay_events)
+            .add_system_to_stage(CoreStage::Last, save_replay_on_exit);
+    }
+}
+
+/// playing replays back, which needs rendering
+pub struct PlaybackPlugin;
+
+impl Plugin for PlaybackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LoadReplayEvent>()
+            .add_event::<StopPlaybackEvent>()
+            .add_system(load_replay)
+            .add_system(stop_playback)
+            .add_system(advance_playback)
+            .add_system(apply_playback.after(advance_playback));
+    }
+}
+
+fn start_replay_recording(mut cmd: Commands, mut events: EventReader<StartReplayRecordingEvent>) {
+    for event in events.iter() {
+        info!("recording replay to {}", event.path.display());
+        cmd.insert_resource(ReplayRecorder {
+            path: event.path.clone(),
+            replay: Replay {
+                interval: event.interval.max(f32::EPSILON),
+                ..default()
+            },
+            next_sample: 0.,
+        });
+    }
+}
+
+fn stop_replay_recording(
+    mut cmd: Commands,
+    recorder: Option<Res<ReplayRecorder>>,
+    mut events: EventReader<StopReplayRecordingEvent>,
+) {
+    if events.iter().count() == 0 {
+        return;
+    }
+    let recorder = some_or_return!(recorder);
+    match recorder.save() {
+        Ok(()) => info!("saved replay to {}", recorder.path.display()),
+        Err(e) => warn!("failed to save replay {}: {}", recorder.path.display(), e),
+    }
+    cmd.remove_resource::<ReplayRecorder>();
+}
+
+/// a replay covers a single run, so restarting the simulation starts it over
+fn replay_recording_run(
+    recorder: Option<ResMut<ReplayRecorder>>,
+    mut events: EventReader<RunSimEvent>,
+) {
+    let mut recorder = some_or_return!(recorder);
+    for _ in events.iter() {
+        let interval = recorder.replay.interval;
+        recorder.replay = Replay {
+            interval,
+            ..default()
+        };
+        recorder.next_sample = 0.;
+    }
+}
+
+fn record_frames(
+    sim: Res<Simulation>,
+    recorder: Option<ResMut<ReplayRecorder>>,
+    pod_pool: Res<PodPool>,
+    query: Query<
+        (
+            Entity,
+            &Transform,
+            &Movement,
+            &RigidBody,
+            Option<&Orca>,
+            Option<&Hunger>,
+            Option<&Breath>,
+        ),
+        Or<(With<Orca>, With<Fish>)>,
+    >,
+) {
+    let mut recorder = some_or_return!(recorder);
+    if sim.time < recorder.next_sample {
+        return;
+    }
+    recorder.next_sample = sim.time + recorder.replay.interval;
+
+    let replay = &mut recorder.replay;
+    // pods can form partway through a run
+    for (pod_id, pod) in pod_pool.iter() {
+        replay.pods.insert(
+            *pod_id,
+            PodInfo {
+                name: pod.name.clone(),
+                color: pod.color.as_rgba_f32(),
+            },
+        );
+    }
+
+    let mut bodies = vec![];
+    for (entity, trans, movement, rb, orca, hunger, breath) in query.iter() {
+        let id = entity.to_bits();
+        if let Some(orca) = orca {
+            replay.orcas.entry(id).or_insert_with(|| OrcaInfo {
+                name: orca.name.clone(),
+                gender: orca.gender.to_string(),
+                age: orca.age,
+                mass: orca.mass,
+                orca_type: orca.orca_type.to_string(),
+            });
+        }
+
+        bodies.push(BodyFrame {
+            id,
+            kind: if orca.is_some() {
+                BodyKind::Orca
+            } else {
+                BodyKind::Fish
+            },
+            pod: orca.and_then(|orca| orca.pod_id),
+            position: trans.translation.truncate().into(),
+            rotation: trans.rotation.to_euler(EulerRot::XYZ).2,
+            depth: movement.depth,
+            velocity: rb.velocity.into(),
+            hunger: hunger.map(|hunger| hunger.0),
+            activity: Activity::of(movement, rb, breath),
+        });
+    }
+    replay.frames.push(Frame {
+        time: sim.time,
+        bodies,
+    });
+}
+
+fn record_replay_events(
+    sim: Res<Simulation>,
+    recorder: Option<ResMut<ReplayRecorder>>,
+    mut kill_events: EventReader<KillEvent>,
+    mut birth_events: EventReader<SpawnOrcaEvent>,
+    mut death_events: EventReader<OrcaDeathEvent>,
+    mut pod_change_events: EventReader<PodChangeEvent>,
+) {
+    let mut recorder = some_or_return!(recorder);
+
+    let mut event = |kind: ReplayEventKind, entity: Entity, other: Option<u64>| {
+        recorder.replay.events.push(ReplayEvent {
+            time: sim.time,
+            kind,
+            entity: entity.to_bits(),
+            other,
+        });
+    };
+    for kill in kill_events.iter() {
+        event(
+            ReplayEventKind::Kill,
+            kill.predator,
+            Some(kill.prey.to_bits()),
+        );
+    }
+    for birth in birth_events.iter() {
+        event(ReplayEventKind::Birth, birth.mother, None);
+    }
+    for OrcaDeathEvent(entity) in death_events.iter() {
+        event(ReplayEventKind::Death, *entity, None);
+    }
+    for pod_change in pod_change_events.iter() {
+        event(
+            ReplayEventKind::PodChange,
+            pod_change.orca,
+            Some(pod_change.to as u64),
+        );
+    }
+}
+
+fn save_replay_on_exit(recorder: Option<Res<ReplayRecorder>>, mut events: EventReader<AppExit>) {
+    if events.iter().count() == 0 {
+        return;
+    }
+    let recorder = some_or_return!(recorder);
+    match recorder.save() {
+        Ok(()) => info!("saved replay to {}", recorder.path.display()),
+        Err(e) => warn!("failed to save replay {}: {}", recorder.path.display(), e),
+    }
+}
+
+fn load_replay(
+    mut cmd: Commands,
+    mut sim: ResMut<Simulation>,
+    mut pod_pool: ResMut<PodPool>,
+    sim_query: Query<Entity, Or<(With<Orca>, With<Fish>, With<Vessel>)>>,
+    body_query: Query<Entity, With<ReplayBody>>,
+    mut events: EventReader<LoadReplayEvent>,
+) {
+    for LoadReplayEvent(path) in events.iter() {
+        let replay = match Replay::load(path) {
+            Ok(replay) => replay,
+            Err(e) => {
+                warn!("failed to load replay {}: {}", path.display(), e);
+                continue;
+            },
+        };
+
+        // clear out the simulation so that nothing moves but the replay
+        for entity in sim_query.iter().chain(body_query.iter()) {
+            cmd.entity(entity).despawn_recursive();
+        }
+        pod_pool.clear();
+        cmd.remove_resource::<FishStock>();
+        sim.stop();
+
+        info!("playing back {}", path.display());
+        cmd.insert_resource(Playback {
+            path: path.clone(),
+            replay,
+            time: 0.,
+            playing: true,
+            speed: 1.,
+            bodies: vec![],
+            entities: HashMap::new(),
+        });
+    }
+}
+
+/// playback ends when asked to or when a new run starts
+fn stop_playback(
+    mut cmd: Commands,
+    body_query: Query<Entity, With<ReplayBody>>,
+    mut stop_events: EventReader<StopPlaybackEvent>,
+    mut run_events: EventReader<RunSimEvent>,
+) {
+    if stop_events.iter().count() + run_events.iter().count() == 0 {
+        return;
+    }
+    for entity in body_query.iter() {
+        cmd.entity(entity).despawn_recursive();
+    }
+    cmd.remove_resource::<Playback>();
+}
+
+fn advance_playback(time: Res<Time>, playback: Option<ResMut<Playback>>) {
+    let mut playback = some_or_return!(playback);
+    if !playback.playing {
+        return;
+    }
+
+    let next = playback.time + time.delta_seconds() * playback.speed;
+    playback.seek(next);
+    if playback.time >= playback.replay.duration() {
+        playback.playing = false;
+    }
+}
+
+fn apply_playback(
+    mut cmd: Commands,
+    playback: Option<ResMut<Playback>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut query: Query<&mut Transform, With<ReplayBody>>,
+) {
+    let mut playback = some_or_return!(playback);
+    let playback = &mut *playback;
+
+    let bodies = playback.replay.bodies_at(playback.time);
+
+    // bodies that are gone by now, such as fish that have been eaten
+    let present: HashSet<u64> = bodies.iter().map(|body| body.id).collect();
+    playback.entities.retain(|id, entity| {
+        let keep = present.contains(id);
+        if !keep {
+            cmd.entity(*entity).despawn_recursive();
+        }
+        keep
+    });
+
+    for body in bodies.iter() {
+        match playback.entities.get(&body.id) {
+            Some(entity) => {
+                if let Ok(mut trans) = query.get_mut(*entity) {
+                    trans.translation = body.position().extend(trans.translation.z);
+                    trans.rotation = Quat::from_rotation_z(body.rotation);
+                }
+            },
+            None => {
+                let entity = spawn_body(
+                    &mut cmd,
+                    &playback.replay,
+                    body,
+                    &mut meshes,
+                    &mut materials,
+                );
+                playback.entities.insert(body.id, entity);
+            },
+        }
+    }
+    playback.bodies = bodies;
+}
+
+/// stand-in drawn like the orca or fish it replays
+fn spawn_body(
+    cmd: &mut Commands,
+    replay: &Replay,
+    body: &BodyFrame,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+) -> Entity {
+    let transform = Transform::from_translation(body.position().extend(0.))
+        .with_rotation(Quat::from_rotation_z(body.rotation));
+
+    match body.kind {
+        BodyKind::Orca => {
+            let color = body
+                .pod
+                .and_then(|pod_id| replay.pods.get(&pod_id))
+                .map(|pod| Color::from(pod.color))
+                .unwrap_or(Color::WHITE);
+            let info = replay.orcas.get(&body.id);
+            let gender = match info {
+                Some(info) if info.gender == Gender::Male.to_string() => Gender::Male,
+                _ => Gender::Female,
+            };
+            let age = info.map(|info| info.age).unwrap_or(0);
+
+            cmd.spawn()
+                .insert(ReplayBody(body.id))
+                .insert_bundle(MaterialMesh2dBundle {
+                    mesh: meshes.add(Mesh::from(shape::Circle::new(3.))).into(),
+                    transform,
+                    material: materials.add(ColorMaterial::from(Color::NONE)),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn_bundle(GeometryBuilder::build_as(
+                        &RegularPolygon {
+                            sides: 3,
+                            ..default()
+                        },
+                        DrawMode::Outlined {
+                            fill_mode: FillMode::color(color),
+                            outline_mode: StrokeMode::new(outline_color(gender), 0.1),
+                        },
+                        Transform::from_scale(body_scale(age)),
+                    ));
+                })
+                .insert_bundle(PickableBundle::default())
+                .id()
+        },
+        BodyKind::Fish => cmd
+            .spawn()
+            .insert(ReplayBody(body.id))
+            .insert_bundle(GeometryBuilder::build_as(
+                &shapes::Circle {
+                    radius: 1.5,
+                    ..default()
+                },
+                DrawMode::Fill(FillMode::color(Color::NONE)),
+                transform,
+            ))
+            .with_children(|parent| {
+                parent.spawn_bundle(GeometryBuilder::build_as(
+                    &RegularPolygon {
+                        sides: 4,
+                        ..default()
+                    },
+                    DrawMode::Outlined {
+                        fill_mode: FillMode::color(Color::RED),
+                        outline_mode: StrokeMode::new(Color::BLACK, 0.1),
+                    },
+                    Transform::default(),
+                ));
+            })
+            .insert_bundle(PickableBundle::default())
+            .id(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(id: u64, x: f32, rotation: f32) -> BodyFrame {
+        BodyFrame {
+            id,
+            kind: BodyKind::Orca,
+            pod: None,
+            position: [x, 0.],
+            rotation,
+            depth: 0.,
+            velocity: [0., 0.],
+            hunger: None,
+            activity: Activity::Swimming,
+        }
+    }
+
+    fn replay(frames: Vec<(f32, Vec<BodyFrame>)>) -> Replay {
+        Replay {
+            interval: 1.,
+            frames: frames
+                .into_iter()
+                .map(|(time, bodies)| Frame { time, bodies })
+                .collect(),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn rotation_turns_the_short_way_across_pi() {
+        let a = body(0, 0., PI - 0.1);
+        let b = body(0, 0., -PI + 0.1);
+        let mid = a.lerp(&b, 0.5);
+        // half way is pointing straight back, not straight ahead
+        assert!((mid.rotation.rem_euclid(2. * PI) - PI).abs() < 1e-4);
+
+        let back = b.lerp(&a, 0.5);
+        assert!((back.rotation.rem_euclid(2. * PI) - PI).abs() < 1e-4);
+    }
+
+    #[test]
+    fn lerp_interpolates_position() {
+        let mid = body(0, 0., 0.).lerp(&body(0, 10., 0.), 0.25);
+        assert_eq!(mid.position, [2.5, 0.]);
+    }
+
+    #[test]
+    fn frame_index_is_clamped_to_the_recording() {
+        let replay = replay(vec![(1., vec![]), (2., vec![]), (3., vec![])]);
+        assert_eq!(replay.frame_index(0.), 0);
+        assert_eq!(replay.frame_index(1.), 0);
+        assert_eq!(replay.frame_index(2.5), 1);
+        assert_eq!(replay.frame_index(3.), 2);
+        assert_eq!(replay.frame_index(10.), 2);
+    }
+
+    #[test]
+    fn empty_replay_has_no_bodies() {
+        assert!(Replay::default().bodies_at(1.).is_empty());
+    }
+
+    #[test]
+    fn bodies_past_the_end_hold_the_last_frame() {
+        let replay = replay(vec![
+            (0., vec![body(0, 0., 0.)]),
+            (1., vec![body(0, 10., 0.)]),
+        ]);
+        let bodies = replay.bodies_at(5.);
+        assert_eq!(bodies.len(), 1);
+        assert_eq!(bodies[0].position, [10., 0.]);
+    }
+
+    #[test]
+    fn appearing_and_disappearing_bodies() {
+        // body 1 dies and body 2 is born between the frames
+        let replay = replay(vec![
+            (0., vec![body(0, 0., 0.), body(1, 5., 0.)]),
+            (1., vec![body(0, 10., 0.), body(2, 20., 0.)]),
+        ]);
+        let bodies = replay.bodies_at(0.5);
+        let ids: Vec<u64> = bodies.iter().map(|body| body.id).collect();
+        assert_eq!(ids, vec![0, 1]);
+        assert_eq!(bodies[0].position, [5., 0.]);
+        // held where it was last seen
+        assert_eq!(bodies[1].position, [5., 0.]);
+
+        let ids: Vec<u64> = replay.bodies_at(1.).iter().map(|body| body.id).collect();
+        assert_eq!(ids, vec![0, 2]);
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let mut original = replay(vec![
+            (0., vec![body(0, 0., 0.)]),
+            (0.5, vec![body(0, 3., 1.)]),
+        ]);
+        original.orcas.insert(
+            0,
+            OrcaInfo {
+                name: "Granny".into(),
+                gender: "Female".into(),
+                age: 40,
+                mass: 3000.,
+                orca_type: "Resident".into(),
+            },
+        );
+        original.events.push(ReplayEvent {
+            time: 0.5,
+            kind: ReplayEventKind::Birth,
+            entity: 0,
+            other: None,
+        });
+
+        let path =
+            std::env::temp_dir().join(format!("sakamata-replay-{}.bin.gz", std::process::id()));
+        original.save(&path).unwrap();
+        let loaded = Replay::load(&path);
+        let _ = std::fs::remove_file(&path);
+        let loaded = loaded.unwrap();
+
+        assert_eq!(loaded.interval, original.interval);
+        assert_eq!(loaded.frames.len(), 2);
+        assert_eq!(loaded.frames[1].time, 0.5);
+        assert_eq!(loaded.frames[1].bodies[0].position, [3., 0.]);
+        assert_eq!(loaded.frames[1].bodies[0].rotation, 1.);
+        assert_eq!(loaded.orcas[&0].name, "Granny");
+        assert_eq!(loaded.events.len(), 1);
+        assert!(loaded.events[0].kind == ReplayEventKind::Birth);
+        assert_eq!(loaded.duration(), 0.5);
+    }
+}
@@ -0,0 +1,194 @@
+use std::{fs, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ai::{decision::LoadThinkersEvent, movement::BoidParams},
+    current::LoadCurrentsEvent,
+    map::{LoadBathymetryEvent, LoadMapEvent},
+    sim::RunSimEvent,
+};
+
+/// names of every parameter that can be read and set by name, such as in parameter sweeps
+pub const PARAMS: &[&str] = &[
+    "pod_count",
+    "pod_size",
+    "pod_size_min",
+    "pod_size_max",
+    "fish_count",
+    "fish_depth_min",
+    "fish_depth_max",
+    "cargo_count",
+    "whale_watch_count",
+    "cargo_noise",
+    "whale_watch_noise",
+    "orca.coherence",
+    "orca.alignment",
+    "orca.seperation",
+    "orca.randomness",
+    "orca.view_range",
+    "orca.view_angle",
+    "orca.peripheral_falloff",
+    "fish.coherence",
+    "fish.alignment",
+    "fish.seperation",
+    "fish.randomness",
+    "fish.view_range",
+    "fish.view_angle",
+    "fish.peripheral_falloff",
+];
+
+/// everything needed to set up a run, stored as json
+///
+/// Any field left out of the file takes its default value.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scenario {
+    pub sim: RunSimEvent,
+    pub map: Option<PathBuf>,
+    pub bathymetry: Option<PathBuf>,
+    pub currents: Option<PathBuf>,
+    /// thinker definitions per ecotype
+    pub thinkers: Option<PathBuf>,
+}
+
+impl Scenario {
+    pub fn load(path: &PathBuf) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let scenario: Self = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+
+        // maps are only loaded once the app is running, so catch missing ones up front
+        for file in [
+            &scenario.map,
+            &scenario.bathymetry,
+            &scenario.currents,
+            &scenario.thinkers,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if !file.exists() {
+                return Err(format!("{} does not exist", file.display()));
+            }
+        }
+        Ok(scenario)
+    }
+
+    pub fn save(&self, path: &PathBuf) -> Result<(), String> {
+        let contents = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+
+    /// value of a parameter listed in `PARAMS`
+    pub fn get(&self, param: &str) -> Result<f32, String> {
+        let sim = &self.sim;
+        Ok(match param {
+            "pod_count" => sim.pod_count as f32,
+            "pod_size" | "pod_size_min" => sim.pod_size_min as f32,
+            "pod_size_max" => sim.pod_size_max as f32,
+            "fish_count" => sim.fish_count as f32,
+            "fish_depth_min" => sim.fish_depth_min,
+            "fish_depth_max" => sim.fish_depth_max,
+            "cargo_count" => sim.cargo_count as f32,
+            "whale_watch_count" => sim.whale_watch_count as f32,
+            "cargo_noise" => sim.cargo_noise,
+            "whale_watch_noise" => sim.whale_watch_noise,
+            _ => {
+                let (species, name) = split_param(param)?;
+                let mut params = match species {
+                    "orca" => sim.orca_params,
+                    "fish" => sim.fish_params,
+                    _ => return Err(unknown_param(param)),
+                };
+                let value = *boid_param(&mut params, name)?;
+                value
+            },
+        })
+    }
+
+    /// set a parameter listed in `PARAMS`, rounding it for parameters that are counts
+    pub fn set(&mut self, param: &str, value: f32) -> Result<(), String> {
+        let count = value.round().max(0.) as usize;
+        let sim = &mut self.sim;
+        match param {
+            "pod_count" => sim.pod_count = count,
+            "pod_size" => {
+                sim.pod_size_min = count;
+                sim.pod_size_max = count;
+            },
+            "pod_size_min" => sim.pod_size_min = count,
+            "pod_size_max" => sim.pod_size_max = count,
+            "fish_count" => sim.fish_count = count,
+            "fish_depth_min" => sim.fish_depth_min = value,
+            "fish_depth_max" => sim.fish_depth_max = value,
+            "cargo_count" => sim.cargo_count = count,
+            "whale_watch_count" => sim.whale_watch_count = count,
+            "cargo_noise" => sim.cargo_noise = value,
+            "whale_watch_noise" => sim.whale_watch_noise = value,
+            _ => {
+                let (species, name) = split_param(param)?;
+                let params = match species {
+                    "orca" => &mut sim.orca_params,
+                    "fish" => &mut sim.fish_params,
+                    _ => return Err(unknown_param(param)),
+                };
+                *boid_param(params, name)? = value;
+            },
+        }
+        Ok(())
+    }
+
+    /// queue up loading the scenario's maps and starting the run
+    pub fn start(&self, world: &mut World) {
+        if let Some(path) = &self.map {
+            world
+                .resource_mut::<Events<LoadMapEvent>>()
+                .send(LoadMapEvent(path.clone()));
+        }
+        if let Some(path) = &self.bathymetry {
+            world
+                .resource_mut::<Events<LoadBathymetryEvent>>()
+                .send(LoadBathymetryEvent(path.clone()));
+        }
+        if let Some(path) = &self.currents {
+            world
+                .resource_mut::<Events<LoadCurrentsEvent>>()
+                .send(LoadCurrentsEvent(path.clone()));
+        }
+        if let Some(path) = &self.thinkers {
+            world
+                .resource_mut::<Events<LoadThinkersEvent>>()
+                .send(LoadThinkersEvent(path.clone()));
+        }
+        world
+            .resource_mut::<Events<RunSimEvent>>()
+            .send(self.sim.clone());
+    }
+}
+
+/// split a boid parameter such as `orca.coherence` into its species and name
+fn split_param(param: &str) -> Result<(&str, &str), String> {
+    param.split_once('.').ok_or_else(|| unknown_param(param))
+}
+
+fn boid_param<'a>(params: &'a mut BoidParams, name: &str) -> Result<&'a mut f32, String> {
+    Ok(match name {
+        "coherence" => &mut params.coherence,
+        "alignment" => &mut params.alignment,
+        "seperation" => &mut params.seperation,
+        "randomness" => &mut params.randomness,
+        "view_range" => &mut params.view_range,
+        "view_angle" => &mut params.view_angle,
+        "peripheral_falloff" => &mut params.peripheral_falloff,
+        _ => return Err(format!("unknown boid parameter '{}'", name)),
+    })
+}
+
+fn unknown_param(param: &str) -> String {
+    format!(
+        "unknown parameter '{}', expected one of: {}",
+        param,
+        PARAMS.join(", ")
+    )
+}
@@ -1,6 +1,13 @@
-use std::ops::Range;
+use std::{
+    ops::Range,
+    time::{Duration, Instant},
+};
 
-use bevy::{prelude::*, sprite::MaterialMesh2dBundle, time::Stopwatch};
+use bevy::{
+    prelude::*,
+    sprite::MaterialMesh2dBundle,
+    time::{Stopwatch, TimeSystem},
+};
 use bevy_bobs::{component::lifetime, physics_2d::RigidBody};
 use bevy_hanabi::{
     ColorOverLifetimeModifier, EffectAsset, Gradient, ParticleEffect, ParticleEffectBundle,
@@ -9,28 +16,122 @@ use bevy_hanabi::{
 };
 use bevy_mod_picking::PickableBundle;
 use bevy_prototype_lyon::prelude::*;
-use big_brain::prelude::*;
 use iyes_loopless::prelude::*;
-use pino_utils::enum_string;
+use pino_utils::{enum_string, some_or_return};
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    acoustic::{CallMemory, Dialect},
     ai::{
-        hunger::{Hunger, Hungry, Hunt},
-        movement::{BoidParams, FishNeighbouring, Movement, OrcaNeighbouring, Sight},
+        decision::ThinkerDef,
+        diving::{Breath, DepthShaded},
+        echolocation::{Echolocation, PassiveListening},
+        hunger::Hunger,
+        movement::{BoidForces, BoidParams, FishNeighbouring, Movement, OrcaNeighbouring, Sight},
     },
+    calendar::{Calendar, CalendarParams},
+    current::Drift,
     fish::Fish,
+    map::Bathymetry,
     names::*,
-    orca::{Gender, Orca, Pod, PodPool, Type},
+    orca::{
+        body_scale, outline_color, CloneOrcaEvent, Gender, Orca, OrcaBundle, Pod, PodPool,
+        SpawnOrcaEvent, Type,
+    },
 };
 
+/// longest time in seconds an orca can stay underwater
+const ORCA_BREATH_HOLD: f32 = 60.;
+/// fraction of the current that carries each species
+const ORCA_DRIFT: f32 = 0.3;
+const FISH_DRIFT: f32 = 1.;
+/// mass of a newborn calf in kg
+const CALF_MASS: f32 = 180.;
+/// consecutive pods that belong to the same clan and share calls
+const PODS_PER_CLAN: usize = 3;
+/// fish spawned per second while the population is below the seasonal abundance
+const FISH_RESTOCK_RATE: f32 = 2.;
+
+/// seeds the `SystemRng` of every system, so that a run can be repeated from its seed
+#[derive(Deref, DerefMut)]
+pub struct SimRng(pub StdRng);
+
+impl SimRng {
+    pub fn seeded(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl Default for SimRng {
+    fn default() -> Self {
+        Self(StdRng::from_entropy())
+    }
+}
+
+/// randomness of a single system, seeded from `SimRng` when the system is first initialised
+///
+/// Systems run in parallel in no fixed order, so sharing one generator would hand out its numbers
+/// differently on every run. Systems are initialised in the order they were added, so each one
+/// gets the same stream every time a seed is reused.
+#[derive(Deref, DerefMut)]
+pub struct SystemRng(pub StdRng);
+
+impl FromWorld for SystemRng {
+    fn from_world(world: &mut World) -> Self {
+        use rand::Rng;
+
+        let seed: u64 = world.get_resource_or_insert_with(SimRng::default).gen();
+        Self(StdRng::seed_from_u64(seed))
+    }
+}
+
+/// how many times faster than real time a windowed simulation runs
+pub struct SimSpeed(pub f32);
+
+impl Default for SimSpeed {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+/// simulation time every frame advances by a fixed step, in place of `SimSpeed`, so frames
+/// captured while recording stay in step with the simulation
+pub struct LockStep {
+    pub step: Duration,
+    /// keep the clock still this frame, set before `ScaleTimeLabel` while the last step still has
+    /// to be captured
+    pub hold: bool,
+}
+
+/// clock running at `SimSpeed` times real time
+#[derive(Default)]
+struct ScaledClock {
+    time: Time,
+    now: Option<Instant>,
+    last_real: Option<Instant>,
+}
+
 #[derive(Default)]
 pub struct Simulation {
     pub time: f32,
     pub orca_count: usize,
+    /// vessel noise received by the average orca
+    pub mean_noise: f32,
 
     timer: Stopwatch,
 }
 
+impl Simulation {
+    /// stop the clock until the next run starts
+    pub fn stop(&mut self) {
+        self.timer.pause();
+    }
+}
+
+/// parameters of a run, which also make up the simulation part of a scenario file
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RunSimEvent {
     pub enable_orca: bool,
     pub pod_count: usize,
@@ -40,9 +141,67 @@ pub struct RunSimEvent {
 
     pub enable_fish: bool,
     pub fish_count: usize,
+    /// range of depths in metres that fish are distributed over
+    pub fish_depth_min: f32,
+    pub fish_depth_max: f32,
 
     pub orca_params: BoidParams,
     pub fish_params: BoidParams,
+
+    pub calendar: CalendarParams,
+
+    pub enable_vessels: bool,
+    pub cargo_count: usize,
+    pub whale_watch_count: usize,
+    /// noise source level of each kind of vessel
+    pub cargo_noise: f32,
+    pub whale_watch_noise: f32,
+}
+
+impl Default for RunSimEvent {
+    fn default() -> Self {
+        Self {
+            enable_orca: true,
+            pod_count: 4,
+            pod_size: 1..6,
+            pod_size_min: 15,
+            pod_size_max: 30,
+
+            enable_fish: true,
+            fish_count: 100,
+            fish_depth_min: 0.,
+            fish_depth_max: 50.,
+
+            orca_params: BoidParams {
+                coherence: 0.5,
+                seperation: 2.0,
+                ..default()
+            },
+            fish_params: BoidParams {
+                randomness: 4.0,
+                view_range: 50.,
+                view_angle: 60.,
+                ..default()
+            },
+
+            calendar: CalendarParams::default(),
+
+            enable_vessels: false,
+            cargo_count: 2,
+            whale_watch_count: 3,
+            cargo_noise: 2.,
+            whale_watch_noise: 1.,
+        }
+    }
+}
+
+/// fish population that is kept stocked over the course of a run
+pub struct FishStock {
+    /// number of fish at an abundance of one
+    pub count: usize,
+    pub params: BoidParams,
+    pub depth_min: f32,
+    pub depth_max: f32,
 }
 
 pub struct SimPlugin;
@@ -52,14 +211,36 @@ impl Plugin for SimPlugin {
         let mut sim = Simulation::default();
         sim.timer.pause();
         app.insert_resource(sim)
+            .insert_resource(SimRng::default())
             .add_event::<RunSimEvent>()
             .add_system(run_sim_orca)
             .add_system(run_sim_fish)
+            .add_system(spawn_calf)
+            .add_system(clone_orca)
+            .add_system(restock_fish)
+            .add_system(start_sim_time)
             .add_system(sim_time)
             .add_system(sim_count);
     }
 }
 
+/// the clock being swapped for one running at `SimSpeed`, which anything in `CoreStage::First` that
+/// reads `Time` has to run after
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScaleTimeLabel;
+
+/// runs time at `SimSpeed` in place of real time, which headless apps step by hand instead
+pub struct SimSpeedPlugin;
+
+impl Plugin for SimSpeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimSpeed>().add_system_to_stage(
+            CoreStage::First,
+            scale_time.label(ScaleTimeLabel).after(TimeSystem),
+        );
+    }
+}
+
 fn run_sim_orca(
     mut cmd: Commands,
     orca_query: Query<Entity, (With<Orca>, Without<Fish>)>,
@@ -69,9 +250,12 @@ fn run_sim_orca(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut events: EventReader<RunSimEvent>,
+    mut rng: Local<SystemRng>,
 ) {
     use std::f32::consts::PI;
 
+    let rng = &mut rng.0;
+
     for event in events.iter() {
         if !event.enable_orca {
             continue;
@@ -84,165 +268,120 @@ fn run_sim_orca(
         for entity in &fish_query {
             cmd.entity(entity).despawn_recursive();
         }
+        pod_pool.clear();
 
-        use rand::{seq::SliceRandom, thread_rng, Rng};
+        use rand::{seq::SliceRandom, Rng};
 
         cmd.insert_resource(Simulation::default());
 
+        let mut clan_dialect = Dialect::default();
         for pod_id in 0..event.pod_count {
+            if pod_id % PODS_PER_CLAN == 0 {
+                clan_dialect = Dialect::clan(rng);
+            }
+
             // create a new pod
             let pod_name = format!(
                 "{} {}",
-                POD_NAME_ADJ.choose(&mut thread_rng()).unwrap(),
-                POD_NAME_NOUN.choose(&mut thread_rng()).unwrap()
+                POD_NAME_ADJ.choose(rng).unwrap(),
+                POD_NAME_NOUN.choose(rng).unwrap()
             );
-            let mut pod = Pod {
-                name: pod_name,
-                members: vec![],
-            };
             let pod_color = Color::rgb(
-                thread_rng().gen_range(0..100) as f32 / 100.,
-                thread_rng().gen_range(0..100) as f32 / 100.,
-                thread_rng().gen_range(0..100) as f32 / 100.,
+                rng.gen_range(0..100) as f32 / 100.,
+                rng.gen_range(0..100) as f32 / 100.,
+                rng.gen_range(0..100) as f32 / 100.,
             );
-            let pod_size = thread_rng().gen_range(event.pod_size_min..event.pod_size_max);
+            let pod_size =
+                rng.gen_range(event.pod_size_min..=event.pod_size_max.max(event.pod_size_min));
 
             let pod_spawn_pos = Vec2::new(
-                thread_rng().gen_range(-100..100) as f32,
-                thread_rng().gen_range(-100..100) as f32,
+                rng.gen_range(-100..100) as f32,
+                rng.gen_range(-100..100) as f32,
             );
-            let pod_type = match thread_rng().gen_range(0..=1) {
+            let pod_type = match rng.gen_range(0..=1) {
                 0 => Type::Resident,
                 1 => Type::Transient,
                 _ => unreachable!(),
             };
+            let mut pod = Pod {
+                name: pod_name,
+                color: pod_color,
+                orca_type: pod_type,
+                dialect: Dialect::pod(&clan_dialect, rng),
+                members: vec![],
+            };
 
             for j in 0..pod_size {
-                let mut gradient = Gradient::new();
-                gradient.add_key(0.0, Vec4::new(0.16, 0.19, 0.59, 1.));
-
-                let mut size_gradient = Gradient::new();
-                size_gradient.add_key(0.0, Vec2::splat(1.0));
-                size_gradient.add_key(0.5, Vec2::splat(1.0));
-                size_gradient.add_key(1.0, Vec2::splat(0.0));
-                let swim_effect = effects.add(
-                    EffectAsset {
-                        name: "Swim Effect".into(),
-                        capacity: 4096,
-                        spawner: Spawner::rate(10.0.into()),
-                        ..default()
-                    }
-                    .init(PositionCircleModifier {
-                        radius: 0.05,
-                        speed: 0.1.into(),
-                        dimension: ShapeDimension::Surface,
-                        ..default()
-                    })
-                    .init(ParticleLifetimeModifier { lifetime: 1.0 })
-                    .render(ColorOverLifetimeModifier { gradient })
-                    .render(SizeOverLifetimeModifier {
-                        gradient: size_gradient,
-                    }),
-                );
-
-                let id = cmd.spawn().id();
-                pod.members.push(id);
-
                 let spawn_offset = Vec2::new(
-                    thread_rng().gen_range(-100..100) as f32,
-                    thread_rng().gen_range(-100..100) as f32,
+                    rng.gen_range(-100..100) as f32,
+                    rng.gen_range(-100..100) as f32,
                 );
 
-                let rand_angle = thread_rng().gen_range(0..(360 as i32)) as f32 * PI / 180.;
+                let rand_angle = rng.gen_range(0..(360 as i32)) as f32 * PI / 180.;
                 let velocity = Mat2::from_angle(rand_angle) * Vec2::X * 10.;
 
                 // independent params
-                let gender = match thread_rng().gen_range(0..=1) {
+                let gender = match rng.gen_range(0..=1) {
                     0 => Gender::Male,
                     1 => Gender::Female,
                     _ => unreachable!(),
                 };
-                let age = thread_rng().gen_range(5..50);
+                let age = rng.gen_range(5..50);
 
                 // dependent params
                 let name = match gender {
-                    Gender::Male => MALE_NAMES.choose(&mut thread_rng()).unwrap(),
-                    Gender::Female => FEMALE_NAMES.choose(&mut thread_rng()).unwrap(),
+                    Gender::Male => MALE_NAMES.choose(rng).unwrap(),
+                    Gender::Female => FEMALE_NAMES.choose(rng).unwrap(),
                 };
-                let mass = thread_rng().gen_range(2000..3000) as f32;
-
-                let id = cmd.spawn().id();
-
-                cmd.entity(id)
-                    .insert(Orca {
-                        name: String::from(*name),
-                        gender,
-                        age,
-                        mass,
-                        orca_type: pod_type.clone(),
-                        pod_id: Some(pod_id),
-                    })
-                    .insert(OrcaNeighbouring::default())
-                    .insert(Hunger(thread_rng().gen_range(0.5f32..0.8f32)))
-                    .insert(Sight {
-                        view_range: event.orca_params.view_range,
-                        view_angle: event.orca_params.view_angle,
-                    })
-                    .insert(Movement {
-                        coherence: event.orca_params.coherence,
-                        alignment: event.orca_params.alignment,
-                        seperation: event.orca_params.seperation,
-                        randomess: event.orca_params.randomness,
-                        tracking: 10.,
-                        wander_angle: 20,
-                        target: None,
-                        speed_scale: thread_rng().gen_range(90..110) as f32 / 10.,
-                        ..default()
-                    })
-                    .insert(RigidBody {
-                        max_velocity: Some(20.),
-                        velocity,
-                        mass: 1.,
-                        ..default()
-                    })
-                    .insert(
-                        Thinker::build()
-                            .picker(FirstToScore { threshold: 0.5 })
-                            .when(Hungry, Hunt),
-                    )
-                    .insert_bundle(MaterialMesh2dBundle {
-                        mesh: meshes.add(Mesh::from(shape::Circle::new(3.))).into(),
-                        transform: Transform::from_translation(
-                            (pod_spawn_pos + spawn_offset).extend(0.),
-                        ),
-                        material: materials.add(ColorMaterial::from(Color::NONE)),
-                        ..default()
-                    })
-                    .with_children(|parent| {
-                        parent.spawn().insert_bundle(GeometryBuilder::build_as(
-                            &RegularPolygon {
-                                sides: 3,
-                                ..default()
-                            },
-                            DrawMode::Outlined {
-                                fill_mode: FillMode::color(pod_color),
-                                outline_mode: StrokeMode::new(
-                                    if gender.clone() == Gender::Male {
-                                        Color::BLACK
-                                    } else {
-                                        Color::GRAY
-                                    },
-                                    0.1,
-                                ),
-                            },
-                            Transform::from_scale(Vec3::splat(0.8 + age as f32 / 50.)),
-                        ));
-                        parent.spawn_bundle(ParticleEffectBundle {
-                            effect: ParticleEffect::new(swim_effect).with_z_layer_2d(Some(-1.)),
+                let mass = rng.gen_range(2000..3000) as f32;
+
+                let id = spawn_orca(
+                    &mut cmd,
+                    &mut meshes,
+                    &mut materials,
+                    &mut effects,
+                    OrcaBundle {
+                        orca: Orca {
+                            name: String::from(*name),
+                            gender,
+                            age,
+                            mass,
+                            orca_type: pod_type,
+                            pod_id: Some(pod_id),
+                        },
+                        neighbouring: OrcaNeighbouring::default(),
+                        hunger: Hunger(rng.gen_range(0.5f32..0.8f32)),
+                        sight: Sight {
+                            view_range: event.orca_params.view_range,
+                            view_angle: event.orca_params.view_angle,
+                            peripheral_falloff: event.orca_params.peripheral_falloff,
+                        },
+                        movement: Movement {
+                            coherence: event.orca_params.coherence,
+                            alignment: event.orca_params.alignment,
+                            seperation: event.orca_params.seperation,
+                            randomess: event.orca_params.randomness,
+                            tracking: 10.,
+                            wander_angle: 20,
+                            target: None,
+                            speed_scale: rng.gen_range(90..110) as f32 / 10.,
+                            dive_speed: 3.,
                             ..default()
-                        });
-                    })
-                    .insert_bundle(PickableBundle::default());
+                        },
+                        breath: Breath::new(ORCA_BREATH_HOLD),
+                        drift: Drift(ORCA_DRIFT),
+                        rigid_body: RigidBody {
+                            max_velocity: Some(20.),
+                            velocity,
+                            mass: 1.,
+                            ..default()
+                        },
+                        call_memory: CallMemory::default(),
+                    },
+                    pod_color,
+                    pod_spawn_pos + spawn_offset,
+                );
+                pod.members.push(id);
             }
 
             pod_pool.insert(pod_id as usize, pod);
@@ -250,78 +389,433 @@ fn run_sim_orca(
     }
 }
 
+/// spawn an orca along with its mesh, shape and swim particles
+fn spawn_orca(
+    cmd: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    effects: &mut Assets<EffectAsset>,
+    bundle: OrcaBundle,
+    color: Color,
+    position: Vec2,
+) -> Entity {
+    let gender = bundle.orca.gender;
+    let age = bundle.orca.age;
+    let orca_type = bundle.orca.orca_type;
+
+    let mut gradient = Gradient::new();
+    gradient.add_key(0.0, Vec4::new(0.16, 0.19, 0.59, 1.));
+
+    let mut size_gradient = Gradient::new();
+    size_gradient.add_key(0.0, Vec2::splat(1.0));
+    size_gradient.add_key(0.5, Vec2::splat(1.0));
+    size_gradient.add_key(1.0, Vec2::splat(0.0));
+    let swim_effect = effects.add(
+        EffectAsset {
+            name: "Swim Effect".into(),
+            capacity: 4096,
+            spawner: Spawner::rate(10.0.into()),
+            ..default()
+        }
+        .init(PositionCircleModifier {
+            radius: 0.05,
+            speed: 0.1.into(),
+            dimension: ShapeDimension::Surface,
+            ..default()
+        })
+        .init(ParticleLifetimeModifier { lifetime: 1.0 })
+        .render(ColorOverLifetimeModifier { gradient })
+        .render(SizeOverLifetimeModifier {
+            gradient: size_gradient,
+        }),
+    );
+
+    let id = cmd
+        .spawn_bundle(bundle)
+        .insert(BoidForces::default())
+        .insert_bundle(MaterialMesh2dBundle {
+            mesh: meshes.add(Mesh::from(shape::Circle::new(3.))).into(),
+            transform: Transform::from_translation(position.extend(0.)),
+            material: materials.add(ColorMaterial::from(Color::NONE)),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn()
+                .insert_bundle(GeometryBuilder::build_as(
+                    &RegularPolygon {
+                        sides: 3,
+                        ..default()
+                    },
+                    DrawMode::Outlined {
+                        fill_mode: FillMode::color(color),
+                        outline_mode: StrokeMode::new(outline_color(gender), 0.1),
+                    },
+                    Transform::from_scale(body_scale(age)),
+                ))
+                .insert(DepthShaded(color));
+            parent.spawn_bundle(ParticleEffectBundle {
+                effect: ParticleEffect::new(swim_effect).with_z_layer_2d(Some(-1.)),
+                ..default()
+            });
+        })
+        .insert_bundle(PickableBundle::default())
+        .id();
+
+    // residents find fish with sonar, transients listen quietly for their prey
+    match orca_type {
+        Type::Resident => cmd.entity(id).insert(Echolocation::default()),
+        Type::Transient => cmd.entity(id).insert(PassiveListening::default()),
+    };
+
+    id
+}
+
+/// calves are born into their mother's pod
+fn spawn_calf(
+    mut cmd: Commands,
+    mut events: EventReader<SpawnOrcaEvent>,
+    mother_query: Query<(&Orca, &Transform, &Sight, &Movement, &RigidBody)>,
+    mut pod_pool: ResMut<PodPool>,
+    mut effects: ResMut<Assets<EffectAsset>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut rng: Local<SystemRng>,
+) {
+    use rand::{seq::SliceRandom, Rng};
+
+    let rng = &mut rng.0;
+
+    for SpawnOrcaEvent { mother } in events.iter() {
+        let (mother, trans, sight, movement, rb) = match mother_query.get(*mother) {
+            Ok(mother) => mother,
+            Err(_) => continue,
+        };
+
+        let gender = match rng.gen_range(0..=1) {
+            0 => Gender::Male,
+            1 => Gender::Female,
+            _ => unreachable!(),
+        };
+        let name = match gender {
+            Gender::Male => MALE_NAMES.choose(rng).unwrap(),
+            Gender::Female => FEMALE_NAMES.choose(rng).unwrap(),
+        };
+        let color = mother
+            .pod_id
+            .and_then(|pod_id| pod_pool.get(&pod_id))
+            .map(|pod| pod.color)
+            .unwrap_or(Color::WHITE);
+
+        let id = spawn_orca(
+            &mut cmd,
+            &mut meshes,
+            &mut materials,
+            &mut effects,
+            OrcaBundle {
+                orca: Orca {
+                    name: String::from(*name),
+                    gender,
+                    age: 0,
+                    mass: CALF_MASS,
+                    orca_type: mother.orca_type,
+                    pod_id: mother.pod_id,
+                },
+                neighbouring: OrcaNeighbouring::default(),
+                hunger: Hunger(0.5),
+                sight: Sight {
+                    view_range: sight.view_range,
+                    view_angle: sight.view_angle,
+                    peripheral_falloff: sight.peripheral_falloff,
+                },
+                movement: Movement {
+                    target: None,
+                    depth: 0.,
+                    target_depth: 0.,
+                    ..movement.clone()
+                },
+                breath: Breath::new(ORCA_BREATH_HOLD),
+                drift: Drift(ORCA_DRIFT),
+                rigid_body: RigidBody {
+                    max_velocity: rb.max_velocity,
+                    velocity: rb.velocity,
+                    mass: 1.,
+                    ..default()
+                },
+                call_memory: CallMemory::default(),
+            },
+            color,
+            trans.translation.truncate(),
+        );
+
+        if let Some(pod) = mother.pod_id.and_then(|pod_id| pod_pool.get_mut(&pod_id)) {
+            pod.members.push(id);
+        }
+    }
+}
+
+/// copies join the same pod, a little way off from the original
+fn clone_orca(
+    mut cmd: Commands,
+    mut events: EventReader<CloneOrcaEvent>,
+    query: Query<(
+        &Orca,
+        &Transform,
+        &Hunger,
+        &Sight,
+        &Movement,
+        &Breath,
+        &RigidBody,
+        Option<&ThinkerDef>,
+    )>,
+    mut pod_pool: ResMut<PodPool>,
+    mut effects: ResMut<Assets<EffectAsset>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for CloneOrcaEvent(entity) in events.iter() {
+        let (orca, trans, hunger, sight, movement, breath, rb, thinker) = match query.get(*entity) {
+            Ok(original) => original,
+            Err(_) => continue,
+        };
+        let color = orca
+            .pod_id
+            .and_then(|pod_id| pod_pool.get(&pod_id))
+            .map(|pod| pod.color)
+            .unwrap_or(Color::WHITE);
+
+        let id = spawn_orca(
+            &mut cmd,
+            &mut meshes,
+            &mut materials,
+            &mut effects,
+            OrcaBundle {
+                orca: Orca {
+                    name: orca.name.clone(),
+                    ..*orca
+                },
+                neighbouring: OrcaNeighbouring::default(),
+                hunger: Hunger(hunger.0),
+                sight: Sight {
+                    view_range: sight.view_range,
+                    view_angle: sight.view_angle,
+                    peripheral_falloff: sight.peripheral_falloff,
+                },
+                movement: Movement {
+                    target: None,
+                    ..movement.clone()
+                },
+                breath: Breath::new(breath.max_hold),
+                drift: Drift(ORCA_DRIFT),
+                rigid_body: RigidBody {
+                    max_velocity: rb.max_velocity,
+                    velocity: rb.velocity,
+                    mass: 1.,
+                    ..default()
+                },
+                call_memory: CallMemory::default(),
+            },
+            color,
+            trans.translation.truncate() + Vec2::splat(5.),
+        );
+        // think like the original rather than like the rest of its ecotype
+        if let Some(thinker) = thinker {
+            cmd.entity(id).insert(thinker.clone());
+        }
+
+        if let Some(pod) = orca.pod_id.and_then(|pod_id| pod_pool.get_mut(&pod_id)) {
+            pod.members.push(id);
+        }
+    }
+}
+
 fn run_sim_fish(
     mut cmd: Commands,
     query: Query<Entity, With<Fish>>,
+    bathymetry: Res<Bathymetry>,
     mut events: EventReader<RunSimEvent>,
+    mut rng: Local<SystemRng>,
 ) {
-    use std::f32::consts::PI;
+    use rand::Rng;
 
-    use rand::{thread_rng, Rng};
+    let rng = &mut rng.0;
 
     for event in events.iter() {
         if !event.enable_fish {
+            cmd.remove_resource::<FishStock>();
             continue;
         }
 
-        for i in 0..=event.fish_count {
-            let id = cmd.spawn().id();
+        let stock = FishStock {
+            count: event.fish_count,
+            params: event.fish_params,
+            depth_min: event.fish_depth_min,
+            depth_max: event.fish_depth_max,
+        };
 
+        for i in 0..=event.fish_count {
             let spawn_pos = Vec2::new(
-                thread_rng().gen_range(-300..300) as f32,
-                thread_rng().gen_range(-300..300) as f32,
+                rng.gen_range(-300..300) as f32,
+                rng.gen_range(-300..300) as f32,
             );
+            spawn_fish(&mut cmd, rng, &bathymetry, &stock, spawn_pos);
+        }
 
-            let rand_angle = thread_rng().gen_range(0..(360 as i32)) as f32 * PI / 180.;
-            let velocity = Mat2::from_angle(rand_angle) * Vec2::X * 10.;
+        cmd.insert_resource(stock);
+    }
+}
 
-            cmd.entity(id)
-                .insert(Fish)
-                .insert(FishNeighbouring::default())
-                .insert_bundle(SpriteBundle {
-                    sprite: Sprite {
-                        color: Color::RED,
+fn spawn_fish(
+    cmd: &mut Commands,
+    rng: &mut StdRng,
+    bathymetry: &Bathymetry,
+    stock: &FishStock,
+    position: Vec2,
+) {
+    use std::f32::consts::PI;
+
+    use rand::Rng;
+
+    let rand_angle = rng.gen_range(0..(360 as i32)) as f32 * PI / 180.;
+    let velocity = Mat2::from_angle(rand_angle) * Vec2::X * 10.;
+
+    // distribute fish over their depth range, limited by the sea floor
+    let depth = if stock.depth_max > stock.depth_min {
+        rng.gen_range(stock.depth_min..stock.depth_max)
+    } else {
+        stock.depth_min
+    }
+    .min(bathymetry.depth_at(position));
+
+    cmd.spawn()
+        .insert(Fish {
+            size: rng.gen_range(0.4..1.),
+        })
+        .insert(FishNeighbouring::default())
+        .insert(BoidForces::default())
+        // invisible hit area so fish can be picked like orcas
+        .insert_bundle(GeometryBuilder::build_as(
+            &shapes::Circle {
+                radius: 1.5,
+                ..default()
+            },
+            DrawMode::Fill(FillMode::color(Color::NONE)),
+            Transform::from_translation(position.extend(0.)),
+        ))
+        .insert_bundle(PickableBundle::default())
+        .insert(Sight {
+            view_range: stock.params.view_range,
+            view_angle: stock.params.view_angle,
+            peripheral_falloff: stock.params.peripheral_falloff,
+        })
+        .insert(Movement {
+            coherence: stock.params.coherence,
+            alignment: stock.params.alignment,
+            seperation: stock.params.seperation,
+            randomess: stock.params.randomness,
+            tracking: 10.,
+            wander_angle: 20,
+            target: None,
+            speed_scale: rng.gen_range(90..110) as f32 / 10.,
+            depth,
+            target_depth: depth,
+            ..default()
+        })
+        .insert(RigidBody {
+            max_velocity: Some(20.),
+            velocity,
+            mass: 1.,
+            ..default()
+        })
+        .insert(Drift(FISH_DRIFT))
+        .with_children(|parent| {
+            parent
+                .spawn()
+                .insert_bundle(GeometryBuilder::build_as(
+                    &RegularPolygon {
+                        sides: 4,
                         ..default()
                     },
-                    transform: Transform::from_translation(spawn_pos.extend(0.)),
-                    ..default()
-                })
-                .insert(Sight {
-                    view_range: event.fish_params.view_range,
-                    view_angle: event.fish_params.view_angle,
-                })
-                .insert(Movement {
-                    coherence: event.fish_params.coherence,
-                    alignment: event.fish_params.alignment,
-                    seperation: event.fish_params.seperation,
-                    randomess: event.fish_params.randomness,
-                    tracking: 10.,
-                    wander_angle: 20,
-                    target: None,
-                    speed_scale: thread_rng().gen_range(90..110) as f32 / 10.,
-                    ..default()
-                })
-                .insert(RigidBody {
-                    max_velocity: Some(20.),
-                    velocity,
-                    mass: 1.,
-                    ..default()
-                })
-                .with_children(|parent| {
-                    parent.spawn().insert_bundle(GeometryBuilder::build_as(
-                        &RegularPolygon {
-                            sides: 4,
-                            ..default()
-                        },
-                        DrawMode::Outlined {
-                            fill_mode: FillMode::color(Color::RED),
-                            outline_mode: StrokeMode::new(Color::BLACK, 0.1),
-                        },
-                        Transform::default(),
-                    ));
-                });
-        }
+                    DrawMode::Outlined {
+                        fill_mode: FillMode::color(Color::RED),
+                        outline_mode: StrokeMode::new(Color::BLACK, 0.1),
+                    },
+                    Transform::default(),
+                ))
+                .insert(DepthShaded(Color::RED));
+        });
+}
+
+/// top the fish population back up towards the seasonal abundance
+fn restock_fish(
+    mut cmd: Commands,
+    time: Res<Time>,
+    stock: Option<Res<FishStock>>,
+    calendar: Res<Calendar>,
+    bathymetry: Res<Bathymetry>,
+    query: Query<(), With<Fish>>,
+    mut pending: Local<f32>,
+    mut rng: Local<SystemRng>,
+) {
+    use rand::Rng;
+
+    let rng = &mut rng.0;
+
+    let stock = some_or_return!(stock);
+
+    let target = (stock.count as f32 * calendar.season_params().salmon_abundance) as usize;
+    let count = query.iter().count();
+    if count >= target {
+        *pending = 0.;
+        return;
     }
+
+    *pending += FISH_RESTOCK_RATE * time.delta_seconds();
+    let spawn_count = (*pending as usize).min(target - count);
+    *pending -= spawn_count as f32;
+    for _ in 0..spawn_count {
+        let spawn_pos = Vec2::new(
+            rng.gen_range(-300..300) as f32,
+            rng.gen_range(-300..300) as f32,
+        );
+        spawn_fish(&mut cmd, rng, &bathymetry, &stock, spawn_pos);
+    }
+}
+
+/// simulation time restarts from zero with every run
+fn start_sim_time(mut sim: ResMut<Simulation>, mut events: EventReader<RunSimEvent>) {
+    for _ in events.iter() {
+        sim.timer.reset();
+        sim.timer.unpause();
+    }
+}
+
+/// replace the real clock with one that runs `SimSpeed` times as fast, or steps by `LockStep`
+///
+/// `Time` is overwritten wholesale with a private clock, so this has to run after `TimeSystem` has
+/// updated the real one and before every system that reads it. Simulation systems all run in later
+/// stages, anything added to `CoreStage::First` has to go after `ScaleTimeLabel`.
+fn scale_time(
+    mut time: ResMut<Time>,
+    speed: Res<SimSpeed>,
+    lock_step: Option<Res<LockStep>>,
+    mut clock: Local<ScaledClock>,
+) {
+    let real = Instant::now();
+    let delta = match lock_step {
+        Some(lock_step) if lock_step.hold => Duration::ZERO,
+        Some(lock_step) => lock_step.step,
+        None => clock
+            .last_real
+            .map(|last| (real - last).mul_f32(speed.0.max(0.)))
+            .unwrap_or_default(),
+    };
+    clock.last_real = Some(real);
+
+    let now = clock.now.unwrap_or(real) + delta;
+    clock.now = Some(now);
+    clock.time.update_with_instant(now);
+    *time = clock.time.clone();
 }
 
 fn sim_time(time: Res<Time>, mut sim: ResMut<Simulation>) {
@@ -0,0 +1,556 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use bevy::prelude::*;
+
+use crate::{
+    ai::hunger::KillEvent,
+    fish::Fish,
+    map::WorldBounds,
+    orca::{Orca, PodId, PodPool},
+    sim::{RunSimEvent, Simulation},
+};
+
+pub const SPACE_USE_CELL_SIZE: f32 = 20.;
+/// seconds of simulation time between samples of where boids are
+const SPACE_USE_INTERVAL: f32 = 1.;
+/// share of use that a home range is drawn around
+pub const HOME_RANGE_FRACTION: f32 = 0.95;
+
+/// square grid of values over the world
+#[derive(Clone)]
+pub struct Grid {
+    /// world position of the corner of the first cell
+    pub origin: Vec2,
+    pub cell_size: f32,
+    pub width: usize,
+    pub height: usize,
+    /// row by row, starting at the origin
+    pub cells: Vec<f32>,
+}
+
+impl Grid {
+    pub fn new(origin: Vec2, cell_size: f32, width: usize, height: usize) -> Self {
+        Self {
+            origin,
+            cell_size,
+            width,
+            height,
+            cells: vec![0.; width * height],
+        }
+    }
+
+    /// grid of `SPACE_USE_CELL_SIZE` cells covering the area between `min` and `max`, with cells
+    /// lined up on the world origin
+    pub fn covering(min: Vec2, max: Vec2) -> Self {
+        let origin = (min / SPACE_USE_CELL_SIZE).floor() * SPACE_USE_CELL_SIZE;
+        // max itself is inside the last cell
+        let cells = ((max - origin) / SPACE_USE_CELL_SIZE).floor() + 1.;
+        Self::new(
+            origin,
+            SPACE_USE_CELL_SIZE,
+            cells.x as usize,
+            cells.y as usize,
+        )
+    }
+
+    /// grid covering the area space use is tracked over
+    pub fn world(bounds: &WorldBounds) -> Self {
+        Self::covering(bounds.min, bounds.max)
+    }
+
+    /// world size of the whole grid
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.width as f32, self.height as f32) * self.cell_size
+    }
+
+    pub fn index(&self, pos: Vec2) -> Option<usize> {
+        let cell = ((pos - self.origin) / self.cell_size).floor();
+        if cell.x < 0. || cell.y < 0. {
+            return None;
+        }
+        let (x, y) = (cell.x as usize, cell.y as usize);
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(y * self.width + x)
+    }
+
+    /// world position of the centre of a cell
+    pub fn center(&self, index: usize) -> Vec2 {
+        let (x, y) = (index % self.width, index / self.width);
+        self.origin + (Vec2::new(x as f32, y as f32) + 0.5) * self.cell_size
+    }
+
+    pub fn add(&mut self, pos: Vec2, amount: f32) {
+        if let Some(i) = self.index(pos) {
+            self.cells[i] += amount;
+        }
+    }
+
+    pub fn max(&self) -> f32 {
+        self.cells.iter().copied().fold(0., f32::max)
+    }
+
+    /// write as a matrix in the layout of bathymetry and current grids
+    pub fn write_csv(&self, path: &PathBuf) -> Result<(), String> {
+        let mut contents = format!("{} {} {}\n", self.origin.x, self.origin.y, self.cell_size);
+        for row in self.cells.chunks(self.width) {
+            let row: Vec<String> = row.iter().map(|cell| cell.to_string()).collect();
+            contents.push_str(&row.join(" "));
+            contents.push('\n');
+        }
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+
+    /// colour of every cell as rgba, north up, scaled logarithmically so sparse use still shows
+    pub fn to_rgba(&self, opacity: f32) -> Vec<u8> {
+        let scale = (1. + self.max()).ln().max(f32::EPSILON);
+        let mut data = Vec::with_capacity(self.cells.len() * 4);
+        for row in self.cells.chunks(self.width).rev() {
+            for cell in row {
+                let t = (1. + cell).ln() / scale;
+                let [r, g, b] = heat_color(t);
+                let alpha = if *cell > 0. {
+                    opacity * (0.3 + 0.7 * t)
+                } else {
+                    0.
+                };
+                data.extend([r, g, b, (alpha * 255.) as u8]);
+            }
+        }
+        data
+    }
+
+    pub fn write_png(&self, path: &PathBuf) -> Result<(), String> {
+        image::save_buffer(
+            path,
+            &self.to_rgba(1.),
+            self.width as u32,
+            self.height as u32,
+            image::ColorType::Rgba8,
+        )
+        .map_err(|e| e.to_string())
+    }
+}
+
+/// black through red and yellow to white
+fn heat_color(t: f32) -> [u8; 3] {
+    let t = t.clamp(0., 1.) * 3.;
+    let r = t.min(1.);
+    let g = (t - 1.).clamp(0., 1.);
+    let b = (t - 2.).clamp(0., 1.);
+    [(r * 255.) as u8, (g * 255.) as u8, (b * 255.) as u8]
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    Orcas,
+    Fish,
+    Kills,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 3] = [Layer::Orcas, Layer::Fish, Layer::Kills];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Layer::Orcas => "orcas",
+            Layer::Fish => "fish",
+            Layer::Kills => "kills",
+        }
+    }
+}
+
+/// where orcas, fish and kills have been over the run
+pub struct SpaceUse {
+    pub orcas: Grid,
+    pub fish: Grid,
+    pub kills: Grid,
+    /// samples of each pod's members binned like the other layers, which home ranges are
+    /// estimated from, so memory and estimation time stay bounded however long the run
+    pub pods: BTreeMap<PodId, Grid>,
+    next_sample: f32,
+}
+
+impl SpaceUse {
+    pub fn new(bounds: &WorldBounds) -> Self {
+        Self {
+            orcas: Grid::world(bounds),
+            fish: Grid::world(bounds),
+            kills: Grid::world(bounds),
+            pods: BTreeMap::new(),
+            next_sample: 0.,
+        }
+    }
+
+    pub fn add_pod_sample(&mut self, pod_id: PodId, pos: Vec2) {
+        let layout = &self.orcas;
+        self.pods
+            .entry(pod_id)
+            .or_insert_with(|| {
+                Grid::new(layout.origin, layout.cell_size, layout.width, layout.height)
+            })
+            .add(pos, 1.);
+    }
+
+    pub fn layer(&self, layer: Layer) -> &Grid {
+        match layer {
+            Layer::Orcas => &self.orcas,
+            Layer::Fish => &self.fish,
+            Layer::Kills => &self.kills,
+        }
+    }
+
+    /// home range of every pod with enough samples, ordered by pod
+    pub fn home_ranges(&self, fraction: f32) -> Vec<(PodId, HomeRange)> {
+        let mut ranges: Vec<(PodId, HomeRange)> = self
+            .pods
+            .iter()
+            .filter_map(|(pod_id, samples)| {
+                HomeRange::estimate(samples, fraction).map(|r| (*pod_id, r))
+            })
+            .collect();
+        ranges.sort_unstable_by_key(|(pod_id, _)| *pod_id);
+        ranges
+    }
+
+    /// write every layer as a matrix and an image, along with the pods' home ranges
+    pub fn write(&self, dir: &PathBuf, pod_pool: &PodPool) -> Result<(), String> {
+        for layer in Layer::ALL {
+            let grid = self.layer(layer);
+            grid.write_csv(&dir.join(format!("space_use_{}.csv", layer.name())))?;
+            grid.write_png(&dir.join(format!("space_use_{}.png", layer.name())))?;
+        }
+
+        let mut contents = String::from("pod,name,samples,mcp_area,kde_area\n");
+        for (pod_id, range) in self.home_ranges(HOME_RANGE_FRACTION) {
+            let name = pod_pool
+                .get(&pod_id)
+                .map(|pod| pod.name.clone())
+                .unwrap_or_default();
+            contents.push_str(&format!(
+                "{},{},{},{},{}\n",
+                pod_id, name, range.samples, range.mcp_area, range.kde_area
+            ));
+        }
+        fs::write(dir.join("home_ranges.csv"), contents).map_err(|e| e.to_string())
+    }
+}
+
+/// area a pod uses, estimated from where its members were sampled
+pub struct HomeRange {
+    pub samples: usize,
+    /// convex hull of the cells closest to the samples' centroid, counter clockwise
+    pub mcp: Vec<Vec2>,
+    pub mcp_area: f32,
+    /// cells inside the kernel density isopleth
+    pub kde_cells: Vec<Vec2>,
+    pub kde_cell_size: f32,
+    pub kde_area: f32,
+}
+
+impl HomeRange {
+    /// minimum convex polygon and kernel density home range holding `fraction` of the use
+    ///
+    /// `samples` holds a count of samples per cell, every sample is taken to be at the centre of
+    /// its cell.
+    pub fn estimate(samples: &Grid, fraction: f32) -> Option<Self> {
+        let occupied: Vec<(Vec2, f32)> = (0..samples.cells.len())
+            .filter(|i| samples.cells[*i] > 0.)
+            .map(|i| (samples.center(i), samples.cells[i]))
+            .collect();
+        let n: f32 = occupied.iter().map(|(_, count)| count).sum();
+        if n < 3. {
+            return None;
+        }
+
+        // the mcp leaves out the cells furthest from the centroid
+        let centroid = occupied.iter().map(|(p, count)| *p * *count).sum::<Vec2>() / n;
+        let mut by_dist = occupied.clone();
+        by_dist.sort_by(|a, b| a.0.distance(centroid).total_cmp(&b.0.distance(centroid)));
+        let mut kept = 0.;
+        let mut hull_points = vec![];
+        for (p, count) in by_dist {
+            if kept >= n * fraction && hull_points.len() >= 3 {
+                break;
+            }
+            kept += count;
+            hull_points.push(p);
+        }
+        let mcp = convex_hull(&hull_points);
+        let mcp_area = polygon_area(&mcp);
+
+        // gaussian kernels with a bandwidth from silverman's rule, one per cell weighted by its count
+        let variance = occupied
+            .iter()
+            .map(|(p, count)| (*p - centroid).length_squared() * count)
+            .sum::<f32>()
+            / (2. * n);
+        let bandwidth = (variance.sqrt() * n.powf(-1. / 6.)).max(SPACE_USE_CELL_SIZE / 2.);
+        let (min, max) = occupied.iter().fold(
+            (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+            |(min, max), (p, _)| (min.min(*p), max.max(*p)),
+        );
+        let mut density = Grid::covering(min - 3. * bandwidth, max + 3. * bandwidth);
+        let reach = (3. * bandwidth / density.cell_size).ceil() as i32;
+        for (p, count) in occupied.iter() {
+            let cell = ((*p - density.origin) / density.cell_size).floor();
+            for dy in -reach..=reach {
+                for dx in -reach..=reach {
+                    let (x, y) = (cell.x as i32 + dx, cell.y as i32 + dy);
+                    if x < 0 || y < 0 || x >= density.width as i32 || y >= density.height as i32 {
+                        continue;
+                    }
+                    let i = y as usize * density.width + x as usize;
+                    let d2 = density.center(i).distance_squared(*p);
+                    density.cells[i] += count * (-d2 / (2. * bandwidth * bandwidth)).exp();
+                }
+            }
+        }
+
+        // densest cells until they hold the fraction of the total
+        let total: f32 = density.cells.iter().sum();
+        let mut order: Vec<usize> = (0..density.cells.len())
+            .filter(|i| density.cells[*i] > 0.)
+            .collect();
+        order.sort_by(|a, b| density.cells[*b].total_cmp(&density.cells[*a]));
+        let mut held = 0.;
+        let mut kde_cells = vec![];
+        for i in order {
+            if held >= total * fraction {
+                break;
+            }
+            held += density.cells[i];
+            kde_cells.push(density.center(i));
+        }
+        let kde_area = kde_cells.len() as f32 * density.cell_size * density.cell_size;
+
+        Some(Self {
+            samples: n as usize,
+            mcp,
+            mcp_area,
+            kde_cells,
+            kde_cell_size: density.cell_size,
+            kde_area,
+        })
+    }
+}
+
+/// monotone chain convex hull
+fn convex_hull(points: &[Vec2]) -> Vec<Vec2> {
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+
+    let cross = |o: Vec2, a: Vec2, b: Vec2| (a - o).perp_dot(b - o);
+    let half = |points: &mut dyn Iterator<Item = &Vec2>| {
+        let mut half: Vec<Vec2> = vec![];
+        for p in points {
+            while half.len() >= 2 && cross(half[half.len() - 2], half[half.len() - 1], *p) <= 0. {
+                half.pop();
+            }
+            half.push(*p);
+        }
+        // the last point starts the other half
+        half.pop();
+        half
+    };
+    let mut hull = half(&mut points.iter());
+    hull.extend(half(&mut points.iter().rev()));
+    hull
+}
+
+fn polygon_area(points: &[Vec2]) -> f32 {
+    let n = points.len();
+    (0..n)
+        .map(|i| points[i].perp_dot(points[(i + 1) % n]))
+        .sum::<f32>()
+        .abs()
+        / 2.
+}
+
+pub struct SpaceUsePlugin;
+
+impl Plugin for SpaceUsePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SpaceUse::new(&WorldBounds::default()))
+            .add_system(reset_space_use)
+            .add_system(sample_space_use)
+            .add_system(record_kills);
+    }
+}
+
+fn reset_space_use(
+    mut space_use: ResMut<SpaceUse>,
+    bounds: Res<WorldBounds>,
+    mut events: EventReader<RunSimEvent>,
+) {
+    if events.iter().count() > 0 {
+        *space_use = SpaceUse::new(&bounds);
+    }
+}
+
+fn sample_space_use(
+    sim: Res<Simulation>,
+    mut space_use: ResMut<SpaceUse>,
+    orca_query: Query<(&Orca, &Transform)>,
+    fish_query: Query<&Transform, With<Fish>>,
+) {
+    if sim.time < space_use.next_sample {
+        return;
+    }
+    space_use.next_sample = sim.time + SPACE_USE_INTERVAL;
+
+    for (orca, trans) in orca_query.iter() {
+        let pos = trans.translation.truncate();
+        space_use.orcas.add(pos, 1.);
+        if let Some(pod_id) = orca.pod_id {
+            space_use.add_pod_sample(pod_id, pos);
+        }
+    }
+    for trans in fish_query.iter() {
+        space_use.fish.add(trans.translation.truncate(), 1.);
+    }
+}
+
+/// kills are placed where the predator was, as the prey may already be gone
+fn record_kills(
+    mut space_use: ResMut<SpaceUse>,
+    mut kill_events: EventReader<KillEvent>,
+    query: Query<&Transform>,
+) {
+    for KillEvent { predator, .. } in kill_events.iter() {
+        if let Ok(trans) = query.get(*predator) {
+            space_use.kills.add(trans.translation.truncate(), 1.);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square() -> Vec<Vec2> {
+        vec![
+            Vec2::new(0., 0.),
+            Vec2::new(10., 0.),
+            Vec2::new(10., 10.),
+            Vec2::new(0., 10.),
+        ]
+    }
+
+    #[test]
+    fn hull_leaves_out_interior_points() {
+        let mut points = square();
+        points.push(Vec2::new(5., 5.));
+        points.push(Vec2::new(2., 8.));
+        let hull = convex_hull(&points);
+        assert_eq!(hull.len(), 4);
+        for corner in square() {
+            assert!(hull.contains(&corner));
+        }
+        // counter clockwise
+        assert!(
+            (0..hull.len())
+                .map(|i| hull[i].perp_dot(hull[(i + 1) % hull.len()]))
+                .sum::<f32>()
+                > 0.
+        );
+    }
+
+    #[test]
+    fn hull_of_collinear_points_is_a_line() {
+        let points = [Vec2::ZERO, Vec2::new(1., 1.), Vec2::new(2., 2.)];
+        assert_eq!(polygon_area(&convex_hull(&points)), 0.);
+    }
+
+    #[test]
+    fn area_of_known_polygons() {
+        assert_eq!(polygon_area(&square()), 100.);
+        let triangle = [Vec2::ZERO, Vec2::new(4., 0.), Vec2::new(0., 3.)];
+        assert_eq!(polygon_area(&triangle), 6.);
+        // winding does not matter
+        let mut clockwise = square();
+        clockwise.reverse();
+        assert_eq!(polygon_area(&clockwise), 100.);
+    }
+
+    #[test]
+    fn index_outside_grid_is_none() {
+        let grid = Grid::new(Vec2::new(-10., -10.), 5., 4, 4);
+        assert_eq!(grid.index(Vec2::new(-10., -10.)), Some(0));
+        assert_eq!(grid.index(Vec2::new(9.9, 9.9)), Some(15));
+        assert_eq!(grid.index(Vec2::new(-10.1, 0.)), None);
+        assert_eq!(grid.index(Vec2::new(0., -10.1)), None);
+        assert_eq!(grid.index(Vec2::new(10., 0.)), None);
+        assert_eq!(grid.index(Vec2::new(0., 10.)), None);
+    }
+
+    #[test]
+    fn center_matches_index() {
+        let grid = Grid::new(Vec2::new(-10., -10.), 5., 4, 4);
+        for i in 0..16 {
+            assert_eq!(grid.index(grid.center(i)), Some(i));
+        }
+    }
+
+    fn samples(points: &[Vec2]) -> Grid {
+        let mut grid = Grid::new(Vec2::ZERO, SPACE_USE_CELL_SIZE, 10, 10);
+        for p in points {
+            grid.add(*p, 1.);
+        }
+        grid
+    }
+
+    #[test]
+    fn too_few_samples_have_no_home_range() {
+        assert!(HomeRange::estimate(&samples(&[]), HOME_RANGE_FRACTION).is_none());
+        let two = samples(&[Vec2::splat(10.), Vec2::splat(50.)]);
+        assert!(HomeRange::estimate(&two, HOME_RANGE_FRACTION).is_none());
+    }
+
+    #[test]
+    fn samples_in_one_cell_are_counted() {
+        let range = HomeRange::estimate(&samples(&[Vec2::splat(5.); 3]), 1.).unwrap();
+        assert_eq!(range.samples, 3);
+        assert_eq!(range.mcp_area, 0.);
+        assert!(range.kde_area > 0.);
+    }
+
+    #[test]
+    fn home_range_of_a_square() {
+        // corners at cell centres, 100 apart
+        let corners = [
+            Vec2::new(10., 10.),
+            Vec2::new(110., 10.),
+            Vec2::new(110., 110.),
+            Vec2::new(10., 110.),
+        ];
+        let range = HomeRange::estimate(&samples(&corners), 1.).unwrap();
+        assert_eq!(range.samples, 4);
+        assert_eq!(range.mcp_area, 10000.);
+        assert!(range.kde_area > 0.);
+        assert_eq!(
+            range.kde_area,
+            range.kde_cells.len() as f32 * range.kde_cell_size * range.kde_cell_size
+        );
+    }
+
+    #[test]
+    fn mcp_leaves_out_outlying_cells() {
+        let mut points = vec![Vec2::new(50., 50.); 20];
+        points.extend([
+            Vec2::new(30., 50.),
+            Vec2::new(70., 50.),
+            Vec2::new(50., 30.),
+            Vec2::new(50., 70.),
+        ]);
+        points.push(Vec2::new(190., 190.));
+        let range = HomeRange::estimate(&samples(&points), HOME_RANGE_FRACTION).unwrap();
+        assert_eq!(range.samples, 25);
+        assert!(!range.mcp.contains(&Vec2::new(190., 190.)));
+    }
+}
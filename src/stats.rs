@@ -0,0 +1,209 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs,
+    path::PathBuf,
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    ai::hunger::{Hunger, KillEvent},
+    fish::Fish,
+    metrics::{CollectiveMetrics, GroupMetrics},
+    orca::{Orca, OrcaDeathEvent, PodId, PodPool, SpawnOrcaEvent},
+    sim::{RunSimEvent, Simulation},
+};
+
+/// simulation seconds between samples
+const SAMPLE_INTERVAL: f32 = 1.;
+/// window in simulation seconds that the kill rate is averaged over
+const KILL_RATE_WINDOW: f32 = 60.;
+
+/// state of the simulation at a point in time
+pub struct Sample {
+    pub time: f32,
+    pub pod_population: BTreeMap<PodId, usize>,
+    pub mean_hunger: f32,
+    pub fish_count: usize,
+    pub kills_per_minute: f32,
+    /// kills, births and deaths since the start of the run
+    pub kills: usize,
+    pub births: usize,
+    pub deaths: usize,
+    pub pod_metrics: BTreeMap<PodId, GroupMetrics>,
+    pub fish_metrics: Option<GroupMetrics>,
+}
+
+/// time series of samples taken over the current run
+#[derive(Default)]
+pub struct Stats {
+    pub samples: Vec<Sample>,
+    next_sample: f32,
+    /// simulation time of each kill within the kill rate window
+    kill_times: VecDeque<f32>,
+    /// orcas alive once the run's initial spawns went through
+    pub orcas_start: Option<usize>,
+    /// totals since the run started, which samples lag behind by up to a sample interval
+    pub kills: usize,
+    pub births: usize,
+    pub deaths: usize,
+}
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Stats::default())
+            .add_system(reset_stats)
+            .add_system(record_orcas_start)
+            .add_system(count_events)
+            .add_system(sample_stats);
+    }
+}
+
+impl Stats {
+    /// ids of every pod that appears in any sample, in order
+    pub fn pod_ids(&self) -> Vec<PodId> {
+        let mut ids: Vec<PodId> = self
+            .samples
+            .iter()
+            .flat_map(|sample| sample.pod_population.keys().copied())
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// write all samples as csv, with population and collective motion columns for each pod
+    pub fn write_csv(&self, path: &PathBuf, pod_pool: &PodPool) -> Result<(), String> {
+        let pod_ids = self.pod_ids();
+
+        let mut header =
+            String::from("time,fish_count,mean_hunger,kills_per_minute,kills,births,deaths");
+        for name in GroupMetrics::NAMES {
+            header.push_str(&format!(",fish_{}", name));
+        }
+        for pod_id in pod_ids.iter() {
+            let name = pod_pool
+                .get(pod_id)
+                .map(|pod| pod.name.replace('"', ""))
+                .unwrap_or_else(|| format!("pod {}", pod_id));
+            header.push_str(&format!(",\"{}\"", name));
+            for metric in GroupMetrics::NAMES {
+                header.push_str(&format!(",\"{} {}\"", name, metric));
+            }
+        }
+
+        let mut contents = header + "\n";
+        for sample in self.samples.iter() {
+            contents.push_str(&format!(
+                "{},{},{},{},{},{},{}",
+                sample.time,
+                sample.fish_count,
+                sample.mean_hunger,
+                sample.kills_per_minute,
+                sample.kills,
+                sample.births,
+                sample.deaths
+            ));
+            push_metrics(&mut contents, sample.fish_metrics.as_ref());
+            for pod_id in pod_ids.iter() {
+                let population = sample.pod_population.get(pod_id).copied().unwrap_or(0);
+                contents.push_str(&format!(",{}", population));
+                push_metrics(&mut contents, sample.pod_metrics.get(pod_id));
+            }
+            contents.push('\n');
+        }
+
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+}
+
+/// csv columns of a group's metrics, left empty if the group had no members
+fn push_metrics(contents: &mut String, metrics: Option<&GroupMetrics>) {
+    match metrics {
+        Some(metrics) => {
+            for value in metrics.values() {
+                contents.push_str(&format!(",{}", value));
+            }
+        },
+        None => contents.push_str(&",".repeat(GroupMetrics::NAMES.len())),
+    }
+}
+
+fn reset_stats(mut cmd: Commands, mut events: EventReader<RunSimEvent>) {
+    for _ in events.iter() {
+        cmd.insert_resource(Stats::default());
+    }
+}
+
+/// count the orcas a run starts with, the frame after its spawns were queued
+fn record_orcas_start(mut stats: ResMut<Stats>, orca_query: Query<(), With<Orca>>) {
+    if stats.orcas_start.is_none() {
+        stats.orcas_start = Some(orca_query.iter().count());
+    }
+}
+
+fn count_events(
+    sim: Res<Simulation>,
+    mut stats: ResMut<Stats>,
+    mut kill_events: EventReader<KillEvent>,
+    mut birth_events: EventReader<SpawnOrcaEvent>,
+    mut death_events: EventReader<OrcaDeathEvent>,
+) {
+    for _ in kill_events.iter() {
+        stats.kill_times.push_back(sim.time);
+        stats.kills += 1;
+    }
+    stats.births += birth_events.iter().count();
+    stats.deaths += death_events.iter().count();
+
+    while let Some(&time) = stats.kill_times.front() {
+        if sim.time - time <= KILL_RATE_WINDOW {
+            break;
+        }
+        stats.kill_times.pop_front();
+    }
+}
+
+fn sample_stats(
+    sim: Res<Simulation>,
+    mut stats: ResMut<Stats>,
+    pod_pool: Res<PodPool>,
+    metrics: Res<CollectiveMetrics>,
+    orca_query: Query<&Hunger, With<Orca>>,
+    fish_query: Query<(), With<Fish>>,
+) {
+    if sim.time < stats.next_sample {
+        return;
+    }
+    stats.next_sample = sim.time + SAMPLE_INTERVAL;
+
+    let orca_count = orca_query.iter().len();
+    let mean_hunger = if orca_count > 0 {
+        orca_query.iter().map(|hunger| hunger.0).sum::<f32>() / orca_count as f32
+    } else {
+        0.
+    };
+
+    // runs shorter than the window are averaged over the time passed so far
+    let window = sim.time.clamp(SAMPLE_INTERVAL, KILL_RATE_WINDOW);
+    let kills_per_minute = stats.kill_times.len() as f32 * 60. / window;
+
+    let sample = Sample {
+        time: sim.time,
+        pod_population: pod_pool
+            .iter()
+            .map(|(pod_id, pod)| (*pod_id, pod.members.len()))
+            .collect(),
+        mean_hunger,
+        fish_count: fish_query.iter().count(),
+        kills_per_minute,
+        kills: stats.kills,
+        births: stats.births,
+        deaths: stats.deaths,
+        pod_metrics: metrics.pods.clone(),
+        fish_metrics: metrics.fish,
+    };
+    stats.samples.push(sample);
+}
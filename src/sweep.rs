@@ -0,0 +1,184 @@
+use std::{
+    fs,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use rayon::prelude::*;
+
+use crate::{
+    cli::SweepArgs,
+    headless::{self, Summary},
+    scenario::Scenario,
+};
+
+/// values a single parameter takes over a sweep
+#[derive(Clone, Debug)]
+pub struct ParamRange {
+    pub name: String,
+    pub values: Vec<f32>,
+}
+
+impl FromStr for ParamRange {
+    type Err = String;
+
+    /// parse `name=start:end:step`, with the end included, or a list of values `name=a,b,c`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, range) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected name=start:end:step or name=a,b,c, got '{}'", s))?;
+        let parse = |v: &str| {
+            v.trim()
+                .parse::<f32>()
+                .map_err(|_| format!("invalid value '{}' for {}", v, name))
+        };
+
+        let values = if range.contains(':') {
+            let parts: Vec<&str> = range.split(':').collect();
+            if parts.len() != 3 {
+                return Err(format!(
+                    "expected start:end:step for {}, got '{}'",
+                    name, range
+                ));
+            }
+            let (start, end, step) = (parse(parts[0])?, parse(parts[1])?, parse(parts[2])?);
+            if step <= 0. || end < start {
+                return Err(format!(
+                    "range of {} must have a positive step and end after it starts",
+                    name
+                ));
+            }
+            // count steps rather than accumulating them to avoid drift
+            let count = ((end - start) / step + 1e-4).floor() as usize;
+            (0..=count).map(|i| start + i as f32 * step).collect()
+        } else {
+            range.split(',').map(parse).collect::<Result<Vec<_>, _>>()?
+        };
+
+        // catch typos before spending any time running
+        Scenario::default().set(name, values[0])?;
+
+        Ok(Self {
+            name: name.to_string(),
+            values,
+        })
+    }
+}
+
+/// every combination of parameter values
+fn combinations(params: &[ParamRange]) -> Vec<Vec<f32>> {
+    params.iter().fold(vec![vec![]], |combos, param| {
+        combos
+            .iter()
+            .flat_map(|combo| {
+                param.values.iter().map(move |value| {
+                    let mut combo = combo.clone();
+                    combo.push(*value);
+                    combo
+                })
+            })
+            .collect()
+    })
+}
+
+pub fn sweep(args: SweepArgs) -> Result<(), String> {
+    let base = match &args.scenario {
+        Some(path) => Scenario::load(path)
+            .map_err(|e| format!("failed to load scenario {}: {}", path.display(), e))?,
+        None => Scenario::default(),
+    };
+    if args.duration <= 0. {
+        return Err(String::from("duration must be positive"));
+    }
+
+    let runs: Vec<(Vec<f32>, u64)> = combinations(&args.params)
+        .into_iter()
+        .flat_map(|combo| (0..args.seeds).map(move |i| (combo.clone(), args.seed + i)))
+        .collect();
+    println!(
+        "running {} simulations of {}s each",
+        runs.len(),
+        args.duration
+    );
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.jobs.unwrap_or(0))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let done = AtomicUsize::new(0);
+    let results: Vec<Result<Summary, String>> = pool.install(|| {
+        runs.par_iter()
+            .map(|(values, seed)| {
+                let mut scenario = base.clone();
+                for (param, value) in args.params.iter().zip(values.iter()) {
+                    scenario.set(&param.name, *value)?;
+                }
+                let summary = headless::run(&scenario, *seed, args.duration);
+                println!(
+                    "finished {}/{}",
+                    done.fetch_add(1, Ordering::Relaxed) + 1,
+                    runs.len()
+                );
+                Ok(summary)
+            })
+            .collect()
+    });
+
+    let mut contents = String::new();
+    for param in args.params.iter() {
+        contents.push_str(&param.name);
+        contents.push(',');
+    }
+    contents.push_str("seed,");
+    contents.push_str(Summary::HEADER);
+    contents.push('\n');
+    for ((values, seed), result) in runs.iter().zip(results.into_iter()) {
+        for value in values {
+            contents.push_str(&format!("{},", value));
+        }
+        contents.push_str(&format!("{},{}\n", seed, result?.csv_row()));
+    }
+
+    fs::write(&args.output, contents)
+        .map_err(|e| format!("failed to write {}: {}", args.output.display(), e))?;
+    println!("wrote results to {}", args.output.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range_includes_end() {
+        let param: ParamRange = "orca.coherence=0:1:0.25".parse().unwrap();
+        assert_eq!(param.name, "orca.coherence");
+        assert_eq!(param.values, vec![0., 0.25, 0.5, 0.75, 1.]);
+    }
+
+    #[test]
+    fn list_of_values() {
+        let param: ParamRange = "pod_count=1, 3,5".parse().unwrap();
+        assert_eq!(param.values, vec![1., 3., 5.]);
+    }
+
+    #[test]
+    fn reversed_bounds_are_rejected() {
+        assert!("pod_count=5:1:1".parse::<ParamRange>().is_err());
+    }
+
+    #[test]
+    fn zero_step_is_rejected() {
+        assert!("pod_count=1:5:0".parse::<ParamRange>().is_err());
+        assert!("pod_count=1:5:-1".parse::<ParamRange>().is_err());
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        assert!("pod_count".parse::<ParamRange>().is_err());
+        assert!("pod_count=1:5".parse::<ParamRange>().is_err());
+        assert!("pod_count=a,b".parse::<ParamRange>().is_err());
+        assert!("not_a_param=1,2".parse::<ParamRange>().is_err());
+    }
+}
@@ -0,0 +1,239 @@
+use std::{fs, path::PathBuf};
+
+use clap::ValueEnum;
+use rand::{prelude::*, rngs::StdRng};
+use rayon::prelude::*;
+
+use crate::{
+    cli::TuneArgs,
+    headless::{self, Summary},
+    scenario::{Scenario, PARAMS},
+};
+
+/// which boids have their parameters evolved
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Species {
+    Orca,
+    Fish,
+    Both,
+}
+
+/// what a parameter set is scored on, higher is better
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Fitness {
+    /// kills per orca over the run
+    Hunting,
+    /// fraction of orcas still alive at the end
+    Survival,
+    /// closeness of the mean pod polarization to the target
+    PodPolarization,
+    /// closeness of the mean fish school polarization to the target
+    FishPolarization,
+}
+
+impl Fitness {
+    fn score(&self, summary: &Summary, target: f32) -> f32 {
+        match self {
+            Fitness::Hunting => summary.kills as f32 / summary.orcas_start.max(1) as f32,
+            Fitness::Survival => summary.survival,
+            Fitness::PodPolarization => 1. - (summary.pod_polarization - target).abs(),
+            Fitness::FishPolarization => 1. - (summary.fish_polarization - target).abs(),
+        }
+    }
+}
+
+/// a candidate parameter set and how well it did
+#[derive(Clone)]
+struct Individual {
+    genes: Vec<f32>,
+    fitness: f32,
+}
+
+/// range a boid parameter is kept within, matching the sliders in the ui
+fn bounds(param: &str) -> (f32, f32) {
+    match param.split_once('.').map(|(_, name)| name) {
+        Some("view_range") => (0., 500.),
+        Some("view_angle") => (0., 180.),
+        Some("peripheral_falloff") => (0., 1.),
+        _ => (0., 10.),
+    }
+}
+
+fn random_genes(params: &[&str], rng: &mut StdRng) -> Vec<f32> {
+    params
+        .iter()
+        .map(|param| {
+            let (min, max) = bounds(param);
+            rng.gen_range(min..=max)
+        })
+        .collect()
+}
+
+/// pick the fittest of a few random individuals
+fn tournament<'a>(population: &'a [Individual], size: usize, rng: &mut StdRng) -> &'a Individual {
+    (0..size)
+        .map(|_| population.choose(rng).unwrap())
+        .max_by(|a, b| a.fitness.total_cmp(&b.fitness))
+        .unwrap()
+}
+
+/// blend two parents gene by gene, then nudge some genes by a fraction of their range
+fn offspring(
+    params: &[&str],
+    a: &Individual,
+    b: &Individual,
+    mutation_rate: f32,
+    rng: &mut StdRng,
+) -> Vec<f32> {
+    params
+        .iter()
+        .zip(a.genes.iter().zip(b.genes.iter()))
+        .map(|(param, (a, b))| {
+            let (min, max) = bounds(param);
+            let t = rng.gen_range(0.0f32..=1.);
+            let mut gene = a + (b - a) * t;
+            if rng.gen_bool(mutation_rate as f64) {
+                gene += rng.gen_range(-1.0f32..=1.) * 0.1 * (max - min);
+            }
+            gene.clamp(min, max)
+        })
+        .collect()
+}
+
+fn scenario_with(base: &Scenario, params: &[&str], genes: &[f32]) -> Scenario {
+    let mut scenario = base.clone();
+    for (param, gene) in params.iter().zip(genes.iter()) {
+        // every tuned parameter comes from PARAMS so this can't fail
+        scenario.set(param, *gene).unwrap();
+    }
+    scenario
+}
+
+pub fn tune(args: TuneArgs) -> Result<(), String> {
+    let base = match &args.scenario {
+        Some(path) => Scenario::load(path)
+            .map_err(|e| format!("failed to load scenario {}: {}", path.display(), e))?,
+        None => Scenario::default(),
+    };
+    if args.duration <= 0. {
+        return Err(String::from("duration must be positive"));
+    }
+    if args.population < 2 {
+        return Err(String::from("population must be at least 2"));
+    }
+    if args.seeds == 0 {
+        return Err(String::from("seeds must be at least 1"));
+    }
+    if !(0. ..=1.).contains(&args.mutation_rate) {
+        return Err(String::from("mutation rate must be between 0 and 1"));
+    }
+
+    let params: Vec<&str> = PARAMS
+        .iter()
+        .copied()
+        .filter(|param| match args.species {
+            Species::Orca => param.starts_with("orca."),
+            Species::Fish => param.starts_with("fish."),
+            Species::Both => param.contains('.'),
+        })
+        .collect();
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.jobs.unwrap_or(0))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut rng = StdRng::seed_from_u64(args.seed);
+
+    // start from the scenario's own parameters alongside random ones
+    let mut population: Vec<Individual> = (0..args.population)
+        .map(|i| Individual {
+            genes: if i == 0 {
+                params
+                    .iter()
+                    .map(|param| base.get(param).unwrap())
+                    .collect()
+            } else {
+                random_genes(&params, &mut rng)
+            },
+            fitness: 0.,
+        })
+        .collect();
+    let mut best: Vec<Individual> = vec![];
+    let mut history = String::from("generation,best,mean\n");
+
+    for generation in 0..args.generations {
+        // every individual in a generation sees the same seeds, new seeds each generation keep
+        // parameters from overfitting to them
+        let seeds: Vec<u64> = (0..args.seeds)
+            .map(|i| args.seed + generation as u64 * args.seeds + i)
+            .collect();
+        let runs: Vec<(usize, u64)> = (0..population.len())
+            .flat_map(|i| seeds.iter().map(move |seed| (i, *seed)))
+            .collect();
+
+        let scores: Vec<(usize, f32)> = pool.install(|| {
+            runs.par_iter()
+                .map(|(i, seed)| {
+                    let scenario = scenario_with(&base, &params, &population[*i].genes);
+                    let summary = headless::run(&scenario, *seed, args.duration);
+                    (*i, args.fitness.score(&summary, args.target_polarization))
+                })
+                .collect()
+        });
+        for individual in population.iter_mut() {
+            individual.fitness = 0.;
+        }
+        for (i, score) in scores {
+            population[i].fitness += score / args.seeds as f32;
+        }
+        population.sort_by(|a, b| b.fitness.total_cmp(&a.fitness));
+
+        let mean = population.iter().map(|i| i.fitness).sum::<f32>() / population.len() as f32;
+        println!(
+            "generation {}/{}: best {:.3}, mean {:.3}",
+            generation + 1,
+            args.generations,
+            population[0].fitness,
+            mean
+        );
+        history.push_str(&format!(
+            "{},{},{}\n",
+            generation, population[0].fitness, mean
+        ));
+
+        for individual in population.iter().take(args.keep) {
+            // elites are scored again each generation, so average their scores over the seeds
+            match best.iter_mut().find(|b| b.genes == individual.genes) {
+                Some(existing) => existing.fitness = (existing.fitness + individual.fitness) / 2.,
+                None => best.push(individual.clone()),
+            }
+        }
+        best.sort_by(|a, b| b.fitness.total_cmp(&a.fitness));
+        best.truncate(args.keep);
+
+        // the fittest carry over unchanged, the rest are bred from tournament winners
+        let elite = args.elite.min(population.len());
+        let mut next: Vec<Individual> = population.iter().take(elite).cloned().collect();
+        while next.len() < population.len() {
+            let a = tournament(&population, 3, &mut rng);
+            let b = tournament(&population, 3, &mut rng);
+            next.push(Individual {
+                genes: offspring(&params, a, b, args.mutation_rate, &mut rng),
+                fitness: 0.,
+            });
+        }
+        population = next;
+    }
+
+    fs::create_dir_all(&args.output)
+        .map_err(|e| format!("failed to create {}: {}", args.output.display(), e))?;
+    fs::write(args.output.join("history.csv"), history).map_err(|e| e.to_string())?;
+    for (rank, individual) in best.iter().enumerate() {
+        let path: PathBuf = args.output.join(format!("best_{}.json", rank + 1));
+        scenario_with(&base, &params, &individual.genes)
+            .save(&path)
+            .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+        println!("fitness {:.3}: {}", individual.fitness, path.display());
+    }
+    Ok(())
+}
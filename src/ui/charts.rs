@@ -0,0 +1,257 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{
+        plot::{Legend, Line, Plot, PlotPoints},
+        Grid, Slider, Ui, Window,
+    },
+    EguiContext,
+};
+
+use crate::{
+    export::{Recording, StartRecordingEvent, StopRecordingEvent, DEFAULT_RECORD_INTERVAL},
+    metrics::{CollectiveMetrics, GroupMetrics},
+    orca::{PodId, PodPool},
+    stats::{Sample, Stats},
+};
+
+const CHART_HEIGHT: f32 = 200.;
+
+pub struct ChartsState {
+    pub show: bool,
+    pub pod_population: bool,
+    pub mean_hunger: bool,
+    pub fish_count: bool,
+    pub kills_per_minute: bool,
+    pub births: bool,
+    pub deaths: bool,
+    pub polarization: bool,
+    pub milling: bool,
+    pub export_path: String,
+    pub record_dir: String,
+    pub record_interval: f32,
+}
+
+impl Default for ChartsState {
+    fn default() -> Self {
+        Self {
+            show: false,
+            pod_population: true,
+            mean_hunger: true,
+            fish_count: true,
+            kills_per_minute: true,
+            births: true,
+            deaths: true,
+            polarization: true,
+            milling: false,
+            export_path: String::from("stats.csv"),
+            record_dir: String::from("recording"),
+            record_interval: DEFAULT_RECORD_INTERVAL,
+        }
+    }
+}
+
+pub struct ChartsPlugin;
+
+impl Plugin for ChartsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ChartsState::default())
+            .add_system(render_charts)
+            .add_system(charts_controller);
+    }
+}
+
+fn series(stats: &Stats, value: impl Fn(&Sample) -> f64) -> PlotPoints {
+    PlotPoints::new(
+        stats
+            .samples
+            .iter()
+            .map(|sample| [sample.time as f64, value(sample)])
+            .collect(),
+    )
+}
+
+/// name of a pod, which may have been removed since it was sampled
+fn pod_name(pod_pool: &PodPool, pod_id: PodId) -> String {
+    pod_pool
+        .get(&pod_id)
+        .map(|pod| pod.name.clone())
+        .unwrap_or_else(|| format!("pod {}", pod_id))
+}
+
+fn render_charts(
+    mut ctx: ResMut<EguiContext>,
+    mut charts_state: ResMut<ChartsState>,
+    stats: Res<Stats>,
+    pod_pool: Res<PodPool>,
+    metrics: Res<CollectiveMetrics>,
+    recording: Option<Res<Recording>>,
+    mut start_recording_writer: EventWriter<StartRecordingEvent>,
+    mut stop_recording_writer: EventWriter<StopRecordingEvent>,
+) {
+    if !charts_state.show {
+        return;
+    }
+
+    Window::new("Charts")
+        .default_width(500.)
+        .show(ctx.ctx_mut(), |ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.checkbox(&mut charts_state.pod_population, "Pod Population");
+                ui.checkbox(&mut charts_state.mean_hunger, "Mean Hunger");
+                ui.checkbox(&mut charts_state.fish_count, "Fish Count");
+                ui.checkbox(&mut charts_state.kills_per_minute, "Kills / Minute");
+                ui.checkbox(&mut charts_state.births, "Births");
+                ui.checkbox(&mut charts_state.deaths, "Deaths");
+                ui.checkbox(&mut charts_state.polarization, "Polarization");
+                ui.checkbox(&mut charts_state.milling, "Milling");
+            });
+
+            // counts of animals and events share one chart
+            Plot::new("population")
+                .height(CHART_HEIGHT)
+                .legend(Legend::default())
+                .show(ui, |plot_ui| {
+                    if charts_state.pod_population {
+                        for pod_id in stats.pod_ids() {
+                            let name = pod_name(&pod_pool, pod_id);
+                            let points = series(&stats, |sample| {
+                                sample.pod_population.get(&pod_id).copied().unwrap_or(0) as f64
+                            });
+                            plot_ui.line(Line::new(points).name(name));
+                        }
+                    }
+                    if charts_state.fish_count {
+                        let points = series(&stats, |sample| sample.fish_count as f64);
+                        plot_ui.line(Line::new(points).name("fish"));
+                    }
+                    if charts_state.births {
+                        let points = series(&stats, |sample| sample.births as f64);
+                        plot_ui.line(Line::new(points).name("births"));
+                    }
+                    if charts_state.deaths {
+                        let points = series(&stats, |sample| sample.deaths as f64);
+                        plot_ui.line(Line::new(points).name("deaths"));
+                    }
+                });
+
+            // rates are on a much smaller scale than the counts
+            Plot::new("rates")
+                .height(CHART_HEIGHT)
+                .legend(Legend::default())
+                .show(ui, |plot_ui| {
+                    if charts_state.mean_hunger {
+                        let points = series(&stats, |sample| sample.mean_hunger as f64);
+                        plot_ui.line(Line::new(points).name("mean hunger"));
+                    }
+                    if charts_state.kills_per_minute {
+                        let points = series(&stats, |sample| sample.kills_per_minute as f64);
+                        plot_ui.line(Line::new(points).name("kills / minute"));
+                    }
+                });
+
+            // order parameters lie between zero and one
+            Plot::new("formation")
+                .height(CHART_HEIGHT)
+                .legend(Legend::default())
+                .include_y(0.)
+                .include_y(1.)
+                .show(ui, |plot_ui| {
+                    // the fish school is the group without a pod
+                    let mut groups: Vec<(String, Option<PodId>)> =
+                        vec![(String::from("fish"), None)];
+                    for pod_id in stats.pod_ids() {
+                        groups.push((pod_name(&pod_pool, pod_id), Some(pod_id)));
+                    }
+
+                    for (name, group) in groups {
+                        let value = |sample: &Sample, f: fn(&GroupMetrics) -> f32| {
+                            let metrics = match group {
+                                Some(pod_id) => sample.pod_metrics.get(&pod_id),
+                                None => sample.fish_metrics.as_ref(),
+                            };
+                            metrics.map(f).unwrap_or(0.) as f64
+                        };
+                        if charts_state.polarization {
+                            let points = series(&stats, |sample| value(sample, |m| m.polarization));
+                            plot_ui.line(Line::new(points).name(format!("{} polarization", name)));
+                        }
+                        if charts_state.milling {
+                            let points = series(&stats, |sample| value(sample, |m| m.milling));
+                            plot_ui.line(Line::new(points).name(format!("{} milling", name)));
+                        }
+                    }
+                });
+
+            ui.collapsing("Collective Motion", |ui| {
+                Grid::new("collective_motion").striped(true).show(ui, |ui| {
+                    ui.label("group");
+                    ui.label("size");
+                    for name in GroupMetrics::NAMES {
+                        ui.label(name.replace('_', " "));
+                    }
+                    ui.end_row();
+
+                    let row = |ui: &mut Ui, name: String, m: &GroupMetrics| {
+                        ui.label(name);
+                        ui.label(m.size.to_string());
+                        for value in m.values() {
+                            ui.label(format!("{:.2}", value));
+                        }
+                        ui.end_row();
+                    };
+                    if let Some(fish) = &metrics.fish {
+                        row(ui, String::from("fish"), fish);
+                    }
+                    let mut pod_ids: Vec<_> = metrics.pods.keys().copied().collect();
+                    pod_ids.sort_unstable();
+                    for pod_id in pod_ids {
+                        row(ui, pod_name(&pod_pool, pod_id), &metrics.pods[&pod_id]);
+                    }
+                });
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut charts_state.export_path);
+                if ui.button("Export CSV").clicked() {
+                    let path = PathBuf::from(&charts_state.export_path);
+                    match stats.write_csv(&path, &pod_pool) {
+                        Ok(()) => info!("exported stats to {}", path.display()),
+                        Err(e) => warn!("failed to export stats {}: {}", path.display(), e),
+                    }
+                }
+            });
+
+            ui.separator();
+            ui.label("Trajectory and event recording");
+            match &recording {
+                Some(recording) => {
+                    ui.label(format!("recording to {}", recording.dir.display()));
+                    if ui.button("Stop Recording").clicked() {
+                        stop_recording_writer.send(StopRecordingEvent);
+                    }
+                },
+                None => {
+                    ui.text_edit_singleline(&mut charts_state.record_dir);
+                    ui.add(
+                        Slider::new(&mut charts_state.record_interval, 0.1f32..=10.)
+                            .text("Sample Interval"),
+                    );
+                    if ui.button("Start Recording").clicked() {
+                        start_recording_writer.send(StartRecordingEvent {
+                            dir: PathBuf::from(&charts_state.record_dir),
+                            interval: charts_state.record_interval,
+                        });
+                    }
+                },
+            }
+        });
+}
+
+fn charts_controller(keys: Res<Input<KeyCode>>, mut charts_state: ResMut<ChartsState>) {
+    if keys.just_pressed(KeyCode::C) {
+        charts_state.show = !charts_state.show;
+    }
+}
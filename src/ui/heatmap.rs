@@ -0,0 +1,248 @@
+use std::{fs, path::PathBuf};
+
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use bevy_egui::{
+    egui::{ComboBox, Grid as UiGrid, Slider, Window},
+    EguiContext,
+};
+use bevy_prototype_lyon::prelude::*;
+
+use super::DebugLine;
+use crate::{
+    orca::{PodId, PodPool},
+    space_use::{Grid, HomeRange, Layer, SpaceUse, HOME_RANGE_FRACTION},
+};
+
+/// seconds between redraws of the heatmap texture
+const HEATMAP_REFRESH: f32 = 1.;
+/// above the map, below everything swimming on it
+const HEATMAP_Z: f32 = -5.;
+
+pub struct HeatmapState {
+    pub show: bool,
+    pub layer: Option<Layer>,
+    pub opacity: f32,
+    pub export_dir: String,
+    pub show_home_ranges: bool,
+    pub home_ranges: Vec<(PodId, HomeRange)>,
+}
+
+impl Default for HeatmapState {
+    fn default() -> Self {
+        Self {
+            show: false,
+            layer: None,
+            opacity: 0.6,
+            export_dir: String::from("space_use"),
+            show_home_ranges: false,
+            home_ranges: vec![],
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct HeatmapSprite;
+
+pub struct HeatmapPlugin;
+
+impl Plugin for HeatmapPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HeatmapState::default())
+            .add_system(render_heatmap_ui)
+            .add_system(heatmap_controller)
+            .add_system(draw_heatmap)
+            .add_system(draw_home_ranges);
+    }
+}
+
+fn render_heatmap_ui(
+    mut ctx: ResMut<EguiContext>,
+    mut heatmap_state: ResMut<HeatmapState>,
+    space_use: Res<SpaceUse>,
+    pod_pool: Res<PodPool>,
+) {
+    if !heatmap_state.show {
+        return;
+    }
+
+    let layer_name = |layer: Option<Layer>| layer.map(|layer| layer.name()).unwrap_or("none");
+
+    Window::new("Space Use").show(ctx.ctx_mut(), |ui| {
+        let state = &mut *heatmap_state;
+        ComboBox::from_label("Heatmap")
+            .selected_text(layer_name(state.layer))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut state.layer, None, layer_name(None));
+                for layer in Layer::ALL {
+                    ui.selectable_value(&mut state.layer, Some(layer), layer.name());
+                }
+            });
+        ui.add(Slider::new(&mut state.opacity, 0.0f32..=1.).text("Opacity"));
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Estimate Home Ranges").clicked() {
+                state.home_ranges = space_use.home_ranges(HOME_RANGE_FRACTION);
+                state.show_home_ranges = true;
+            }
+            ui.checkbox(&mut state.show_home_ranges, "Show");
+        });
+        if !state.home_ranges.is_empty() {
+            UiGrid::new("home_ranges").striped(true).show(ui, |ui| {
+                for name in ["pod", "samples", "mcp area", "kde area"] {
+                    ui.label(name);
+                }
+                ui.end_row();
+                for (pod_id, range) in state.home_ranges.iter() {
+                    ui.label(
+                        pod_pool
+                            .get(pod_id)
+                            .map(|pod| pod.name.clone())
+                            .unwrap_or_else(|| format!("pod {}", pod_id)),
+                    );
+                    ui.label(range.samples.to_string());
+                    ui.label(format!("{:.0}", range.mcp_area));
+                    ui.label(format!("{:.0}", range.kde_area));
+                    ui.end_row();
+                }
+            });
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut state.export_dir);
+            if ui.button("Export").clicked() {
+                let dir = PathBuf::from(&state.export_dir);
+                let result = fs::create_dir_all(&dir)
+                    .map_err(|e| e.to_string())
+                    .and_then(|_| space_use.write(&dir, &pod_pool));
+                match result {
+                    Ok(()) => info!("wrote space use to {}", dir.display()),
+                    Err(e) => warn!("failed to write space use to {}: {}", dir.display(), e),
+                }
+            }
+        });
+    });
+}
+
+fn heatmap_image(grid: &Grid, opacity: f32) -> Image {
+    Image::new(
+        Extent3d {
+            width: grid.width as u32,
+            height: grid.height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        grid.to_rgba(opacity),
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+/// keep a sprite of the chosen layer over the map, redrawn every so often
+fn draw_heatmap(
+    mut cmd: Commands,
+    time: Res<Time>,
+    heatmap_state: Res<HeatmapState>,
+    space_use: Res<SpaceUse>,
+    mut images: ResMut<Assets<Image>>,
+    mut query: Query<(Entity, &Handle<Image>, &mut Sprite, &mut Transform), With<HeatmapSprite>>,
+    mut since_refresh: Local<f32>,
+    mut drawn: Local<Option<(Layer, f32)>>,
+) {
+    let layer = match heatmap_state.layer {
+        Some(layer) => layer,
+        None => {
+            for (entity, ..) in query.iter() {
+                cmd.entity(entity).despawn();
+            }
+            *drawn = None;
+            return;
+        },
+    };
+    let grid = space_use.layer(layer);
+
+    // the ui holds on to the state mutably, so compare with what was drawn rather than
+    // relying on change detection
+    let wanted = Some((layer, heatmap_state.opacity));
+
+    *since_refresh += time.delta_seconds();
+    // the grid follows the world bounds of the run
+    let center = (grid.origin + grid.size() / 2.).extend(HEATMAP_Z);
+    match query.get_single_mut() {
+        Ok((_, handle, mut sprite, mut trans)) => {
+            if *since_refresh < HEATMAP_REFRESH && *drawn == wanted {
+                return;
+            }
+            if let Some(image) = images.get_mut(handle) {
+                *image = heatmap_image(grid, heatmap_state.opacity);
+            }
+            sprite.custom_size = Some(grid.size());
+            trans.translation = center;
+        },
+        Err(_) => {
+            cmd.spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(grid.size()),
+                    ..default()
+                },
+                texture: images.add(heatmap_image(grid, heatmap_state.opacity)),
+                transform: Transform::from_translation(center),
+                ..default()
+            })
+            .insert(HeatmapSprite);
+        },
+    }
+    *since_refresh = 0.;
+    *drawn = wanted;
+}
+
+/// outline of each pod's minimum convex polygon and the cells of its kernel density range
+fn draw_home_ranges(mut cmd: Commands, heatmap_state: Res<HeatmapState>, pod_pool: Res<PodPool>) {
+    if !heatmap_state.show_home_ranges {
+        return;
+    }
+
+    for (pod_id, range) in heatmap_state.home_ranges.iter() {
+        let color = pod_pool
+            .get(pod_id)
+            .map(|pod| pod.color)
+            .unwrap_or(Color::WHITE);
+
+        cmd.spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Polygon {
+                points: range.mcp.clone(),
+                closed: true,
+            },
+            DrawMode::Stroke(StrokeMode::new(color, 1.)),
+            Transform::from_xyz(0., 0., HEATMAP_Z + 1.),
+        ))
+        .insert(DebugLine);
+
+        let mut fill = color;
+        fill.set_a(0.15);
+        let half = range.kde_cell_size / 2.;
+        let mut path = PathBuilder::new();
+        for center in range.kde_cells.iter() {
+            path.move_to(*center + Vec2::new(-half, -half));
+            path.line_to(*center + Vec2::new(half, -half));
+            path.line_to(*center + Vec2::new(half, half));
+            path.line_to(*center + Vec2::new(-half, half));
+            path.close();
+        }
+        cmd.spawn_bundle(GeometryBuilder::build_as(
+            &path.build(),
+            DrawMode::Fill(FillMode::color(fill)),
+            Transform::from_xyz(0., 0., HEATMAP_Z + 1.),
+        ))
+        .insert(DebugLine);
+    }
+}
+
+fn heatmap_controller(keys: Res<Input<KeyCode>>, mut heatmap_state: ResMut<HeatmapState>) {
+    if keys.just_pressed(KeyCode::H) {
+        heatmap_state.show = !heatmap_state.show;
+    }
+}
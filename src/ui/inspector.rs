@@ -0,0 +1,406 @@
+use bevy::prelude::*;
+use bevy_bobs::physics_2d::RigidBody;
+use bevy_egui::{
+    egui::{ComboBox, DragValue, Grid, ScrollArea, Slider, Ui, Window},
+    EguiContext,
+};
+use bevy_prototype_lyon::prelude::*;
+use big_brain::prelude::*;
+use pino_utils::{ok_or_return, some_or_return};
+
+use super::Selected;
+use crate::{
+    acoustic::{call_name, CallMemory},
+    ai::{
+        decision::{DecisionLog, ThinkerDef},
+        diving::{Breath, DepthShaded},
+        echolocation::{Echolocation, PassiveListening},
+        hunger::{ForceHunt, Hunger},
+        movement::{FishNeighbouring, Movement, OrcaNeighbouring, Sight},
+    },
+    export::Activity,
+    fish::Fish,
+    orca::{
+        body_scale, outline_color, CloneOrcaEvent, DespawnOrcaEvent, Gender, Orca, PodChangeEvent,
+        PodId, PodPool,
+    },
+    vessel::{NoiseField, Vessel, VesselKind},
+};
+
+#[derive(Default)]
+pub struct InspectorState {
+    teleport: Vec2,
+}
+
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(InspectorState::default())
+            .add_system(render_inspector)
+            .add_system(render_fish_inspector)
+            .add_system(render_vessel_inspector);
+    }
+}
+
+fn render_inspector(
+    mut cmd: Commands,
+    mut ctx: ResMut<EguiContext>,
+    mut inspector_state: ResMut<InspectorState>,
+    selected: Option<Res<Selected>>,
+    mut pod_pool: ResMut<PodPool>,
+    noise_field: Res<NoiseField>,
+    mut orca_query: Query<
+        (
+            &mut Orca,
+            &mut Hunger,
+            &mut Movement,
+            &mut Transform,
+            &mut Sight,
+            &OrcaNeighbouring,
+            &RigidBody,
+            &Breath,
+            &CallMemory,
+            &DecisionLog,
+            &Children,
+            Option<&Echolocation>,
+            Option<&PassiveListening>,
+            Option<&ForceHunt>,
+        ),
+        Without<DepthShaded>,
+    >,
+    mut body_query: Query<(&mut Transform, &mut DrawMode, &mut DepthShaded), Without<Orca>>,
+    scorer_query: Query<(&Actor, &Score, Option<&Name>)>,
+    action_query: Query<(&Actor, &ActionState, Option<&Name>)>,
+    def_query: Query<&ThinkerDef>,
+    mut despawn_writer: EventWriter<DespawnOrcaEvent>,
+    mut clone_writer: EventWriter<CloneOrcaEvent>,
+    mut pod_change_writer: EventWriter<PodChangeEvent>,
+) {
+    let entity = some_or_return!(selected).0;
+    let (
+        mut orca,
+        mut hunger,
+        mut movement,
+        mut trans,
+        mut sight,
+        neighbouring,
+        rb,
+        breath,
+        call_memory,
+        decision_log,
+        children,
+        echolocation,
+        passive_listening,
+        forced,
+    ) = ok_or_return!(orca_query.get_mut(entity));
+
+    let before = (orca.gender, orca.age, orca.pod_id);
+
+    Window::new("Inspector")
+        .default_width(250.)
+        .show(ctx.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("name");
+                ui.text_edit_singleline(&mut orca.name);
+            });
+            ui.label(format!("type: {}", orca.orca_type.to_string()));
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut orca.gender, Gender::Male, "Male");
+                ui.radio_value(&mut orca.gender, Gender::Female, "Female");
+            });
+            ui.add(Slider::new(&mut orca.age, 0..=90).text("Age"));
+            ui.add(Slider::new(&mut orca.mass, 100.0f32..=6000.).text("Mass (kg)"));
+            ui.add(Slider::new(&mut hunger.0, 0.0f32..=1.).text("Hunger"));
+
+            let pod_name = |pod_id: Option<PodId>| {
+                pod_id
+                    .and_then(|pod_id| pod_pool.get(&pod_id))
+                    .map(|pod| pod.name.clone())
+                    .unwrap_or_else(|| String::from("none"))
+            };
+            let mut pod_ids: Vec<PodId> = pod_pool.keys().copied().collect();
+            pod_ids.sort_unstable();
+            ComboBox::from_label("Pod")
+                .selected_text(pod_name(orca.pod_id))
+                .show_ui(ui, |ui| {
+                    for pod_id in pod_ids {
+                        ui.selectable_value(&mut orca.pod_id, Some(pod_id), pod_name(Some(pod_id)));
+                    }
+                });
+            if let Some(pod) = orca.pod_id.and_then(|pod_id| pod_pool.get(&pod_id)) {
+                ui.label(format!(
+                    "dialect: {}",
+                    pod.dialect
+                        .calls
+                        .iter()
+                        .map(|call| call_name(*call))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+
+            ui.separator();
+            ui.label(format!(
+                "behaviour: {}",
+                Activity::of(&movement, rb, Some(breath)).name()
+            ));
+            ui.label(format!("depth: {} m", movement.depth.round()));
+            ui.label(format!(
+                "breath held: {}/{}s",
+                breath.held.round(),
+                breath.max_hold
+            ));
+            ui.label(format!(
+                "noise: {}",
+                (noise_field.level_at(trans.translation.truncate()) * 100.).round() / 100.
+            ));
+            if let Some(echolocation) = echolocation {
+                ui.label(format!(
+                    "echolocating: {}",
+                    if echolocation.active { "yes" } else { "no" }
+                ));
+            }
+            if passive_listening.is_some() {
+                ui.label("listening passively");
+            }
+            if let Some(heard) = &call_memory.heard {
+                ui.label(format!(
+                    "last heard: {} at {}s",
+                    call_name(heard.call),
+                    heard.time.round()
+                ));
+            }
+
+            ui.collapsing("Perception", |ui| {
+                sight_sliders(ui, &mut sight);
+                ui.label(format!(
+                    "sees {} pod members, {} prey",
+                    neighbouring.pod_members.len(),
+                    neighbouring.prey.len()
+                ));
+            });
+            ui.collapsing("Movement", |ui| {
+                ui.add(Slider::new(&mut movement.coherence, 0.0f32..=10.).text("Coherence"));
+                ui.add(Slider::new(&mut movement.alignment, 0.0f32..=10.).text("Alignment"));
+                ui.add(Slider::new(&mut movement.seperation, 0.0f32..=10.).text("Seperation"));
+                ui.add(Slider::new(&mut movement.randomess, 0.0f32..=10.).text("Randomness"));
+                ui.add(Slider::new(&mut movement.tracking, 0.0f32..=10.).text("Tracking"));
+                ui.add(Slider::new(&mut movement.avoidance, 0.0f32..=20.).text("Avoidance"));
+                ui.add(Slider::new(&mut movement.speed_scale, 0.0f32..=5.).text("Speed Scale"));
+            });
+
+            // why the thinker picked what it is doing
+            ui.collapsing("Decisions", |ui| {
+                Grid::new("scorers").show(ui, |ui| {
+                    for (Actor(actor), score, name) in scorer_query.iter() {
+                        if *actor == entity {
+                            ui.label(name.map(|name| name.as_str()).unwrap_or("scorer"));
+                            ui.label(format!("{:.2}", score.get()));
+                            ui.end_row();
+                        }
+                    }
+                    if let Ok(def) = def_query.get(entity) {
+                        ui.label("picker");
+                        ui.label(def.picker.describe());
+                        ui.end_row();
+                    }
+                });
+                let active: Vec<String> = action_query
+                    .iter()
+                    .filter(|(Actor(actor), _, _)| *actor == entity)
+                    .map(|(_, state, name)| {
+                        format!(
+                            "{} ({:?})",
+                            name.map(|name| name.as_str()).unwrap_or("action"),
+                            state
+                        )
+                    })
+                    .collect();
+                ui.label(format!(
+                    "action: {}",
+                    if active.is_empty() {
+                        String::from("idle")
+                    } else {
+                        active.join(", ")
+                    }
+                ));
+                ui.label("history");
+                ScrollArea::vertical().max_height(150.).show(ui, |ui| {
+                    for transition in decision_log.transitions.iter().rev() {
+                        ui.label(format!(
+                            "{:.1}s {} {:?}",
+                            transition.time, transition.action, transition.state
+                        ));
+                    }
+                });
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.add(DragValue::new(&mut inspector_state.teleport.x).prefix("x: "));
+                ui.add(DragValue::new(&mut inspector_state.teleport.y).prefix("y: "));
+                if ui.button("Teleport").clicked() {
+                    trans.translation = inspector_state.teleport.extend(trans.translation.z);
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Clone").clicked() {
+                    clone_writer.send(CloneOrcaEvent(entity));
+                }
+                if ui.button("Kill").clicked() {
+                    despawn_writer.send(DespawnOrcaEvent(entity));
+                }
+                if forced.is_some() {
+                    ui.label("hunt forced");
+                } else if ui.button("Force Hunt").clicked() {
+                    cmd.entity(entity).insert(ForceHunt);
+                }
+            });
+        });
+
+    // keep the body drawn true to the edits
+    let (gender, age, pod_id) = before;
+    if orca.gender != gender || orca.age != age {
+        for child in children.iter() {
+            if let Ok((mut body_trans, mut draw_mode, _)) = body_query.get_mut(*child) {
+                body_trans.scale = body_scale(orca.age);
+                if let DrawMode::Outlined {
+                    ref mut outline_mode,
+                    ..
+                } = *draw_mode
+                {
+                    outline_mode.color = outline_color(orca.gender);
+                }
+            }
+        }
+    }
+    if orca.pod_id != pod_id {
+        if let Some(pod) = pod_id.and_then(|pod_id| pod_pool.get_mut(&pod_id)) {
+            pod.members.retain(|member| *member != entity);
+        }
+        if let Some(new_pod_id) = orca.pod_id {
+            if let Some(pod) = pod_pool.get_mut(&new_pod_id) {
+                pod.members.push(entity);
+                for child in children.iter() {
+                    if let Ok((_, _, mut shade)) = body_query.get_mut(*child) {
+                        shade.0 = pod.color;
+                    }
+                }
+            }
+            pod_change_writer.send(PodChangeEvent {
+                orca: entity,
+                from: pod_id,
+                to: new_pod_id,
+            });
+        }
+    }
+}
+
+fn render_fish_inspector(
+    mut ctx: ResMut<EguiContext>,
+    selected: Option<Res<Selected>>,
+    mut fish_query: Query<
+        (
+            &mut Fish,
+            &mut Movement,
+            &mut Sight,
+            &FishNeighbouring,
+            &RigidBody,
+        ),
+        Without<Orca>,
+    >,
+    orca_query: Query<(&Orca, &Movement)>,
+) {
+    let entity = some_or_return!(selected).0;
+    let (mut fish, mut movement, mut sight, neighbouring, rb) =
+        ok_or_return!(fish_query.get_mut(entity));
+
+    let hunters: Vec<String> = orca_query
+        .iter()
+        .filter(|(_, movement)| movement.target == Some(entity))
+        .map(|(orca, _)| orca.name.clone())
+        .collect();
+
+    Window::new("Inspector")
+        .default_width(250.)
+        .show(ctx.ctx_mut(), |ui| {
+            ui.label("fish");
+            ui.add(Slider::new(&mut fish.size, 0.1f32..=2.).text("Size (m)"));
+            ui.label(format!(
+                "behaviour: {}",
+                Activity::of(&movement, rb, None).name()
+            ));
+            ui.label(format!("depth: {} m", movement.depth.round()));
+            ui.label(format!("speed: {:.1}", rb.velocity.length()));
+            if !hunters.is_empty() {
+                ui.label(format!("hunted by: {}", hunters.join(", ")));
+            }
+
+            ui.collapsing("Perception", |ui| {
+                sight_sliders(ui, &mut sight);
+                ui.label(format!("sees {} fish", neighbouring.around.len()));
+            });
+            ui.collapsing("Movement", |ui| {
+                ui.add(Slider::new(&mut movement.coherence, 0.0f32..=10.).text("Coherence"));
+                ui.add(Slider::new(&mut movement.alignment, 0.0f32..=10.).text("Alignment"));
+                ui.add(Slider::new(&mut movement.seperation, 0.0f32..=10.).text("Seperation"));
+                ui.add(Slider::new(&mut movement.randomess, 0.0f32..=10.).text("Randomness"));
+                ui.add(Slider::new(&mut movement.avoidance, 0.0f32..=20.).text("Avoidance"));
+                ui.add(Slider::new(&mut movement.speed_scale, 0.0f32..=20.).text("Speed Scale"));
+            });
+        });
+}
+
+fn render_vessel_inspector(
+    mut ctx: ResMut<EguiContext>,
+    selected: Option<Res<Selected>>,
+    mut vessel_query: Query<(&mut Vessel, &Transform)>,
+    orca_query: Query<(&Orca, &Transform)>,
+) {
+    let entity = some_or_return!(selected).0;
+    let (mut vessel, trans) = ok_or_return!(vessel_query.get_mut(entity));
+    let pos = trans.translation.truncate();
+
+    Window::new("Inspector")
+        .default_width(250.)
+        .show(ctx.ctx_mut(), |ui| {
+            ui.label(format!("vessel: {}", vessel.kind.to_string()));
+            ui.add(Slider::new(&mut vessel.speed, 0.0f32..=30.).text("Speed"));
+            ui.label(format!("noise: {}", vessel.noise));
+
+            // mirrors how each kind of vessel steers
+            match vessel.kind {
+                VesselKind::Cargo => {
+                    if let Some(waypoint) = vessel.route.get(vessel.waypoint) {
+                        ui.label(format!(
+                            "behaviour: heading to waypoint {} at ({:.0}, {:.0})",
+                            vessel.waypoint, waypoint.x, waypoint.y
+                        ));
+                    }
+                },
+                VesselKind::WhaleWatching => {
+                    let closest = orca_query.iter().min_by(|(_, a), (_, b)| {
+                        a.translation
+                            .truncate()
+                            .distance_squared(pos)
+                            .total_cmp(&b.translation.truncate().distance_squared(pos))
+                    });
+                    match closest {
+                        Some((orca, orca_trans)) => ui.label(format!(
+                            "behaviour: following {} at {:.0}",
+                            orca.name,
+                            orca_trans.translation.truncate().distance(pos)
+                        )),
+                        None => ui.label("behaviour: returning to port"),
+                    };
+                },
+            }
+        });
+}
+
+fn sight_sliders(ui: &mut Ui, sight: &mut Sight) {
+    ui.add(Slider::new(&mut sight.view_range, 0.0f32..=500.).text("View Range"));
+    ui.add(Slider::new(&mut sight.view_angle, 0.0f32..=180.).text("View Angle"));
+    ui.add(Slider::new(&mut sight.peripheral_falloff, 0.0f32..=1.).text("Peripheral Falloff"));
+}
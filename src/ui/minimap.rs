@@ -0,0 +1,145 @@
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, Color32, Sense, Shape, Stroke, Window},
+    EguiContext,
+};
+
+use crate::{
+    camera::{CameraFollow, CameraFollowPod, MainCamera, ZoomToFitEvent},
+    fish::Fish,
+    map::{ObstacleMap, WorldBounds},
+    orca::{Orca, PodPool},
+};
+
+const MINIMAP_SIZE: f32 = 200.;
+
+pub struct MinimapState {
+    pub show: bool,
+}
+
+impl Default for MinimapState {
+    fn default() -> Self {
+        Self { show: true }
+    }
+}
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MinimapState::default())
+            .add_system(render_minimap)
+            .add_system(minimap_controller);
+    }
+}
+
+fn color32(color: Color) -> Color32 {
+    let [r, g, b, a] = color.as_rgba_f32();
+    Color32::from_rgba_unmultiplied(
+        (r * 255.) as u8,
+        (g * 255.) as u8,
+        (b * 255.) as u8,
+        (a * 255.) as u8,
+    )
+}
+
+fn render_minimap(
+    mut cmd: Commands,
+    mut ctx: ResMut<EguiContext>,
+    minimap_state: Res<MinimapState>,
+    windows: Res<Windows>,
+    obstacle_map: Res<ObstacleMap>,
+    world_bounds: Res<WorldBounds>,
+    pod_pool: Res<PodPool>,
+    orca_query: Query<(&Orca, &Transform), Without<MainCamera>>,
+    fish_query: Query<&Transform, (With<Fish>, Without<MainCamera>)>,
+    mut camera_query: Query<(&mut Transform, &OrthographicProjection), With<MainCamera>>,
+    mut fit_writer: EventWriter<ZoomToFitEvent>,
+) {
+    if !minimap_state.show {
+        return;
+    }
+
+    Window::new("Minimap")
+        .resizable(false)
+        .show(ctx.ctx_mut(), |ui| {
+            let (response, painter) =
+                ui.allocate_painter(egui::vec2(MINIMAP_SIZE, MINIMAP_SIZE), Sense::click());
+            let rect = response.rect;
+            // square so the world is not stretched to fit
+            let bounds = world_bounds.square();
+            let to_map = |pos: Vec2| {
+                let t = (pos - bounds.min) / bounds.size();
+                egui::pos2(
+                    rect.left() + t.x * rect.width(),
+                    rect.bottom() - t.y * rect.height(),
+                )
+            };
+            let to_world = |pos: egui::Pos2| {
+                let t = Vec2::new(
+                    (pos.x - rect.left()) / rect.width(),
+                    (rect.bottom() - pos.y) / rect.height(),
+                );
+                bounds.min + t * bounds.size()
+            };
+
+            painter.rect_filled(rect, 0., Color32::from_rgb(20, 40, 80));
+            for obstacle in obstacle_map.obstacles.iter() {
+                let points = obstacle.points.iter().map(|p| to_map(*p)).collect();
+                painter.add(Shape::closed_line(
+                    points,
+                    Stroke::new(1., Color32::from_rgb(117, 158, 84)),
+                ));
+            }
+            for trans in fish_query.iter() {
+                painter.circle_filled(
+                    to_map(trans.translation.truncate()),
+                    0.5,
+                    Color32::LIGHT_GRAY,
+                );
+            }
+            for (orca, trans) in orca_query.iter() {
+                let color = orca
+                    .pod_id
+                    .and_then(|pod_id| pod_pool.get(&pod_id))
+                    .map(|pod| color32(pod.color))
+                    .unwrap_or(Color32::WHITE);
+                painter.circle_filled(to_map(trans.translation.truncate()), 2., color);
+            }
+
+            // what the main camera is looking at
+            if let (Ok((camera_trans, projection)), Some(window)) =
+                (camera_query.get_single(), windows.get_primary())
+            {
+                let center = camera_trans.translation.truncate();
+                let half = Vec2::new(window.width(), window.height()) / 2. * projection.scale;
+                painter.rect_stroke(
+                    egui::Rect::from_two_pos(to_map(center - half), to_map(center + half)),
+                    0.,
+                    Stroke::new(1., Color32::WHITE),
+                );
+            }
+
+            // jump to wherever is clicked
+            if response.clicked() {
+                if let (Some(pos), Ok((mut camera_trans, _))) = (
+                    response.interact_pointer_pos(),
+                    camera_query.get_single_mut(),
+                ) {
+                    camera_trans.translation = to_world(pos).extend(camera_trans.translation.z);
+                    cmd.remove_resource::<CameraFollow>();
+                    cmd.remove_resource::<CameraFollowPod>();
+                }
+            }
+
+            if ui.button("Zoom to Fit").clicked() {
+                fit_writer.send(ZoomToFitEvent);
+            }
+        });
+}
+
+fn minimap_controller(keys: Res<Input<KeyCode>>, mut minimap_state: ResMut<MinimapState>) {
+    if keys.just_pressed(KeyCode::M) {
+        minimap_state.show = !minimap_state.show;
+    }
+}
@@ -1,24 +1,44 @@
+pub mod charts;
+pub mod heatmap;
+pub mod inspector;
+pub mod minimap;
+pub mod overlays;
+pub mod pods;
+pub mod replay;
+
 use bevy::{prelude::*, render::render_phase::Draw};
 use bevy_egui::{
-    egui::{containers::panel::Side, ScrollArea, SidePanel, Slider, Window},
+    egui::{containers::panel::Side, ComboBox, ScrollArea, SidePanel, Slider, Window},
     EguiContext, EguiPlugin,
 };
 use bevy_mod_picking::events::PickingEvent;
 use bevy_prototype_lyon::prelude::*;
 use pino_utils::{ok_or_return, some_or_return};
 
+use self::{
+    charts::ChartsPlugin, heatmap::HeatmapPlugin, inspector::InspectorPlugin,
+    minimap::MinimapPlugin, overlays::OverlaysPlugin, pods::PodsPlugin, replay::ReplayUIPlugin,
+};
 use crate::{
     ai::{
-        hunger::Hunger,
-        movement::{BoidParams, OrcaNeighbouring},
+        decision::LoadThinkersEvent,
+        movement::{BoidParams, OrcaNeighbouring, ParamTarget, SetBoidParamsEvent},
     },
-    camera::CameraFollow,
-    orca::{Orca, PodPool},
+    calendar::{Calendar, CalendarParams, Season},
+    camera::{CameraFollow, CameraFollowPod},
+    current::{CurrentField, CurrentSource, LoadCurrentsEvent},
+    map::{LoadBathymetryEvent, LoadMapEvent},
+    orca::{Orca, PodId, PodPool},
     sim::{RunSimEvent, Simulation},
 };
 
+/// spacing in world units between the arrows of the current overlay
+const CURRENT_OVERLAY_SPACING: usize = 50;
+const CURRENT_OVERLAY_EXTENT: i32 = 500;
+const CURRENT_ARROW_SCALE: f32 = 2.;
+
 #[derive(Component)]
-pub struct NeighbourLine;
+pub struct DebugLine;
 
 pub struct UIPlugin;
 
@@ -27,22 +47,44 @@ impl Plugin for UIPlugin {
         app.insert_resource(UIState::default())
             .insert_resource(SimFormState::default())
             .add_plugin(EguiPlugin)
+            .add_plugin(ChartsPlugin)
+            .add_plugin(HeatmapPlugin)
+            .add_plugin(InspectorPlugin)
+            .add_plugin(MinimapPlugin)
+            .add_plugin(OverlaysPlugin)
+            .add_plugin(PodsPlugin)
+            .add_plugin(ReplayUIPlugin)
             .add_system(render_ui)
             .add_system(ui_controller)
             .add_system(select_controller)
             .add_system(deselect_controller)
             .add_system(neighbour_debug)
+            .add_system(current_debug)
             .add_system(line_cleaner);
     }
 }
 
-pub struct SelectedOrca(Entity);
+/// entity picked by clicking it or choosing it in a list, of any species
+pub struct Selected(Entity);
 
 pub struct UIState {
     show_panel: bool,
+    show_currents: bool,
+}
+
+/// orcas that live edits to the orca parameters apply to
+#[derive(Clone, Copy, PartialEq)]
+pub enum LiveTarget {
+    All,
+    Pod(PodId),
+    Selected,
 }
 
 pub struct SimFormState {
+    /// apply edits to boid parameters to the running simulation straight away
+    live_edit: bool,
+    live_target: LiveTarget,
+
     enable_orca: bool,
     pod_count: usize,
     pod_size_min: usize,
@@ -50,14 +92,32 @@ pub struct SimFormState {
 
     enable_fish: bool,
     fish_count: usize,
+    fish_depth_min: f32,
+    fish_depth_max: f32,
 
     orca_params: BoidParams,
     fish_params: BoidParams,
+
+    calendar: CalendarParams,
+
+    enable_vessels: bool,
+    cargo_count: usize,
+    whale_watch_count: usize,
+    cargo_noise: f32,
+    whale_watch_noise: f32,
+
+    map_path: String,
+    bathymetry_path: String,
+    currents_path: String,
+    thinkers_path: String,
 }
 
 impl Default for SimFormState {
     fn default() -> Self {
         Self {
+            live_edit: false,
+            live_target: LiveTarget::All,
+
             enable_orca: true,
             pod_count: 4,
             pod_size_min: 15,
@@ -65,6 +125,8 @@ impl Default for SimFormState {
 
             enable_fish: true,
             fish_count: 100,
+            fish_depth_min: 0.,
+            fish_depth_max: 50.,
 
             orca_params: BoidParams {
                 coherence: 0.5,
@@ -77,24 +139,46 @@ impl Default for SimFormState {
                 view_angle: 60.,
                 ..default()
             },
+
+            calendar: CalendarParams::default(),
+
+            enable_vessels: false,
+            cargo_count: 2,
+            whale_watch_count: 3,
+            cargo_noise: 2.,
+            whale_watch_noise: 1.,
+
+            map_path: String::new(),
+            bathymetry_path: String::new(),
+            currents_path: String::new(),
+            thinkers_path: String::new(),
         }
     }
 }
 
 impl Default for UIState {
     fn default() -> Self {
-        Self { show_panel: true }
+        Self {
+            show_panel: true,
+            show_currents: false,
+        }
     }
 }
 
 fn render_ui(
     mut ctx: ResMut<EguiContext>,
-    ui_state: Res<UIState>,
+    mut ui_state: ResMut<UIState>,
     mut sim_form_state: ResMut<SimFormState>,
-    selected: Option<Res<SelectedOrca>>,
-    query: Query<(&Orca, &Hunger)>,
+    mut current_field: ResMut<CurrentField>,
+    selected: Option<Res<Selected>>,
     mut run_sim_writer: EventWriter<RunSimEvent>,
+    mut set_params_writer: EventWriter<SetBoidParamsEvent>,
+    mut load_map_writer: EventWriter<LoadMapEvent>,
+    mut load_bathymetry_writer: EventWriter<LoadBathymetryEvent>,
+    mut load_currents_writer: EventWriter<LoadCurrentsEvent>,
+    mut load_thinkers_writer: EventWriter<LoadThinkersEvent>,
     sim: Res<Simulation>,
+    calendar: Res<Calendar>,
     pod_pool: Res<PodPool>,
 ) {
     if ui_state.show_panel {
@@ -107,6 +191,19 @@ fn render_ui(
                     ui.separator();
                     ui.label(format!("simulated orcas: {}", sim.orca_count));
                     ui.label(format!("time: {}s", (sim.time * 100.).round() / 100.));
+                    ui.label(format!(
+                        "day {} ({}) {}",
+                        calendar.day + 1,
+                        calendar.season.to_string(),
+                        calendar.clock()
+                    ));
+                    ui.label(format!(
+                        "mean noise at orcas: {}",
+                        (sim.mean_noise * 100.).round() / 100.
+                    ));
+
+                    ui.separator();
+                    ui.checkbox(&mut sim_form_state.live_edit, "Live Edit Boid Params");
 
                     ui.separator();
                     ui.label("Orca Params");
@@ -122,6 +219,32 @@ fn render_ui(
                         sim_form_state.pod_size_max = sim_form_state.pod_size_min;
                     }
                     ui.add_space(10.);
+                    if sim_form_state.live_edit {
+                        let target_name = |target: LiveTarget| match target {
+                            LiveTarget::All => String::from("All Orcas"),
+                            LiveTarget::Pod(pod_id) => pod_pool
+                                .get(&pod_id)
+                                .map(|pod| pod.name.clone())
+                                .unwrap_or_else(|| format!("pod {}", pod_id)),
+                            LiveTarget::Selected => String::from("Selected"),
+                        };
+                        let mut pod_ids: Vec<PodId> = pod_pool.keys().copied().collect();
+                        pod_ids.sort_unstable();
+                        ComboBox::from_label("Apply To")
+                            .selected_text(target_name(sim_form_state.live_target))
+                            .show_ui(ui, |ui| {
+                                let mut targets = vec![LiveTarget::All, LiveTarget::Selected];
+                                targets.extend(pod_ids.into_iter().map(LiveTarget::Pod));
+                                for target in targets {
+                                    ui.selectable_value(
+                                        &mut sim_form_state.live_target,
+                                        target,
+                                        target_name(target),
+                                    );
+                                }
+                            });
+                    }
+                    let orca_params = sim_form_state.orca_params;
                     ui.add(
                         Slider::new(&mut sim_form_state.orca_params.coherence, 0.0f32..=10.)
                             .text("Coherence"),
@@ -146,12 +269,46 @@ fn render_ui(
                         Slider::new(&mut sim_form_state.orca_params.view_angle, 0.0f32..=180.0)
                             .text("View Angle"),
                     );
+                    ui.add(
+                        Slider::new(
+                            &mut sim_form_state.orca_params.peripheral_falloff,
+                            0.0f32..=1.0,
+                        )
+                        .text("Peripheral Falloff"),
+                    );
+                    if sim_form_state.live_edit && sim_form_state.orca_params != orca_params {
+                        let target = match sim_form_state.live_target {
+                            LiveTarget::All => Some(ParamTarget::Orcas),
+                            LiveTarget::Pod(pod_id) => Some(ParamTarget::Pod(pod_id)),
+                            LiveTarget::Selected => selected
+                                .as_ref()
+                                .map(|selected| ParamTarget::Orca(selected.0)),
+                        };
+                        if let Some(target) = target {
+                            set_params_writer.send(SetBoidParamsEvent {
+                                params: sim_form_state.orca_params,
+                                target,
+                            });
+                        }
+                    }
 
                     ui.separator();
                     ui.label("Fish Params");
                     ui.checkbox(&mut sim_form_state.enable_fish, "Enable Fish");
                     ui.add(Slider::new(&mut sim_form_state.fish_count, 0..=500).text("Fish Count"));
+                    ui.add(
+                        Slider::new(&mut sim_form_state.fish_depth_min, 0.0f32..=300.)
+                            .text("Depth Min"),
+                    );
+                    ui.add(
+                        Slider::new(&mut sim_form_state.fish_depth_max, 0.0f32..=300.)
+                            .text("Depth Max"),
+                    );
+                    if sim_form_state.fish_depth_min > sim_form_state.fish_depth_max {
+                        sim_form_state.fish_depth_max = sim_form_state.fish_depth_min;
+                    }
                     ui.add_space(10.);
+                    let fish_params = sim_form_state.fish_params;
                     ui.add(
                         Slider::new(&mut sim_form_state.fish_params.coherence, 0.0f32..=10.)
                             .text("Coherence"),
@@ -176,6 +333,159 @@ fn render_ui(
                         Slider::new(&mut sim_form_state.fish_params.view_angle, 0.0f32..=180.0)
                             .text("View Angle"),
                     );
+                    ui.add(
+                        Slider::new(
+                            &mut sim_form_state.fish_params.peripheral_falloff,
+                            0.0f32..=1.0,
+                        )
+                        .text("Peripheral Falloff"),
+                    );
+                    if sim_form_state.live_edit && sim_form_state.fish_params != fish_params {
+                        set_params_writer.send(SetBoidParamsEvent {
+                            params: sim_form_state.fish_params,
+                            target: ParamTarget::Fish,
+                        });
+                    }
+
+                    ui.separator();
+                    ui.label("Calendar");
+                    ui.add(
+                        Slider::new(&mut sim_form_state.calendar.day_length, 10.0f32..=600.)
+                            .text("Day Length"),
+                    );
+                    ui.add(
+                        Slider::new(&mut sim_form_state.calendar.days_per_season, 1..=30)
+                            .text("Days per Season"),
+                    );
+                    ComboBox::from_label("Start Season")
+                        .selected_text(sim_form_state.calendar.start_season.to_string())
+                        .show_ui(ui, |ui| {
+                            for season in Season::ALL {
+                                ui.selectable_value(
+                                    &mut sim_form_state.calendar.start_season,
+                                    season,
+                                    season.to_string(),
+                                );
+                            }
+                        });
+                    for season in Season::ALL {
+                        let params = &mut sim_form_state.calendar.seasons[season.index()];
+                        ui.collapsing(season.to_string(), |ui| {
+                            ui.add(
+                                Slider::new(&mut params.salmon_abundance, 0.0f32..=3.)
+                                    .text("Salmon Abundance"),
+                            );
+                            ui.add(
+                                Slider::new(&mut params.night_activity, 0.0f32..=1.)
+                                    .text("Night Activity"),
+                            );
+                            ui.add(
+                                Slider::new(&mut params.birth_rate, 0.0f32..=0.2)
+                                    .text("Birth Rate"),
+                            );
+                        });
+                    }
+
+                    ui.separator();
+                    ui.label("Vessels");
+                    ui.checkbox(&mut sim_form_state.enable_vessels, "Enable Vessels");
+                    ui.add(
+                        Slider::new(&mut sim_form_state.cargo_count, 0..=20).text("Cargo Ships"),
+                    );
+                    ui.add(
+                        Slider::new(&mut sim_form_state.whale_watch_count, 0..=20)
+                            .text("Whale Watchers"),
+                    );
+                    ui.add(
+                        Slider::new(&mut sim_form_state.cargo_noise, 0.0f32..=10.)
+                            .text("Cargo Noise"),
+                    );
+                    ui.add(
+                        Slider::new(&mut sim_form_state.whale_watch_noise, 0.0f32..=10.)
+                            .text("Whale Watcher Noise"),
+                    );
+                    ui.separator();
+                    ui.label("Map");
+                    ui.text_edit_singleline(&mut sim_form_state.map_path);
+                    if ui.button("Load Map").clicked() {
+                        load_map_writer.send(LoadMapEvent(sim_form_state.map_path.clone().into()));
+                    }
+                    ui.label("Bathymetry");
+                    ui.text_edit_singleline(&mut sim_form_state.bathymetry_path);
+                    if ui.button("Load Bathymetry").clicked() {
+                        load_bathymetry_writer.send(LoadBathymetryEvent(
+                            sim_form_state.bathymetry_path.clone().into(),
+                        ));
+                    }
+                    // applies to orcas spawned after loading
+                    ui.label("Thinkers");
+                    ui.text_edit_singleline(&mut sim_form_state.thinkers_path);
+                    if ui.button("Load Thinkers").clicked() {
+                        load_thinkers_writer.send(LoadThinkersEvent(
+                            sim_form_state.thinkers_path.clone().into(),
+                        ));
+                    }
+
+                    ui.separator();
+                    ui.label("Currents");
+                    ui.horizontal(|ui| {
+                        if ui
+                            .radio(matches!(current_field.source, CurrentSource::None), "None")
+                            .clicked()
+                        {
+                            current_field.source = CurrentSource::None;
+                        }
+                        if ui
+                            .radio(
+                                matches!(current_field.source, CurrentSource::Constant(_)),
+                                "Constant",
+                            )
+                            .clicked()
+                        {
+                            current_field.source = CurrentSource::Constant(Vec2::new(5., 0.));
+                        }
+                        if ui
+                            .radio(
+                                matches!(current_field.source, CurrentSource::Procedural { .. }),
+                                "Eddies",
+                            )
+                            .clicked()
+                        {
+                            current_field.source = CurrentSource::Procedural {
+                                strength: 5.,
+                                scale: 100.,
+                            };
+                        }
+                    });
+                    match &mut current_field.source {
+                        CurrentSource::Constant(current) => {
+                            ui.add(Slider::new(&mut current.x, -20.0f32..=20.).text("East"));
+                            ui.add(Slider::new(&mut current.y, -20.0f32..=20.).text("North"));
+                        },
+                        CurrentSource::Procedural { strength, scale } => {
+                            ui.add(Slider::new(strength, 0.0f32..=20.).text("Strength"));
+                            ui.add(Slider::new(scale, 10.0f32..=500.).text("Eddy Size"));
+                        },
+                        CurrentSource::Grid(grid) => {
+                            ui.label(format!("grid: {}x{}", grid.width, grid.height));
+                        },
+                        CurrentSource::None => {},
+                    }
+                    ui.text_edit_singleline(&mut sim_form_state.currents_path);
+                    if ui.button("Load Currents").clicked() {
+                        load_currents_writer.send(LoadCurrentsEvent(
+                            sim_form_state.currents_path.clone().into(),
+                        ));
+                    }
+                    ui.add(
+                        Slider::new(&mut current_field.tide_period, 0.0f32..=600.)
+                            .text("Tide Period"),
+                    );
+                    ui.add(
+                        Slider::new(&mut current_field.tide_strength, 0.0f32..=1.)
+                            .text("Tide Strength"),
+                    );
+                    ui.checkbox(&mut ui_state.show_currents, "Show Currents");
 
                     ui.separator();
                     if ui.button("Restart Simulation").clicked() {
@@ -188,28 +498,20 @@ fn render_ui(
 
                             enable_fish: sim_form_state.enable_fish,
                             fish_count: sim_form_state.fish_count,
+                            fish_depth_min: sim_form_state.fish_depth_min,
+                            fish_depth_max: sim_form_state.fish_depth_max,
 
                             orca_params: sim_form_state.orca_params,
                             fish_params: sim_form_state.fish_params,
-                        });
-                    }
 
-                    if let Some(selected) = selected {
-                        if let Ok((orca, hunger)) = query.get(selected.0) {
-                            ui.heading("Inspector");
-                            ui.separator();
-                            if let Some(pod_id) = orca.pod_id {
-                                if let Some(pod) = pod_pool.get(&pod_id) {
-                                    ui.label(format!("pod: {}", pod.name));
-                                }
-                            }
-                            ui.label(format!("name: {}", orca.name));
-                            ui.label(format!("gender: {}", orca.gender.to_string()));
-                            ui.label(format!("age: {} years", orca.age));
-                            ui.label(format!("mass: {} kg", orca.mass));
-                            ui.label(format!("type: {}", orca.orca_type.to_string()));
-                            ui.label(format!("hunger: {}", (hunger.0 * 100.).round() / 100.));
-                        }
+                            calendar: sim_form_state.calendar,
+
+                            enable_vessels: sim_form_state.enable_vessels,
+                            cargo_count: sim_form_state.cargo_count,
+                            whale_watch_count: sim_form_state.whale_watch_count,
+                            cargo_noise: sim_form_state.cargo_noise,
+                            whale_watch_noise: sim_form_state.whale_watch_noise,
+                        });
                     }
                 });
             });
@@ -226,7 +528,7 @@ fn select_controller(mut cmd: Commands, mut events: EventReader<PickingEvent>) {
     for evt in events.iter() {
         match evt {
             PickingEvent::Clicked(entity) => {
-                cmd.insert_resource(SelectedOrca(*entity));
+                cmd.insert_resource(Selected(*entity));
                 cmd.insert_resource(CameraFollow(*entity));
             },
             _ => {},
@@ -236,14 +538,15 @@ fn select_controller(mut cmd: Commands, mut events: EventReader<PickingEvent>) {
 
 fn deselect_controller(mut cmd: Commands, keys: Res<Input<KeyCode>>) {
     if keys.just_pressed(KeyCode::Escape) {
-        cmd.remove_resource::<SelectedOrca>();
+        cmd.remove_resource::<Selected>();
         cmd.remove_resource::<CameraFollow>();
+        cmd.remove_resource::<CameraFollowPod>();
     }
 }
 
 fn neighbour_debug(
     mut cmd: Commands,
-    selected: Option<Res<SelectedOrca>>,
+    selected: Option<Res<Selected>>,
     query: Query<(&OrcaNeighbouring, &Transform)>,
 ) {
     let selected = some_or_return!(selected);
@@ -258,10 +561,44 @@ fn neighbour_debug(
             DrawMode::Stroke(StrokeMode::new(Color::BLACK, 0.2)),
             Transform::default(),
         ))
-        .insert(NeighbourLine);
+        .insert(DebugLine);
     }
 }
-fn line_cleaner(mut cmd: Commands, query: Query<Entity, With<NeighbourLine>>) {
+fn current_debug(
+    mut cmd: Commands,
+    ui_state: Res<UIState>,
+    sim: Res<Simulation>,
+    current_field: Res<CurrentField>,
+) {
+    if !ui_state.show_currents {
+        return;
+    }
+
+    for x in (-CURRENT_OVERLAY_EXTENT..=CURRENT_OVERLAY_EXTENT).step_by(CURRENT_OVERLAY_SPACING) {
+        for y in (-CURRENT_OVERLAY_EXTENT..=CURRENT_OVERLAY_EXTENT).step_by(CURRENT_OVERLAY_SPACING)
+        {
+            let start = Vec2::new(x as f32, y as f32);
+            let end = start + current_field.at(start, sim.time) * CURRENT_ARROW_SCALE;
+            let head = (start - end).normalize_or_zero() * 3.;
+
+            let mut path = PathBuilder::new();
+            path.move_to(start);
+            path.line_to(end);
+            path.move_to(end + Mat2::from_angle(0.5) * head);
+            path.line_to(end);
+            path.line_to(end + Mat2::from_angle(-0.5) * head);
+
+            cmd.spawn_bundle(GeometryBuilder::build_as(
+                &path.build(),
+                DrawMode::Stroke(StrokeMode::new(Color::WHITE, 0.5)),
+                Transform::from_xyz(0., 0., 5.),
+            ))
+            .insert(DebugLine);
+        }
+    }
+}
+
+fn line_cleaner(mut cmd: Commands, query: Query<Entity, With<DebugLine>>) {
     for e in &query {
         cmd.entity(e).despawn_recursive();
     }
@@ -0,0 +1,327 @@
+use std::collections::{HashMap, VecDeque};
+
+use bevy::prelude::*;
+use bevy_bobs::physics_2d::RigidBody;
+use bevy_egui::{
+    egui::{Color32, Slider, Ui, Window},
+    EguiContext,
+};
+use bevy_prototype_lyon::prelude::*;
+
+use super::{DebugLine, Selected};
+use crate::{
+    ai::{
+        movement::{BoidForces, Movement, Sight},
+        perception::heading,
+    },
+    sim::Simulation,
+};
+
+/// segments a sight cone's arc is drawn with
+const CONE_SEGMENTS: usize = 16;
+/// seconds between trail points
+const TRAIL_INTERVAL: f32 = 0.5;
+const OVERLAY_Z: f32 = 4.;
+
+/// which boids an overlay is drawn for
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum OverlayScope {
+    Off,
+    Selected,
+    All,
+}
+
+impl OverlayScope {
+    fn includes(&self, entity: Entity, selected: Option<Entity>) -> bool {
+        match self {
+            OverlayScope::Off => false,
+            OverlayScope::Selected => selected == Some(entity),
+            OverlayScope::All => true,
+        }
+    }
+}
+
+pub struct OverlayState {
+    pub show: bool,
+    pub sight: OverlayScope,
+    pub forces: OverlayScope,
+    pub velocity: OverlayScope,
+    pub trails: OverlayScope,
+    /// length drawn per unit of force
+    pub force_scale: f32,
+    /// number of points kept per trail
+    pub trail_length: usize,
+}
+
+impl Default for OverlayState {
+    fn default() -> Self {
+        Self {
+            show: false,
+            sight: OverlayScope::Off,
+            forces: OverlayScope::Off,
+            velocity: OverlayScope::Off,
+            trails: OverlayScope::Off,
+            force_scale: 1.,
+            trail_length: 40,
+        }
+    }
+}
+
+/// recent positions of boids with a trail, oldest first
+#[derive(Default)]
+pub struct Trails {
+    positions: HashMap<Entity, VecDeque<Vec2>>,
+    next_sample: f32,
+}
+
+pub struct OverlaysPlugin;
+
+impl Plugin for OverlaysPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(OverlayState::default())
+            .insert_resource(Trails::default())
+            .add_system(render_overlays)
+            .add_system(overlays_controller)
+            .add_system(sight_overlay)
+            .add_system(force_overlay)
+            .add_system(velocity_overlay)
+            .add_system(record_trails)
+            .add_system(trail_overlay);
+    }
+}
+
+/// colours of each steering force, also used for the legend
+fn force_colors(forces: &BoidForces) -> [(&'static str, Vec2, Color); 6] {
+    [
+        ("alignment", forces.alignment, Color::BLUE),
+        ("cohesion", forces.cohesion, Color::GREEN),
+        ("separation", forces.separation, Color::RED),
+        ("wander", forces.wander, Color::WHITE),
+        ("avoidance", forces.avoidance, Color::ORANGE),
+        ("target", forces.target, Color::PURPLE),
+    ]
+}
+
+fn render_overlays(mut ctx: ResMut<EguiContext>, mut overlay_state: ResMut<OverlayState>) {
+    if !overlay_state.show {
+        return;
+    }
+
+    Window::new("Overlays").show(ctx.ctx_mut(), |ui| {
+        let scope_row = |ui: &mut Ui, name: &str, scope: &mut OverlayScope| {
+            ui.horizontal(|ui| {
+                ui.label(name);
+                ui.radio_value(scope, OverlayScope::Off, "Off");
+                ui.radio_value(scope, OverlayScope::Selected, "Selected");
+                ui.radio_value(scope, OverlayScope::All, "All");
+            });
+        };
+        let state = &mut *overlay_state;
+        scope_row(ui, "Sight Cones", &mut state.sight);
+        scope_row(ui, "Forces", &mut state.forces);
+        scope_row(ui, "Velocity", &mut state.velocity);
+        scope_row(ui, "Trails", &mut state.trails);
+
+        ui.add(
+            Slider::new(&mut state.force_scale, 0.01f32..=10.)
+                .logarithmic(true)
+                .text("Force Scale"),
+        );
+        ui.add(Slider::new(&mut state.trail_length, 2..=200).text("Trail Length"));
+
+        ui.horizontal_wrapped(|ui| {
+            for (name, _, color) in force_colors(&BoidForces::default()) {
+                let [r, g, b, _] = color.as_rgba_f32();
+                ui.colored_label(
+                    Color32::from_rgb((r * 255.) as u8, (g * 255.) as u8, (b * 255.) as u8),
+                    name,
+                );
+            }
+        });
+    });
+}
+
+fn arrow(cmd: &mut Commands, start: Vec2, end: Vec2, color: Color) {
+    let head = (start - end).normalize_or_zero() * 2.;
+
+    let mut path = PathBuilder::new();
+    path.move_to(start);
+    path.line_to(end);
+    path.move_to(end + Mat2::from_angle(0.5) * head);
+    path.line_to(end);
+    path.line_to(end + Mat2::from_angle(-0.5) * head);
+
+    cmd.spawn_bundle(GeometryBuilder::build_as(
+        &path.build(),
+        DrawMode::Stroke(StrokeMode::new(color, 0.3)),
+        Transform::from_xyz(0., 0., OVERLAY_Z),
+    ))
+    .insert(DebugLine);
+}
+
+/// view cone shaped by the peripheral falloff
+fn sight_overlay(
+    mut cmd: Commands,
+    overlay_state: Res<OverlayState>,
+    selected: Option<Res<Selected>>,
+    query: Query<(Entity, &Transform, &Sight, &RigidBody)>,
+) {
+    if overlay_state.sight == OverlayScope::Off {
+        return;
+    }
+    let selected = selected.map(|selected| selected.0);
+
+    for (entity, trans, sight, rb) in query.iter() {
+        if !overlay_state.sight.includes(entity, selected) {
+            continue;
+        }
+        let pos = trans.translation.truncate();
+        let front = heading(rb.velocity, trans.rotation);
+        let half_angle = sight.view_angle.to_radians();
+
+        let mut path = PathBuilder::new();
+        path.move_to(pos);
+        for i in 0..=CONE_SEGMENTS {
+            // the falloff of `view_factor`, worked out here as that is zero right on the edge
+            let edge = 2. * i as f32 / CONE_SEGMENTS as f32 - 1.;
+            let falloff = 1. - sight.peripheral_falloff.clamp(0., 1.) * edge * edge;
+            let dir = Mat2::from_angle(edge * half_angle) * front;
+            path.line_to(pos + dir * sight.view_range * falloff);
+        }
+        path.close();
+
+        cmd.spawn_bundle(GeometryBuilder::build_as(
+            &path.build(),
+            DrawMode::Outlined {
+                fill_mode: FillMode::color(Color::rgba(1., 1., 0., 0.05)),
+                outline_mode: StrokeMode::new(Color::rgba(1., 1., 0., 0.3), 0.3),
+            },
+            Transform::from_xyz(0., 0., OVERLAY_Z),
+        ))
+        .insert(DebugLine);
+    }
+}
+
+fn force_overlay(
+    mut cmd: Commands,
+    overlay_state: Res<OverlayState>,
+    selected: Option<Res<Selected>>,
+    query: Query<(Entity, &Transform, &BoidForces)>,
+) {
+    if overlay_state.forces == OverlayScope::Off {
+        return;
+    }
+    let selected = selected.map(|selected| selected.0);
+
+    for (entity, trans, forces) in query.iter() {
+        if !overlay_state.forces.includes(entity, selected) {
+            continue;
+        }
+        let pos = trans.translation.truncate();
+        for (_, force, color) in force_colors(forces) {
+            if force != Vec2::ZERO {
+                arrow(
+                    &mut cmd,
+                    pos,
+                    pos + force * overlay_state.force_scale,
+                    color,
+                );
+            }
+        }
+    }
+}
+
+fn velocity_overlay(
+    mut cmd: Commands,
+    overlay_state: Res<OverlayState>,
+    selected: Option<Res<Selected>>,
+    query: Query<(Entity, &Transform, &RigidBody), With<Movement>>,
+) {
+    if overlay_state.velocity == OverlayScope::Off {
+        return;
+    }
+    let selected = selected.map(|selected| selected.0);
+
+    for (entity, trans, rb) in query.iter() {
+        if !overlay_state.velocity.includes(entity, selected) {
+            continue;
+        }
+        let pos = trans.translation.truncate();
+        arrow(&mut cmd, pos, pos + rb.velocity, Color::CYAN);
+    }
+}
+
+fn record_trails(
+    overlay_state: Res<OverlayState>,
+    sim: Res<Simulation>,
+    selected: Option<Res<Selected>>,
+    mut trails: ResMut<Trails>,
+    query: Query<(Entity, &Transform), With<Movement>>,
+) {
+    if overlay_state.trails == OverlayScope::Off {
+        trails.positions.clear();
+        return;
+    }
+    // a new run restarts the clock
+    if sim.time + TRAIL_INTERVAL < trails.next_sample {
+        trails.positions.clear();
+        trails.next_sample = 0.;
+    }
+    if sim.time < trails.next_sample {
+        return;
+    }
+    trails.next_sample = sim.time + TRAIL_INTERVAL;
+
+    let selected = selected.map(|selected| selected.0);
+    let mut positions = HashMap::new();
+    for (entity, trans) in query.iter() {
+        if !overlay_state.trails.includes(entity, selected) {
+            continue;
+        }
+        // trails of despawned or no longer included boids are dropped
+        let mut trail = trails.positions.remove(&entity).unwrap_or_default();
+        trail.push_back(trans.translation.truncate());
+        while trail.len() > overlay_state.trail_length {
+            trail.pop_front();
+        }
+        positions.insert(entity, trail);
+    }
+    trails.positions = positions;
+}
+
+/// trail segments fade out with age
+fn trail_overlay(
+    mut cmd: Commands,
+    overlay_state: Res<OverlayState>,
+    trails: Res<Trails>,
+    query: Query<&Transform>,
+) {
+    if overlay_state.trails == OverlayScope::Off {
+        return;
+    }
+
+    for (entity, trail) in trails.positions.iter() {
+        // join the last recorded point up with where the boid is now
+        let current = query
+            .get(*entity)
+            .map(|trans| trans.translation.truncate())
+            .ok();
+        let points: Vec<Vec2> = trail.iter().copied().chain(current).collect();
+        let count = points.len();
+        for (i, segment) in points.windows(2).enumerate() {
+            let alpha = (i + 1) as f32 / count as f32;
+            cmd.spawn_bundle(GeometryBuilder::build_as(
+                &shapes::Line(segment[0], segment[1]),
+                DrawMode::Stroke(StrokeMode::new(Color::rgba(1., 1., 1., alpha * 0.8), 0.4)),
+                Transform::from_xyz(0., 0., OVERLAY_Z),
+            ))
+            .insert(DebugLine);
+        }
+    }
+}
+
+fn overlays_controller(keys: Res<Input<KeyCode>>, mut overlay_state: ResMut<OverlayState>) {
+    if keys.just_pressed(KeyCode::O) {
+        overlay_state.show = !overlay_state.show;
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{Color32, Grid, ScrollArea, Slider, Window},
+    EguiContext,
+};
+
+use super::Selected;
+use crate::{
+    ai::{
+        hunger::{ForceHunt, Hunger},
+        movement::{BoidParams, ParamTarget, SetBoidParamsEvent},
+    },
+    camera::{CameraFollow, CameraFollowPod, MainCamera},
+    orca::{Orca, PodId, PodPool},
+};
+
+pub struct PodsState {
+    pub show: bool,
+    /// pod whose members and edits are shown
+    pub expanded: Option<PodId>,
+    pub params: BoidParams,
+    pub hunger: f32,
+}
+
+impl Default for PodsState {
+    fn default() -> Self {
+        Self {
+            show: false,
+            expanded: None,
+            params: BoidParams::default(),
+            hunger: 0.5,
+        }
+    }
+}
+
+pub struct PodsPlugin;
+
+impl Plugin for PodsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PodsState::default())
+            .add_system(render_pods)
+            .add_system(pods_controller);
+    }
+}
+
+/// members of a pod gathered from the orcas themselves
+#[derive(Default)]
+struct PodSummary {
+    members: Vec<(Entity, String)>,
+    hunger: f32,
+    centroid: Vec2,
+}
+
+fn render_pods(
+    mut cmd: Commands,
+    mut ctx: ResMut<EguiContext>,
+    mut pods_state: ResMut<PodsState>,
+    mut pod_pool: ResMut<PodPool>,
+    mut orca_query: Query<(Entity, &Orca, &mut Hunger, &Transform), Without<MainCamera>>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+    mut set_params_writer: EventWriter<SetBoidParamsEvent>,
+) {
+    if !pods_state.show {
+        return;
+    }
+
+    let mut summaries: HashMap<PodId, PodSummary> = HashMap::new();
+    for (entity, orca, hunger, trans) in orca_query.iter() {
+        if let Some(pod_id) = orca.pod_id {
+            let summary = summaries.entry(pod_id).or_default();
+            summary.members.push((entity, orca.name.clone()));
+            summary.hunger += hunger.0;
+            summary.centroid += trans.translation.truncate();
+        }
+    }
+    for summary in summaries.values_mut() {
+        let count = summary.members.len() as f32;
+        summary.hunger /= count;
+        summary.centroid /= count;
+    }
+
+    let mut pod_ids: Vec<PodId> = pod_pool.keys().copied().collect();
+    pod_ids.sort_unstable();
+    let empty = PodSummary::default();
+
+    Window::new("Pods")
+        .default_width(400.)
+        .show(ctx.ctx_mut(), |ui| {
+            ScrollArea::vertical().max_height(250.).show(ui, |ui| {
+                Grid::new("pods").striped(true).show(ui, |ui| {
+                    for name in ["", "pod", "ecotype", "members", "hunger", "centroid"] {
+                        ui.label(name);
+                    }
+                    ui.end_row();
+
+                    for pod_id in pod_ids.iter() {
+                        let pod = &pod_pool[pod_id];
+                        let summary = summaries.get(pod_id).unwrap_or(&empty);
+                        let [r, g, b, _] = pod.color.as_rgba_f32();
+                        let swatch =
+                            Color32::from_rgb((r * 255.) as u8, (g * 255.) as u8, (b * 255.) as u8);
+
+                        ui.colored_label(swatch, "■");
+                        let expanded = pods_state.expanded == Some(*pod_id);
+                        if ui.selectable_label(expanded, &pod.name).clicked() {
+                            pods_state.expanded = if expanded { None } else { Some(*pod_id) };
+                            // look at the pod rather than whichever orca was followed
+                            if !expanded && !summary.members.is_empty() {
+                                cmd.remove_resource::<CameraFollow>();
+                                cmd.remove_resource::<CameraFollowPod>();
+                                if let Ok(mut camera_trans) = camera_query.get_single_mut() {
+                                    camera_trans.translation =
+                                        summary.centroid.extend(camera_trans.translation.z);
+                                }
+                            }
+                        }
+                        ui.label(pod.orca_type.to_string());
+                        ui.label(summary.members.len().to_string());
+                        ui.label(format!("{:.2}", summary.hunger));
+                        ui.label(format!(
+                            "({:.0}, {:.0})",
+                            summary.centroid.x, summary.centroid.y
+                        ));
+                        ui.end_row();
+                    }
+                });
+            });
+
+            let pod_id = match pods_state.expanded {
+                Some(pod_id) if pod_pool.contains_key(&pod_id) => pod_id,
+                _ => return,
+            };
+            let summary = summaries.get(&pod_id).unwrap_or(&empty);
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("name");
+                if let Some(pod) = pod_pool.get_mut(&pod_id) {
+                    ui.text_edit_singleline(&mut pod.name);
+                }
+                if ui.button("Follow").clicked() {
+                    cmd.remove_resource::<CameraFollow>();
+                    cmd.insert_resource(CameraFollowPod(pod_id));
+                }
+            });
+
+            ui.collapsing(format!("Members ({})", summary.members.len()), |ui| {
+                ScrollArea::vertical()
+                    .id_source("pod_members")
+                    .max_height(150.)
+                    .show(ui, |ui| {
+                        for (entity, name) in summary.members.iter() {
+                            if ui.button(name).clicked() {
+                                cmd.insert_resource(Selected(*entity));
+                                cmd.insert_resource(CameraFollow(*entity));
+                            }
+                        }
+                    });
+            });
+
+            // edits that apply to every member at once
+            ui.collapsing("Boid Params", |ui| {
+                let params = &mut pods_state.params;
+                ui.add(Slider::new(&mut params.coherence, 0.0f32..=10.).text("Coherence"));
+                ui.add(Slider::new(&mut params.alignment, 0.0f32..=10.).text("Alignment"));
+                ui.add(Slider::new(&mut params.seperation, 0.0f32..=10.).text("Seperation"));
+                ui.add(Slider::new(&mut params.randomness, 0.0f32..=10.).text("Randomness"));
+                ui.add(Slider::new(&mut params.view_range, 0.0f32..=500.).text("View Range"));
+                ui.add(Slider::new(&mut params.view_angle, 0.0f32..=180.).text("View Angle"));
+                ui.add(
+                    Slider::new(&mut params.peripheral_falloff, 0.0f32..=1.)
+                        .text("Peripheral Falloff"),
+                );
+                if ui.button("Apply to Pod").clicked() {
+                    set_params_writer.send(SetBoidParamsEvent {
+                        params: *params,
+                        target: ParamTarget::Pod(pod_id),
+                    });
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.add(Slider::new(&mut pods_state.hunger, 0.0f32..=1.).text("Hunger"));
+                if ui.button("Set").clicked() {
+                    for (entity, _) in summary.members.iter() {
+                        if let Ok((_, _, mut hunger, _)) = orca_query.get_mut(*entity) {
+                            hunger.0 = pods_state.hunger;
+                        }
+                    }
+                }
+            });
+            if ui.button("Force Hunt").clicked() {
+                for (entity, _) in summary.members.iter() {
+                    cmd.entity(*entity).insert(ForceHunt);
+                }
+            }
+        });
+}
+
+fn pods_controller(keys: Res<Input<KeyCode>>, mut pods_state: ResMut<PodsState>) {
+    if keys.just_pressed(KeyCode::P) {
+        pods_state.show = !pods_state.show;
+    }
+}
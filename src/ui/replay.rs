@@ -0,0 +1,230 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{Grid, ScrollArea, Slider, Window},
+    EguiContext,
+};
+
+use super::Selected;
+use crate::{
+    camera::CameraFollow,
+    replay::{
+        LoadReplayEvent, Playback, ReplayBody, ReplayEventKind, ReplayRecorder,
+        StartReplayRecordingEvent, StopPlaybackEvent, StopReplayRecordingEvent,
+        DEFAULT_REPLAY_INTERVAL,
+    },
+};
+
+/// seconds of lead-up shown before an event that is jumped to
+const EVENT_LEAD_UP: f32 = 2.;
+
+pub struct ReplayUIState {
+    pub show: bool,
+    pub record_path: String,
+    pub record_interval: f32,
+    pub load_path: String,
+}
+
+impl Default for ReplayUIState {
+    fn default() -> Self {
+        Self {
+            show: false,
+            record_path: String::from("run.replay"),
+            record_interval: DEFAULT_REPLAY_INTERVAL,
+            load_path: String::from("run.replay"),
+        }
+    }
+}
+
+pub struct ReplayUIPlugin;
+
+impl Plugin for ReplayUIPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ReplayUIState::default())
+            .add_system(render_replay)
+            .add_system(replay_controller);
+    }
+}
+
+fn render_replay(
+    mut cmd: Commands,
+    mut ctx: ResMut<EguiContext>,
+    mut replay_ui_state: ResMut<ReplayUIState>,
+    recorder: Option<Res<ReplayRecorder>>,
+    playback: Option<ResMut<Playback>>,
+    selected: Option<Res<Selected>>,
+    body_query: Query<&ReplayBody>,
+    mut start_recording_writer: EventWriter<StartReplayRecordingEvent>,
+    mut stop_recording_writer: EventWriter<StopReplayRecordingEvent>,
+    mut load_writer: EventWriter<LoadReplayEvent>,
+    mut stop_playback_writer: EventWriter<StopPlaybackEvent>,
+) {
+    if !replay_ui_state.show {
+        return;
+    }
+
+    Window::new("Replay")
+        .default_width(300.)
+        .show(ctx.ctx_mut(), |ui| {
+            ui.label("Recording");
+            match &recorder {
+                Some(recorder) => {
+                    ui.label(format!(
+                        "recording to {} ({} frames)",
+                        recorder.path.display(),
+                        recorder.replay.frames.len()
+                    ));
+                    if ui.button("Stop and Save").clicked() {
+                        stop_recording_writer.send(StopReplayRecordingEvent);
+                    }
+                },
+                None => {
+                    ui.text_edit_singleline(&mut replay_ui_state.record_path);
+                    ui.add(
+                        Slider::new(&mut replay_ui_state.record_interval, 0.02f32..=1.)
+                            .text("Frame Interval"),
+                    );
+                    if ui.button("Start Recording").clicked() {
+                        start_recording_writer.send(StartReplayRecordingEvent {
+                            path: PathBuf::from(&replay_ui_state.record_path),
+                            interval: replay_ui_state.record_interval,
+                        });
+                    }
+                },
+            }
+
+            ui.separator();
+            ui.label("Playback");
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut replay_ui_state.load_path);
+                if ui.button("Load").clicked() {
+                    load_writer.send(LoadReplayEvent(PathBuf::from(&replay_ui_state.load_path)));
+                }
+            });
+
+            let mut playback = match playback {
+                Some(playback) => playback,
+                None => return,
+            };
+            let duration = playback.replay.duration();
+
+            ui.label(format!("playing {}", playback.path.display()));
+            ui.horizontal(|ui| {
+                if ui.button("|<").clicked() {
+                    playback.playing = false;
+                    playback.step(-1);
+                }
+                let label = if playback.playing { "Pause" } else { "Play" };
+                if ui.button(label).clicked() {
+                    // playing from the end starts over
+                    if !playback.playing && playback.time >= duration {
+                        playback.seek(0.);
+                    }
+                    playback.playing = !playback.playing;
+                }
+                if ui.button(">|").clicked() {
+                    playback.playing = false;
+                    playback.step(1);
+                }
+                if ui.button("Stop Playback").clicked() {
+                    stop_playback_writer.send(StopPlaybackEvent);
+                }
+            });
+
+            let mut time = playback.time;
+            if ui
+                .add(Slider::new(&mut time, 0.0f32..=duration).text("Time"))
+                .changed()
+            {
+                playback.seek(time);
+            }
+            ui.add(
+                Slider::new(&mut playback.speed, 0.1f32..=20.)
+                    .logarithmic(true)
+                    .text("Speed"),
+            );
+
+            // jump to the lead-up of a kill and follow the predator
+            ui.collapsing("Kills", |ui| {
+                ScrollArea::vertical().max_height(150.).show(ui, |ui| {
+                    let kills: Vec<_> = playback
+                        .replay
+                        .events
+                        .iter()
+                        .filter(|event| event.kind == ReplayEventKind::Kill)
+                        .cloned()
+                        .collect();
+                    for kill in kills {
+                        let name = playback
+                            .replay
+                            .orcas
+                            .get(&kill.entity)
+                            .map(|info| info.name.clone())
+                            .unwrap_or_default();
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{:.1}s {}", kill.time, name));
+                            if ui.button("Go").clicked() {
+                                playback.playing = false;
+                                playback.seek(kill.time - EVENT_LEAD_UP);
+                                if let Some(entity) = playback.entity(kill.entity) {
+                                    cmd.insert_resource(Selected(entity));
+                                    cmd.insert_resource(CameraFollow(entity));
+                                }
+                            }
+                        });
+                    }
+                });
+            });
+
+            // recorded state of whichever stand-in is selected
+            let id = match selected
+                .as_ref()
+                .and_then(|selected| body_query.get(selected.0).ok())
+            {
+                Some(ReplayBody(id)) => *id,
+                None => return,
+            };
+            let body = match playback.body(id) {
+                Some(body) => body,
+                None => return,
+            };
+            ui.separator();
+            ui.heading("Inspector");
+            Grid::new("replay_inspector").show(ui, |ui| {
+                let mut row = |name: &str, value: String| {
+                    ui.label(name);
+                    ui.label(value);
+                    ui.end_row();
+                };
+                if let Some(pod) = body
+                    .pod
+                    .and_then(|pod_id| playback.replay.pods.get(&pod_id))
+                {
+                    row("pod", pod.name.clone());
+                }
+                if let Some(info) = playback.replay.orcas.get(&id) {
+                    row("name", info.name.clone());
+                    row("gender", info.gender.clone());
+                    row("age", format!("{} years", info.age));
+                    row("mass", format!("{} kg", info.mass));
+                    row("type", info.orca_type.clone());
+                }
+                if let Some(hunger) = body.hunger {
+                    row("hunger", format!("{:.2}", hunger));
+                }
+                row("depth", format!("{} m", body.depth.round()));
+                row(
+                    "speed",
+                    format!("{:.1}", Vec2::from(body.velocity).length()),
+                );
+                row("activity", body.activity.name().to_string());
+            });
+        });
+}
+
+fn replay_controller(keys: Res<Input<KeyCode>>, mut replay_ui_state: ResMut<ReplayUIState>) {
+    if keys.just_pressed(KeyCode::R) {
+        replay_ui_state.show = !replay_ui_state.show;
+    }
+}
@@ -0,0 +1,272 @@
+use bevy::prelude::*;
+use bevy_bobs::physics_2d::RigidBody;
+use bevy_mod_picking::PickableBundle;
+use bevy_prototype_lyon::prelude::*;
+use pino_utils::enum_string;
+
+use crate::{
+    orca::Orca,
+    sim::{RunSimEvent, Simulation, SystemRng},
+};
+
+/// distance at which a noise source is heard at half its source level
+const NOISE_REFERENCE_RANGE: f32 = 50.;
+/// distance at which orcas start swimming away from vessels
+const VESSEL_AVOID_RANGE: f32 = 60.;
+const VESSEL_AVOIDANCE: f32 = 3.;
+/// distance whale watching boats try to keep from the orca they are following
+const WHALE_WATCH_STANDOFF: f32 = 40.;
+/// half length of a shipping lane
+const LANE_EXTENT: f32 = 600.;
+const WAYPOINT_RANGE: f32 = 5.;
+
+#[enum_string]
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum VesselKind {
+    Cargo,
+    WhaleWatching,
+}
+
+#[derive(Component)]
+pub struct Vessel {
+    pub kind: VesselKind,
+    /// speed in world units per second
+    pub speed: f32,
+    /// source level of the noise emitted
+    pub noise: f32,
+    /// waypoints of a shipping lane, or the home port of a whale watching boat
+    pub route: Vec<Vec2>,
+    pub waypoint: usize,
+}
+
+/// noise emitted by all vessels, sampled by orcas
+#[derive(Default)]
+pub struct NoiseField {
+    pub sources: Vec<(Vec2, f32)>,
+}
+
+impl NoiseField {
+    /// received noise level at a world position
+    pub fn level_at(&self, pos: Vec2) -> f32 {
+        self.sources
+            .iter()
+            .map(|(source, level)| {
+                let d = pos.distance(*source) / NOISE_REFERENCE_RANGE;
+                level / (1. + d * d)
+            })
+            .sum()
+    }
+
+    /// fraction of the normal detection range left once noise masks sounds from prey
+    pub fn detection_factor(&self, pos: Vec2) -> f32 {
+        1. / (1. + self.level_at(pos))
+    }
+}
+
+pub struct VesselPlugin;
+
+impl Plugin for VesselPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(NoiseField::default())
+            .add_system(run_sim_vessels)
+            .add_system(cargo_movement)
+            .add_system(whale_watching_movement)
+            .add_system(update_noise_field)
+            .add_system(noise_exposure)
+            .add_system(avoid_vessels);
+    }
+}
+
+fn run_sim_vessels(
+    mut cmd: Commands,
+    query: Query<Entity, With<Vessel>>,
+    mut events: EventReader<RunSimEvent>,
+    mut rng: Local<SystemRng>,
+) {
+    use std::f32::consts::PI;
+
+    use rand::Rng;
+
+    for event in events.iter() {
+        for entity in &query {
+            cmd.entity(entity).despawn_recursive();
+        }
+
+        if !event.enable_vessels {
+            continue;
+        }
+
+        // cargo ships go back and forth along straight shipping lanes
+        for _ in 0..event.cargo_count {
+            let angle = rng.gen_range(0. ..PI);
+            let offset = rng.gen_range(-200. ..200.);
+            let dir = Vec2::new(angle.cos(), angle.sin());
+            let normal = dir.perp() * offset;
+            let route = vec![normal - dir * LANE_EXTENT, normal + dir * LANE_EXTENT];
+            let start = route[0].lerp(route[1], rng.gen_range(0. ..1.));
+
+            spawn_vessel(
+                &mut cmd,
+                Vessel {
+                    kind: VesselKind::Cargo,
+                    speed: 8.,
+                    noise: event.cargo_noise,
+                    route,
+                    waypoint: 1,
+                },
+                start,
+                Color::DARK_GRAY,
+            );
+        }
+
+        // whale watching boats leave from a port on the edge of the map
+        for _ in 0..event.whale_watch_count {
+            let angle = rng.gen_range(0. ..2. * PI);
+            let port = Vec2::new(angle.cos(), angle.sin()) * LANE_EXTENT / 2.;
+
+            spawn_vessel(
+                &mut cmd,
+                Vessel {
+                    kind: VesselKind::WhaleWatching,
+                    speed: 15.,
+                    noise: event.whale_watch_noise,
+                    route: vec![port],
+                    waypoint: 0,
+                },
+                port,
+                Color::WHITE,
+            );
+        }
+    }
+}
+
+fn spawn_vessel(cmd: &mut Commands, vessel: Vessel, position: Vec2, color: Color) {
+    let noise = vessel.noise;
+    cmd.spawn()
+        .insert(vessel)
+        .insert_bundle(GeometryBuilder::build_as(
+            &shapes::Rectangle {
+                extents: Vec2::new(12., 4.),
+                ..default()
+            },
+            DrawMode::Outlined {
+                fill_mode: FillMode::color(color),
+                outline_mode: StrokeMode::new(Color::BLACK, 0.5),
+            },
+            Transform::from_translation(position.extend(2.)),
+        ))
+        .insert_bundle(PickableBundle::default())
+        .with_children(|parent| {
+            // ring showing how far the noise carries
+            parent.spawn_bundle(GeometryBuilder::build_as(
+                &shapes::Circle {
+                    radius: NOISE_REFERENCE_RANGE * noise.sqrt(),
+                    ..default()
+                },
+                DrawMode::Stroke(StrokeMode::new(Color::rgba(1., 1., 1., 0.3), 0.5)),
+                Transform::default(),
+            ));
+        });
+}
+
+fn steer_towards(trans: &mut Transform, target: Vec2, speed: f32, dt: f32) {
+    let diff = target - trans.translation.truncate();
+    let step = diff.clamp_length_max(speed * dt);
+    trans.translation += step.extend(0.);
+    if diff.length_squared() > 0. {
+        trans.rotation = Quat::from_rotation_z(diff.y.atan2(diff.x));
+    }
+}
+
+fn cargo_movement(time: Res<Time>, mut query: Query<(&mut Transform, &mut Vessel)>) {
+    let dt = time.delta_seconds();
+    for (mut trans, mut vessel) in query.iter_mut() {
+        if vessel.kind != VesselKind::Cargo || vessel.route.is_empty() {
+            continue;
+        }
+
+        let target = vessel.route[vessel.waypoint];
+        if trans.translation.truncate().distance(target) < WAYPOINT_RANGE {
+            vessel.waypoint = (vessel.waypoint + 1) % vessel.route.len();
+        }
+        steer_towards(&mut trans, target, vessel.speed, dt);
+    }
+}
+
+/// whale watchers head for the closest orca and keep their distance, going home if none are left
+fn whale_watching_movement(
+    time: Res<Time>,
+    mut query: Query<(&mut Transform, &Vessel), Without<Orca>>,
+    orca_query: Query<&Transform, With<Orca>>,
+) {
+    let dt = time.delta_seconds();
+    for (mut trans, vessel) in query.iter_mut() {
+        if vessel.kind != VesselKind::WhaleWatching {
+            continue;
+        }
+
+        let pos = trans.translation.truncate();
+        let closest = orca_query
+            .iter()
+            .map(|orca_trans| orca_trans.translation.truncate())
+            .min_by(|a, b| a.distance_squared(pos).total_cmp(&b.distance_squared(pos)));
+
+        match closest {
+            Some(orca_pos) => {
+                if pos.distance(orca_pos) > WHALE_WATCH_STANDOFF {
+                    let target =
+                        orca_pos + (pos - orca_pos).normalize_or_zero() * WHALE_WATCH_STANDOFF;
+                    steer_towards(&mut trans, target, vessel.speed, dt);
+                }
+            },
+            None => {
+                if let Some(port) = vessel.route.first() {
+                    steer_towards(&mut trans, *port, vessel.speed, dt);
+                }
+            },
+        }
+    }
+}
+
+fn update_noise_field(mut noise_field: ResMut<NoiseField>, query: Query<(&Transform, &Vessel)>) {
+    noise_field.sources = query
+        .iter()
+        .map(|(trans, vessel)| (trans.translation.truncate(), vessel.noise))
+        .collect();
+}
+
+fn noise_exposure(
+    noise_field: Res<NoiseField>,
+    query: Query<&Transform, With<Orca>>,
+    mut sim: ResMut<Simulation>,
+) {
+    let count = query.iter().len();
+    sim.mean_noise = if count > 0 {
+        query
+            .iter()
+            .map(|trans| noise_field.level_at(trans.translation.truncate()))
+            .sum::<f32>()
+            / count as f32
+    } else {
+        0.
+    };
+}
+
+fn avoid_vessels(
+    time: Res<Time>,
+    vessel_query: Query<&Transform, With<Vessel>>,
+    mut orca_query: Query<(&Transform, &mut RigidBody), With<Orca>>,
+) {
+    for (trans, mut rb) in orca_query.iter_mut() {
+        let pos = trans.translation.truncate();
+        let mut force = Vec2::ZERO;
+        for vessel_trans in &vessel_query {
+            let diff = pos - vessel_trans.translation.truncate();
+            let dist = diff.length();
+            if dist < VESSEL_AVOID_RANGE {
+                force += diff.normalize_or_zero() * (1. - dist / VESSEL_AVOID_RANGE);
+            }
+        }
+        rb.force += force * VESSEL_AVOIDANCE * 1000. * time.delta_seconds();
+    }
+}